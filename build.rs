@@ -0,0 +1,32 @@
+use std::env;
+
+/// Code shared by several command modules is compiled in when one of them is, under a cfg of its
+/// own so the list of features is only written down here.
+const FEATURE_GROUPS: &[(&str, &[&str])] = &[
+    // Put actions on cooldown
+    (
+        "uses_cooldowns",
+        &["ask", "colors", "dino", "duel", "itad", "rpg"],
+    ),
+    // Show the nickname of the users they mention
+    ("uses_names", &["dino", "duel", "rpg", "sudoku", "wallet"]),
+    // Listen to button presses in the background
+    ("uses_collectors", &["dino", "rpg"]),
+];
+
+fn main() {
+    // Rebuild when a migration is added, since they are embedded with `sqlx::migrate!`
+    println!("cargo:rerun-if-changed=migrations");
+
+    for (group, features) in FEATURE_GROUPS {
+        println!("cargo::rustc-check-cfg=cfg({group})");
+
+        let enabled = features.iter().any(|feature| {
+            let variable = format!("CARGO_FEATURE_{}", feature.to_uppercase());
+            env::var_os(variable).is_some()
+        });
+        if enabled {
+            println!("cargo::rustc-cfg={group}");
+        }
+    }
+}
@@ -0,0 +1,7 @@
+# /dino, $attempt is how many eggs in a row failed to hatch, counting this one
+
+dino-hatch-failed = You failed to hatch the egg ({ $attempt ->
+        [1] 1st
+        [2] 2nd
+       *[3] 3rd
+    } attempt), better luck next time.
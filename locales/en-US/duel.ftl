@@ -0,0 +1,9 @@
+# /duel, $challenger and $accepter are mentions of the two duelists
+
+duel-looking = { $challenger } is looking for a duel, press the button to accept.
+duel-no-opponent = { $challenger } failed to find someone to duel.
+duel-own-challenge = You cannot join your own duel.
+duel-taken = Someone beat you to the challenge already
+duel-won = { $winner } has won!
+duel-draw = It's a draw! Now go sit in a corner for 10 mintues and think about your actions...
+duel-result = { $accepter } has rolled a { $accepter_score } and { $challenger } has rolled a { $challenger_score }. { $outcome }
@@ -0,0 +1,22 @@
+# The answers of /eightball, one is picked at random
+eightball-reply =
+    .v1 = It is certain.
+    .v2 = It is decidedly so.
+    .v3 = Without a doubt.
+    .v4 = Yes definitely.
+    .v5 = You may rely on it.
+    .v6 = As I see it, yes.
+    .v7 = Most likely.
+    .v8 = Outlook good.
+    .v9 = Yes.
+    .v10 = Signs point to yes.
+    .v11 = Reply hazy, try again.
+    .v12 = Ask again later.
+    .v13 = Better not tell you now.
+    .v14 = Cannot predict now.
+    .v15 = Concentrate and ask again.
+    .v16 = Don't count on it.
+    .v17 = My reply is no.
+    .v18 = My sources say no.
+    .v19 = Outlook not so good.
+    .v20 = Very doubtful.
@@ -0,0 +1,260 @@
+# The fight log of /rpg challenge. A turn is an attack text followed by the defender's answer,
+# picked at random from the attributes of the message for the stats they used.
+# $attacker and $defender are the names with the hit points left, $damage is a number.
+
+## Attacks
+
+rpg-attack-str =
+    .v1 = { $attacker } swings a wild haymaker at { $defender },
+    .v2 = { $attacker } throws a punch at { $defender },
+    .v3 = { $attacker } goes in for the bear hug,
+    .v4 = { $attacker } tries to crush { $defender } like a bug,
+    .v5 = { $attacker } hurls a boulder at { $defender },
+    .v6 = { $attacker } advances menacingly,
+    .v7 = { $attacker } does a shoryuken,
+    .v8 = { $attacker } tries to bonk { $defender } on the noggin,
+    .v9 = { $attacker } yeets { $defender },
+
+rpg-attack-dex =
+    .v1 = { $attacker } lunges at { $defender },
+    .v2 = { $attacker } darts in with an attack,
+    .v3 = { $attacker } throws a rock,
+    .v4 = { $attacker } unleashes a flurry of blows
+    .v5 = { $attacker } sneaks up on { $defender },
+    .v6 = { $attacker } shoots an arrow at { $defender },
+    .v7 = { $attacker } begins the 5 point exploding heart technique,
+    .v8 = { $attacker } pulls off a special move,
+    .v9 = { $attacker } starts throwing hands,
+
+rpg-attack-con =
+    .v1 = { $attacker } flexes at { $defender },
+    .v2 = { $attacker } bull-charges { $defender },
+    .v3 = { $attacker } challenges { $defender } to a drinking contest,
+    .v4 = { $attacker } body slams { $defender },
+    .v5 = { $attacker } shows off their hot bod,
+    .v6 = { $attacker } winks at { $defender },
+    .v7 = { $attacker } starts throwing shapes,
+
+rpg-attack-int =
+    .v1 = { $attacker } throws a fireball at { $defender },
+    .v2 = { $attacker } unleashes a psychic assault,
+    .v3 = { $attacker } plays a face-down card and ends their turn,
+    .v4 = { $attacker } outsmarts { $defender },
+    .v5 = { $attacker } points their finger of death at { $defender },
+    .v6 = { $attacker } reads the dictionary at { $defender },
+    .v7 = { $attacker } throws a spirit bomb at { $defender },
+
+rpg-attack-wis =
+    .v1 = { $attacker } calls on a higher power to smite { $defender },
+    .v2 = { $attacker } orders their animal companion to attack,
+    .v3 = { $attacker } believes in themself,
+    .v4 = { $attacker } springs an ambush,
+    .v5 = { $attacker } enacts a cunning plan,
+    .v6 = { $attacker } appeals to { $defender }'s better nature,
+    .v7 = { $attacker } casts turn undead,
+    .v8 = { $attacker } stands in contemplation,
+
+rpg-attack-chr =
+    .v1 = { $attacker } says mean things about { $defender },
+    .v2 = { $attacker } cancels { $defender } on Twitter,
+    .v3 = { $attacker } bombards { $defender } with discord pings,
+    .v4 = { $attacker } starts the crowd chanting,
+    .v5 = { $attacker } drops a truth bomb on { $defender },
+    .v6 = { $attacker } taunts { $defender },
+    .v7 = { $attacker } reads { $defender } their rights,
+    .v8 = { $attacker } uses "good" as an adverb,
+
+## Defences that hold
+
+rpg-defence-success-str =
+    .v1 = but { $defender } pushes them over.
+    .v2 = but { $defender } simply flexes.
+    .v3 = but it glances off { $defender }'s washboard abs.
+    .v4 = but { $defender } is a force of nature.
+    .v5 = but { $defender } is having none of it.
+    .v6 = but { $defender } is too strong.
+    .v7 = but { $defender } is too stacked.
+    .v8 = but { $defender } is built like a brick shithouse.
+
+rpg-defence-success-dex =
+    .v1 = but { $defender } dodges the attack.
+    .v2 = but { $defender } is nowhere to be seen!
+    .v3 = but { $defender } is somewhere else.
+    .v4 = { $defender } parries!
+    .v5 = { $defender } counters with pocket sand!
+    .v6 = but { $defender } narrowly avoids it.
+    .v7 = but { $defender } sidesteps.
+
+rpg-defence-success-con =
+    .v1 = but { $defender } stands impervious.
+    .v2 = but { $defender } hardly notices.
+    .v3 = but { $defender } ignores it.
+    .v4 = but { $defender } isn't affected.
+    .v5 = but { $defender } is built of sterner stuff.
+    .v6 = it's not very effective.
+    .v7 = { $defender } takes it on the chin.
+    .v8 = { $defender } just blinks.
+    .v9 = but { $defender } goes super saiyan!
+
+rpg-defence-success-int =
+    .v1 = but { $defender } reads them like a book.
+    .v2 = but { $defender } uses their brain wrinkles to counter.
+    .v3 = but { $defender } teleports away.
+    .v4 = but { $defender } casts stoneskin for extra armor.
+    .v5 = but { $defender } knows better.
+    .v6 = but { $defender } shouts COUNTERSPELL!
+    .v7 = but { $defender } outsmarts them.
+    .v8 = but { $defender } is one step ahead.
+
+rpg-defence-success-wis =
+    .v1 = but { $defender } is protected by divine light.
+    .v2 = but { $defender } is saved by their animal companion.
+    .v3 = but { $defender } doesn't believe in damage.
+    .v4 = but { $defender } has other ideas.
+    .v5 = but { $defender } already prepared for that.
+    .v6 = but { $defender } has other plans.
+    .v7 = but { $defender } is destined for greater things.
+    .v8 = but { $defender } just turns the other cheek.
+    .v9 = { $defender } meditates through the attack.
+
+rpg-defence-success-chr =
+    .v1 = but { $defender } just laughs, unnerving { $attacker }.
+    .v2 = but { $defender } convinces them it's a bad idea.
+    .v3 = but { $defender } talks them out of it.
+    .v4 = but { $defender } distracts them.
+    .v5 = but { $defender } just cracks wise.
+    .v6 = but { $defender } just shouts them down.
+    .v7 = but { $defender } talks their way out of it.
+    .v8 = but { $defender } is too pretty.
+    .v9 = but { $defender } gets the crowd on their side.
+
+## Defences that fail
+
+rpg-defence-failure-str =
+    .v1 = and { $defender }'s strength fails, taking { $damage } damage.
+    .v2 = and { $defender } can't resist the { $damage } damage.
+    .v3 = and { $defender } is too weak to prevent the { $damage } damage.
+    .v4 = overpowering { $defender }'s defence inflicting { $damage } damage.
+    .v5 = and { $defender } can't quite get the upper hand. { $damage } damage.
+    .v6 = and { $defender } can't push through. { $damage } damage.
+    .v7 = { $defender }'s muscles aren't big enough to avoid the { $damage } damage.
+
+rpg-defence-failure-dex =
+    .v1 = and { $defender } is too slow to get out the way, eating { $damage } damage.
+    .v2 = { $defender } fails to dodge. { $damage } damage done.
+    .v3 = { $defender } didn't react in time and takes { $damage } damage.
+    .v4 = { $defender } stumbles and takes the full { $damage } damage.
+    .v5 = and { $defender } gets the parry timing wrong, taking { $damage } damage.
+    .v6 = { $defender } takes { $damage } damage and blames lag.
+    .v7 = { $defender } walks right into the { $damage } damage.
+    .v8 = { $defender }'s fancy footwork isn't enough. { $damage } damage.
+
+rpg-defence-failure-con =
+    .v1 = and { $defender } takes the full { $damage } damage.
+    .v2 = and { $defender } blocks it with their face taking { $damage } damage.
+    .v3 = and { $defender } can't resist the { $damage } damage.
+    .v4 = { $defender } is left with { $damage } fewer hit points.
+    .v5 = and { $defender } isn't tough enough to resist the { $damage } damage.
+    .v6 = and { $defender } isn't tough enough to ignore { $damage } damage.
+    .v7 = { $defender }'s is less healthy after the { $damage } damage.
+
+rpg-defence-failure-int =
+    .v1 = and { $defender } reacts poorly suffering { $damage } damage.
+    .v2 = and { $defender } has a smooth brain moment resulting in { $damage } damage.
+    .v3 = and { $defender } didn't see the { $damage } damage coming.
+    .v4 = and { $defender }'s counterspell fizzles, taking { $damage } damage.
+    .v5 = { $defender } forgot the words to their spell and takes { $damage } damage.
+    .v6 = { $defender } doesn't know what hit them. { $damage } damage.
+    .v7 = and { $defender } can't think of a solution to the { $damage } damage.
+    .v8 = { $defender } hurt themself in confusion for { $damage } damage.
+
+rpg-defence-failure-wis =
+    .v1 = and { $defender }'s power abandons them, taking { $damage } damage.
+    .v2 = and { $defender } wasn't prepared for that, taking { $damage } damage.
+    .v3 = and { $defender } didn't expect it. { $damage } damage done.
+    .v4 = and { $defender }'s faith falters suffering { $damage } damage.
+    .v5 = { $defender } turns the other cheek. It gets hit for { $damage } damage.
+    .v6 = { $defender } is caught off guard, suffering { $damage } damage.
+    .v7 = and { $defender } didn't try hard enough. { $damage } damage.
+    .v8 = and { $defender } can't come to accept it. { $damage } damage.
+
+rpg-defence-failure-chr =
+    .v1 = and { $defender }'s laughter is not the best medicine. { $damage } damage.
+    .v2 = and { $defender }'s talking doesn't stop the { $damage } damage.
+    .v3 = cutting { $defender } off mid sentence and inflicting { $damage } damage.
+    .v4 = interrupting { $defender }'s monologue and inflicting { $damage } damage.
+    .v5 = and { $defender } is left speechless. { $damage } damage.
+    .v6 = and { $defender } has no reply. { $damage } damage.
+    .v7 = and { $defender } is tongue-tied. { $damage } damage.
+    .v8 = and the { $damage } damage makes { $defender } cry.
+
+## How the fight ends, $victor and $loser are the names of the fighters
+
+rpg-victory-standard =
+    .v1 = { $loser } falls and { $victor } wins!
+    .v2 = { $loser } is smashed like a bowl of eggs. { $victor } wins!
+    .v3 = { $loser } taps out. { $victor } wins!
+    .v4 = Sucks to suck { $loser }, { $victor } wins!
+    .v5 = { $loser } can't go on, { $victor } wins!
+    .v6 = { $victor } stands victorious, { $loser } is left to lick their wounds.
+    .v7 = { $victor } wins! GG go next.
+    .v8 = { $victor } wins! GG no re.
+    .v9 = { $loser } faints. { $victor } jumps for joy!
+    .v10 = { $loser } can't take it any more, { $victor } wins!
+    .v11 = { $loser } is outplayed, { $victor } is the winner!
+    .v12 = Winner winner chicken dinner for { $victor }. { $loser } starves.
+    .v13 = { $victor } wins! { $loser } thinks the game is rigged!
+
+rpg-victory-perfect =
+    .v1 = { $victor } scores a perfect victory! { $loser } is shamed!
+    .v2 = { $victor } is untouchable! { $loser } never got a hit in.
+    .v3 = { $victor } must be hacking because { $loser } couldn't land a hit.
+    .v4 = FRAUD ALERT! { $victor } scores a perfect victory over { $loser }.
+    .v5 = { $victor } wins without breaking a sweat. Was { $loser } even trying?
+
+rpg-victory-close =
+    .v1 = { $victor } stands bloodied but victorious. { $loser } gave as good as they got!
+    .v2 = { $victor } scrapes by, narrowly defeating { $loser }.
+    .v3 = { $victor } wins over { $loser } by a hair.
+    .v4 = { $victor } and { $loser } are evenly matched, but { $victor } comes out ahead.
+    .v5 = A close one, but { $victor } wins.
+
+rpg-draw = After { $rounds } rounds they decide to call it a draw.
+
+## /rpg ladder, the value is the title of the field. $user is a mention and $score a number
+
+rpg-ladder-top = Top
+    .v1 = { $user } is the champion with { $score } LP
+    .v2 = { $user } is the big cheese with { $score } LP
+    .v3 = { $user } is top banana with { $score } LP
+    .v4 = { $user } is supreme ruler with { $score } LP
+    .v5 = { $user } is the coolest chatter with { $score } LP
+    .v6 = { $user } is the gout gamer with { $score } LP
+    .v7 = { $user } is based and RPG pilled with { $score } LP
+    .v8 = { $user } probably cheated with { $score } LP
+    .v9 = { $user } is the raid boss with { $score } LP
+    .v10 = { $user } is on top with { $score } LP
+
+rpg-ladder-tail = Tail
+    .v1 = { $user } is everyone's best friend with { $score } LP
+    .v2 = { $user } had their lunch money taken with { $score } LP
+    .v3 = { $user } has the best personality with { $score } LP
+    .v4 = { $user } is making the room brighter with { $score } LP
+    .v5 = { $user } can't seem to catch a break with { $score } LP
+    .v6 = { $user } is a sweet summer child with { $score } LP
+    .v7 = { $user } gave peace a chance with { $score } LP
+
+rpg-ladder-wins = Wins
+    .v1 = { $user } has the most bedpost notches with { $score } wins
+    .v2 = { $user } has the biggest tally with { $score } wins
+    .v3 = { $user } sits on a throne of skulls with { $score } wins
+    .v4 = { $user } has been winning a lot with { $score } wins
+
+rpg-ladder-losses = Losses
+    .v1 = { $user } has the worst luck with { $score } losses
+    .v2 = { $user } can't catch a break with { $score } losses
+    .v3 = { $user } needs to work on their technique with { $score } losses
+    .v4 = { $user } has found inner peace with { $score } losses
+    .v5 = { $user } will turn it around soon with { $score } losses
+    .v6 = { $user } is a victim of variance with { $score } losses
+    .v7 = { $user } has taken the most Ls with { $score } losses
@@ -0,0 +1,7 @@
+-- Add migration script here
+CREATE TABLE GuildSettings (
+    guild_id INTEGER NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (guild_id, key)
+);
@@ -0,0 +1,16 @@
+-- Add migration script here
+CREATE TABLE Cooldowns (
+    guild_id INTEGER NOT NULL,
+    -- 0 for cooldowns shared by the whole server
+    user_id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    expires_at DATETIME NOT NULL,
+    PRIMARY KEY (guild_id, user_id, action)
+);
+
+CREATE TABLE CooldownDurations (
+    guild_id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    seconds INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, action)
+);
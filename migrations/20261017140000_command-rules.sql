@@ -0,0 +1,11 @@
+-- Add migration script here
+CREATE TABLE CommandRules (
+    guild_id INTEGER NOT NULL,
+    command TEXT NOT NULL,
+    -- 'guild', 'channel' or 'role'
+    scope TEXT NOT NULL,
+    -- The channel or role ID, 0 for rules that apply to the whole server
+    target_id INTEGER NOT NULL,
+    allow BOOLEAN NOT NULL,
+    PRIMARY KEY (guild_id, command, scope, target_id)
+);
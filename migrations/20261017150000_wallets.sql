@@ -0,0 +1,20 @@
+-- Add migration script here
+CREATE TABLE Wallet (
+    user_id INTEGER NOT NULL PRIMARY KEY,
+    balance INTEGER NOT NULL DEFAULT 0 CHECK (balance >= 0)
+);
+
+CREATE TABLE Ledger (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    -- Negative for debits
+    amount INTEGER NOT NULL,
+    -- The balance right after this entry
+    balance INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    -- What the entry is about, e.g. the duel message or the hatched dino
+    reference_id TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_ledger_user ON Ledger(user_id, id);
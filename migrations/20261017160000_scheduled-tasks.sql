@@ -0,0 +1,16 @@
+-- Add migration script here
+CREATE TABLE ScheduledTasks (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    -- The whole job as JSON, `kind` is repeated so tasks can be looked up without parsing it
+    payload TEXT NOT NULL,
+    run_at DATETIME NOT NULL,
+    -- A cron-like schedule for recurring tasks, NULL for tasks that run once
+    recurrence TEXT,
+    -- Failed attempts since the last success
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_scheduled_tasks_run_at ON ScheduledTasks(run_at);
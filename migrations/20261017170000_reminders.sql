@@ -0,0 +1,15 @@
+-- Add migration script here
+CREATE TABLE Reminders (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    -- Where the reminder was set, it is posted there unless it goes by DM
+    channel_id INTEGER NOT NULL,
+    message TEXT NOT NULL,
+    remind_at DATETIME NOT NULL,
+    by_dm BOOLEAN NOT NULL,
+    -- The ScheduledTasks row that delivers it
+    task_id INTEGER,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_reminders_user ON Reminders(user_id, remind_at);
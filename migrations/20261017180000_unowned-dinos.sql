@@ -0,0 +1,3 @@
+-- Add migration script here
+-- Takes over the dinos of the users who asked to be forgotten
+INSERT OR IGNORE INTO DinoUser (id) VALUES ('0');
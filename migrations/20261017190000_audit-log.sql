@@ -0,0 +1,14 @@
+-- Add migration script here
+CREATE TABLE AuditLog (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    -- NULL for actions outside of a server
+    guild_id INTEGER,
+    -- Who did it, 0 once they asked to be forgotten
+    user_id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    -- The member it was done to, if any
+    target_id INTEGER,
+    details TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX idx_audit_log_guild ON AuditLog(guild_id, created_at);
@@ -0,0 +1,3 @@
+-- Add migration script here
+-- The image posted by the attachment commands, their content is the file name
+ALTER TABLE SimpleCommands ADD COLUMN attachment BLOB;
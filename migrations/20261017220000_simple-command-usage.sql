@@ -0,0 +1,15 @@
+-- Add migration script here
+-- Every use of a simple command, for /commands stats and the {count} placeholder. Uses older than
+-- /commands stats looks back get folded into one row per command and user, `uses` says how many a
+-- row stands for.
+CREATE TABLE SimpleCommandUsage (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    used_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    uses INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE INDEX idx_simple_command_usage ON SimpleCommandUsage(guild_id, used_at);
+CREATE INDEX idx_simple_command_usage_user ON SimpleCommandUsage(guild_id, name, user_id);
@@ -0,0 +1,19 @@
+-- Add migration script here
+-- What a simple command looked like after each change, kind and content are NULL once it's removed
+CREATE TABLE SimpleCommandRevisions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    revision INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    kind TEXT,
+    content TEXT,
+    attachment BLOB,
+    author_id INTEGER,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE UNIQUE INDEX idx_simple_command_revision ON SimpleCommandRevisions(guild_id, name, revision);
+
+-- The commands added before the history was kept, by nobody known
+INSERT INTO SimpleCommandRevisions (guild_id, name, revision, action, kind, content, attachment)
+SELECT guild_id, name, 1, 'Add', kind, content, attachment FROM SimpleCommands;
@@ -0,0 +1,34 @@
+-- Add migration script here
+-- Carries over the cooldowns still running in the columns used before the Cooldowns table. The
+-- durations are the ones the old code hard-coded for those columns, so they stay as they are when
+-- the defaults in `[cooldowns]` change. They applied everywhere, so they are copied to every
+-- server the bot knows about and to DMs.
+CREATE TEMP TABLE KnownGuilds AS
+    SELECT 0 AS guild_id
+    UNION SELECT guild_id FROM GuildSettings
+    UNION SELECT guild_id FROM CooldownDurations
+    UNION SELECT guild_id FROM CommandRules
+    UNION SELECT guild_id FROM SimpleCommands;
+
+CREATE TEMP TABLE LegacyCooldowns AS
+    SELECT CAST(id AS INTEGER) AS user_id, 'color_change' AS action,
+        datetime(last_random, '+3600 seconds') AS expires_at FROM User
+    UNION ALL SELECT CAST(id AS INTEGER), 'duel_loss', datetime(last_loss, '+3600 seconds') FROM User
+    UNION ALL SELECT CAST(user_id AS INTEGER), 'rpg_loss', datetime(last_loss, '+30 seconds')
+        FROM RPGCharacter
+    UNION ALL SELECT CAST(id AS INTEGER), 'dino_hatch', datetime(date(last_hatch), '+1 day')
+        FROM DinoUser
+    UNION ALL SELECT CAST(id AS INTEGER), 'dino_slurp', datetime(last_slurp, '+3600 seconds')
+        FROM DinoUser
+    UNION ALL SELECT CAST(id AS INTEGER), 'dino_gift', datetime(last_gifting, '+3600 seconds')
+        FROM DinoUser;
+
+-- Cooldowns started since the deploy are newer, they are kept
+INSERT OR IGNORE INTO Cooldowns (guild_id, user_id, action, expires_at)
+SELECT KnownGuilds.guild_id, LegacyCooldowns.user_id, LegacyCooldowns.action,
+    LegacyCooldowns.expires_at
+FROM LegacyCooldowns CROSS JOIN KnownGuilds
+WHERE LegacyCooldowns.expires_at > datetime('now');
+
+DROP TABLE LegacyCooldowns;
+DROP TABLE KnownGuilds;
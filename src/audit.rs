@@ -0,0 +1,260 @@
+use std::fmt::Display;
+
+use chrono::NaiveDateTime;
+use poise::serenity_prelude::{GuildId, UserId};
+use poise::ChoiceParameter;
+use sqlx::{QueryBuilder, Sqlite, SqliteExecutor};
+
+use crate::{Context, Result};
+
+/// Everything that ends up in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum AuditAction {
+    #[name = "command_add"]
+    CommandAdd,
+    #[name = "command_edit"]
+    CommandEdit,
+    #[name = "command_remove"]
+    CommandRemove,
+    #[name = "command_import"]
+    CommandImport,
+    #[name = "command_revert"]
+    CommandRevert,
+    #[name = "color_change"]
+    ColorChange,
+    #[name = "color_remove"]
+    ColorRemove,
+    #[name = "gamble_chance"]
+    GambleChance,
+    #[name = "dino_gift"]
+    DinoGift,
+    #[name = "dino_slurp"]
+    DinoSlurp,
+    #[name = "dino_rename"]
+    DinoRename,
+    #[name = "poll_close"]
+    PollClose,
+    #[name = "duel_timeout"]
+    DuelTimeout,
+    #[name = "user_purge"]
+    UserPurge,
+    #[name = "setting_change"]
+    SettingChange,
+    #[name = "cooldown_change"]
+    CooldownChange,
+    #[name = "cooldown_reset"]
+    CooldownReset,
+    #[name = "rule_change"]
+    RuleChange,
+    #[name = "rule_remove"]
+    RuleRemove,
+    #[name = "money_gift"]
+    MoneyGift,
+}
+
+impl Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Something that changed, and who changed it.
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub guild_id: Option<GuildId>,
+    pub user_id: UserId,
+    pub action: AuditAction,
+    pub target_id: Option<UserId>,
+    pub details: String,
+}
+
+impl Event {
+    pub fn new(
+        guild_id: Option<GuildId>,
+        user_id: UserId,
+        action: AuditAction,
+        details: impl Into<String>,
+    ) -> Self {
+        Self {
+            guild_id,
+            user_id,
+            action,
+            target_id: None,
+            details: details.into(),
+        }
+    }
+
+    /// An action by whoever called the command, in the server it was called in.
+    pub fn from_ctx(ctx: Context<'_>, action: AuditAction, details: impl Into<String>) -> Self {
+        Self::new(ctx.guild_id(), ctx.author().id, action, details)
+    }
+
+    pub fn target(mut self, target_id: UserId) -> Self {
+        self.target_id = Some(target_id);
+        self
+    }
+}
+
+/// Writes `event` to the audit log, pass a transaction to only keep it if the change is kept.
+pub async fn record(executor: impl SqliteExecutor<'_>, event: &Event) -> Result<()> {
+    let guild_id = event.guild_id.map(|id| id.get() as i64);
+    let user_id = event.user_id.get() as i64;
+    let action = event.action.name();
+    let target_id = event.target_id.map(|id| id.get() as i64);
+
+    sqlx::query!(
+        "INSERT INTO AuditLog (guild_id, user_id, action, target_id, details) VALUES (?, ?, ?, ?, ?)",
+        guild_id,
+        user_id,
+        action,
+        target_id,
+        event.details
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// A line of the audit log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub id: i64,
+    /// `None` once they asked to be forgotten.
+    pub user_id: Option<UserId>,
+    pub action: Option<AuditAction>,
+    pub target_id: Option<UserId>,
+    pub details: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(sqlx::FromRow)]
+struct EntryRow {
+    id: i64,
+    user_id: i64,
+    action: String,
+    target_id: Option<i64>,
+    details: String,
+    created_at: NaiveDateTime,
+}
+
+fn to_user_id(id: i64) -> Option<UserId> {
+    (id > 0).then(|| UserId::new(id as u64))
+}
+
+impl From<EntryRow> for Entry {
+    fn from(row: EntryRow) -> Self {
+        Self {
+            id: row.id,
+            user_id: to_user_id(row.user_id),
+            action: AuditAction::from_name(&row.action),
+            target_id: row.target_id.and_then(to_user_id),
+            details: row.details,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// What to look for in a server's audit log, every filter is optional.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    pub guild_id: GuildId,
+    /// Matches both who did it and who it was done to.
+    pub user_id: Option<UserId>,
+    pub action: Option<AuditAction>,
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+}
+
+/// The most recent entries matching `filter`, newest first.
+pub async fn search(
+    executor: impl SqliteExecutor<'_>,
+    filter: &Filter,
+    limit: i64,
+) -> Result<Vec<Entry>> {
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "SELECT id, user_id, action, target_id, details, created_at FROM AuditLog WHERE guild_id = ",
+    );
+    query.push_bind(filter.guild_id.get() as i64);
+
+    if let Some(user_id) = filter.user_id {
+        let user_id = user_id.get() as i64;
+        query.push(" AND (user_id = ");
+        query.push_bind(user_id);
+        query.push(" OR target_id = ");
+        query.push_bind(user_id);
+        query.push(")");
+    }
+    if let Some(action) = filter.action {
+        query.push(" AND action = ");
+        query.push_bind(action.name());
+    }
+    if let Some(since) = filter.since {
+        query.push(" AND created_at >= ");
+        query.push_bind(since);
+    }
+    if let Some(until) = filter.until {
+        query.push(" AND created_at < ");
+        query.push_bind(until);
+    }
+    query.push(" ORDER BY id DESC LIMIT ");
+    query.push_bind(limit);
+
+    let rows: Vec<EntryRow> = query.build_query_as().fetch_all(executor).await?;
+
+    Ok(rows.into_iter().map(Entry::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[tokio::test]
+    async fn entries_are_filtered() {
+        let database = testing::database().await;
+        let guild_id = GuildId::new(1);
+        let (mod_id, member_id) = (UserId::new(2), UserId::new(3));
+
+        let events = [
+            Event::new(Some(guild_id), mod_id, AuditAction::CommandAdd, "Added !hi"),
+            Event::new(Some(guild_id), mod_id, AuditAction::ColorRemove, "Removed")
+                .target(member_id),
+            Event::new(Some(guild_id), member_id, AuditAction::DinoRename, "Rex"),
+            Event::new(
+                Some(GuildId::new(9)),
+                mod_id,
+                AuditAction::CommandAdd,
+                "Other",
+            ),
+        ];
+        for event in &events {
+            record(&database, event).await.unwrap();
+        }
+
+        let mut filter = Filter {
+            guild_id,
+            user_id: None,
+            action: None,
+            since: None,
+            until: None,
+        };
+        let entries = search(&database, &filter, 10).await.unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].action, Some(AuditAction::DinoRename));
+
+        filter.user_id = Some(member_id);
+        let entries = search(&database, &filter, 10).await.unwrap();
+        assert_eq!(entries.len(), 2);
+
+        filter.action = Some(AuditAction::ColorRemove);
+        let entries = search(&database, &filter, 10).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].user_id, Some(mod_id));
+        assert_eq!(entries[0].target_id, Some(member_id));
+
+        filter.action = None;
+        filter.until = Some(NaiveDateTime::default());
+        assert!(search(&database, &filter, 10).await.unwrap().is_empty());
+    }
+}
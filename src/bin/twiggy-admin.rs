@@ -0,0 +1,123 @@
+//! Maintenance of the bot's database, run it while the bot is stopped.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, ensure, Context, Result};
+use twiggy_bot::{config::Config, maintenance, migrations};
+
+const USAGE: &str = "Usage: twiggy-admin <command>
+
+Commands:
+    backup <file>                         Copy the database to a new file
+    restore <file>                        Replace the database with a backup
+    reset-elo <user id>                   Put someone's RPG rating back to 1000
+    reset-cooldowns <user id> [guild id]  Remove someone's cooldowns
+    regenerate-dinos                      Draw the missing dino images again
+    export-commands <guild id> [file]     Write a server's simple commands as JSON
+    import-commands <guild id> <file>     Add the simple commands of an export to a server
+    check                                 Look for damaged or orphaned rows
+
+The database and assets come from twiggy.toml, or the file in TWIGGY_CONFIG.";
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let config = Config::load()?;
+
+    match args[..] {
+        ["backup", file] => {
+            let database = twiggy_bot::connect(&config, false).await?;
+            maintenance::backup(&database, &PathBuf::from(file)).await?;
+            println!("Backed up {} to {file}", config.database.display());
+        }
+        ["restore", file] => {
+            match maintenance::restore(&PathBuf::from(file), &config.database).await? {
+                Some(previous) => println!(
+                    "Restored {file}, the previous database was moved to {}",
+                    previous.display()
+                ),
+                None => println!("Restored {file}"),
+            }
+        }
+        ["reset-elo", user] => {
+            let database = open(&config).await?;
+            match maintenance::reset_elo(&database, parse_id(user)?).await? {
+                true => println!("The ELO of {user} is back to 1000"),
+                false => println!("{user} has no RPG character"),
+            }
+        }
+        ["reset-cooldowns", user] | ["reset-cooldowns", user, _] => {
+            let database = open(&config).await?;
+            let guild_id = args.get(2).map(|id| parse_id(id)).transpose()?;
+            let count = maintenance::reset_cooldowns(&database, parse_id(user)?, guild_id).await?;
+            println!("Removed {count} cooldowns of {user}");
+        }
+        #[cfg(feature = "dino")]
+        ["regenerate-dinos"] => {
+            let database = open(&config).await?;
+            let names = maintenance::regenerate_dino_images(&database, &config.assets).await?;
+            println!("Regenerated {} dino images", names.len());
+            for name in names {
+                println!("    {name}");
+            }
+        }
+        #[cfg(not(feature = "dino"))]
+        ["regenerate-dinos"] => bail!("This build of the bot has no dinos"),
+        ["export-commands", guild] | ["export-commands", guild, _] => {
+            let database = open(&config).await?;
+            let json = maintenance::export_commands(&database, parse_id(guild)?).await?;
+            match args.get(2) {
+                Some(file) => {
+                    std::fs::write(file, json).with_context(|| format!("Could not write {file}"))?
+                }
+                None => println!("{json}"),
+            }
+        }
+        ["import-commands", guild, file] => {
+            let database = open(&config).await?;
+            let json =
+                std::fs::read_to_string(file).with_context(|| format!("Could not read {file}"))?;
+            let summary = maintenance::import_commands(&database, parse_id(guild)?, &json).await?;
+            println!("{summary}\nThe commands show up once the bot restarts");
+        }
+        ["check"] => {
+            let database = open(&config).await?;
+            let problems = maintenance::check_integrity(&database).await?;
+            if problems.is_empty() {
+                println!("No problems found");
+                return Ok(());
+            }
+            for problem in &problems {
+                println!("{problem}");
+            }
+            bail!("Found {} problems", problems.len());
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            std::process::exit(2);
+        }
+    }
+
+    Ok(())
+}
+
+/// Opens the database, which has to be on the latest schema.
+async fn open(config: &Config) -> Result<sqlx::SqlitePool> {
+    let database = twiggy_bot::connect(config, false).await?;
+    let pending = migrations::pending_migrations(&database).await?;
+    ensure!(
+        pending.is_empty(),
+        "The database is missing {} migrations, start the bot once to apply them",
+        pending.len()
+    );
+
+    Ok(database)
+}
+
+fn parse_id<T: From<std::num::NonZeroU64>>(id: &str) -> Result<T> {
+    match id.parse() {
+        Ok(id) => Ok(T::from(id)),
+        Err(_) => bail!("{id} is not a Discord ID"),
+    }
+}
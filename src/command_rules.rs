@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use poise::serenity_prelude::{ChannelId, GuildId, Mention, RoleId};
+use sqlx::SqlitePool;
+use tokio::sync::RwLock;
+
+use crate::{
+    audit::{self, Event},
+    common::ephemeral_reply,
+    Context, Result,
+};
+
+/// Where a rule applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleTarget {
+    Guild,
+    Channel(ChannelId),
+    Role(RoleId),
+}
+
+impl RuleTarget {
+    fn scope(self) -> &'static str {
+        match self {
+            RuleTarget::Guild => "guild",
+            RuleTarget::Channel(_) => "channel",
+            RuleTarget::Role(_) => "role",
+        }
+    }
+
+    fn target_id(self) -> i64 {
+        match self {
+            RuleTarget::Guild => 0,
+            RuleTarget::Channel(id) => id.get() as i64,
+            RuleTarget::Role(id) => id.get() as i64,
+        }
+    }
+
+    fn from_row(scope: &str, target_id: i64) -> Option<Self> {
+        let id = u64::try_from(target_id).ok().filter(|id| *id != 0);
+        match scope {
+            "guild" => Some(RuleTarget::Guild),
+            "channel" => id.map(|id| RuleTarget::Channel(ChannelId::new(id))),
+            "role" => id.map(|id| RuleTarget::Role(RoleId::new(id))),
+            _ => None,
+        }
+    }
+}
+
+impl Display for RuleTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleTarget::Guild => write!(f, "the whole server"),
+            RuleTarget::Channel(id) => write!(f, "{}", Mention::from(*id)),
+            RuleTarget::Role(id) => write!(f, "{}", Mention::from(*id)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub command: String,
+    pub target: RuleTarget,
+    pub allow: bool,
+}
+
+struct RuleRow {
+    guild_id: i64,
+    command: String,
+    scope: String,
+    target_id: i64,
+    allow: bool,
+}
+
+/// Decides whether `command` can be used in `channel_id` by a member with `roles`.
+///
+/// Channel rules win over role rules, which win over server rules: a command allowed in a
+/// channel can be used there by everyone, even with a role it's denied to. Allowing a command in
+/// some channels or for some roles means it can only be used there.
+fn is_allowed(rules: &[Rule], command: &str, channel_id: ChannelId, roles: &[RoleId]) -> bool {
+    let rules = rules.iter().filter(|r| r.command == command);
+
+    let mut only_in_channels = false;
+    let mut role_allowed = false;
+    let mut role_denied = false;
+    let mut only_for_roles = false;
+    let mut guild_denied = false;
+
+    for rule in rules {
+        match (rule.target, rule.allow) {
+            (RuleTarget::Channel(id), false) if id == channel_id => return false,
+            (RuleTarget::Channel(id), true) if id == channel_id => return true,
+            (RuleTarget::Channel(_), allow) => only_in_channels |= allow,
+            (RuleTarget::Role(id), allow) => {
+                only_for_roles |= allow;
+                if roles.contains(&id) {
+                    role_allowed |= allow;
+                    role_denied |= !allow;
+                }
+            }
+            (RuleTarget::Guild, allow) => guild_denied = !allow,
+        }
+    }
+
+    if only_in_channels || role_denied {
+        return false;
+    }
+    if only_for_roles {
+        return role_allowed;
+    }
+
+    !guild_denied
+}
+
+/// Allow and deny rules for the default and dynamic commands of every guild.
+pub struct CommandRules {
+    database: SqlitePool,
+    rules: RwLock<HashMap<i64, Vec<Rule>>>,
+}
+
+impl CommandRules {
+    pub async fn new(database: &SqlitePool) -> Result<Self> {
+        let rows = sqlx::query_as!(
+            RuleRow,
+            r#"SELECT guild_id, command, scope, target_id, allow as "allow: bool" FROM CommandRules"#
+        )
+        .fetch_all(database)
+        .await?;
+
+        let mut rules: HashMap<i64, Vec<Rule>> = HashMap::new();
+        for row in rows {
+            let Some(target) = RuleTarget::from_row(&row.scope, row.target_id) else {
+                tracing::warn!(
+                    guild = row.guild_id,
+                    "Ignoring invalid command rule '{}' for {}",
+                    row.scope,
+                    row.command
+                );
+                continue;
+            };
+
+            rules.entry(row.guild_id).or_default().push(Rule {
+                command: row.command,
+                target,
+                allow: row.allow,
+            });
+        }
+
+        Ok(Self {
+            database: database.clone(),
+            rules: RwLock::new(rules),
+        })
+    }
+
+    pub async fn is_allowed(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        roles: &[RoleId],
+        command: &str,
+    ) -> bool {
+        let rules = self.rules.read().await;
+        match rules.get(&(guild_id.get() as i64)) {
+            Some(rules) => is_allowed(rules, command, channel_id, roles),
+            None => true,
+        }
+    }
+
+    pub async fn list(&self, guild_id: GuildId) -> Vec<Rule> {
+        let rules = self.rules.read().await;
+        rules
+            .get(&(guild_id.get() as i64))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Adds the rule, replacing the opposite one if it exists.
+    pub async fn set(&self, guild_id: GuildId, rule: Rule, event: Option<&Event>) -> Result<()> {
+        let guild_id = guild_id.get() as i64;
+        let scope = rule.target.scope();
+        let target_id = rule.target.target_id();
+
+        let mut rules = self.rules.write().await;
+        let mut transaction = self.database.begin().await?;
+        sqlx::query!(
+            r#"INSERT INTO CommandRules (guild_id, command, scope, target_id, allow) VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(guild_id, command, scope, target_id) DO UPDATE SET allow = excluded.allow"#,
+            guild_id,
+            rule.command,
+            scope,
+            target_id,
+            rule.allow
+        )
+        .execute(&mut *transaction)
+        .await?;
+        if let Some(event) = event {
+            audit::record(&mut *transaction, event).await?;
+        }
+        transaction.commit().await?;
+
+        let guild_rules = rules.entry(guild_id).or_default();
+        guild_rules.retain(|r| r.command != rule.command || r.target != rule.target);
+        guild_rules.push(rule);
+
+        Ok(())
+    }
+
+    /// Removes the rule for this command and target. Returns whether there was one.
+    pub async fn remove(
+        &self,
+        guild_id: GuildId,
+        command: &str,
+        target: RuleTarget,
+        event: Option<&Event>,
+    ) -> Result<bool> {
+        let guild_id = guild_id.get() as i64;
+        let scope = target.scope();
+        let target_id = target.target_id();
+
+        let mut rules = self.rules.write().await;
+        let mut transaction = self.database.begin().await?;
+        let result = sqlx::query!(
+            "DELETE FROM CommandRules WHERE guild_id = ? AND command = ? AND scope = ? AND target_id = ?",
+            guild_id,
+            command,
+            scope,
+            target_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+        // Nothing changed when there was no such rule
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        if let Some(event) = event {
+            audit::record(&mut *transaction, event).await?;
+        }
+        transaction.commit().await?;
+
+        if let Some(guild_rules) = rules.get_mut(&guild_id) {
+            guild_rules.retain(|r| r.command != command || r.target != target);
+        }
+
+        Ok(true)
+    }
+}
+
+/// The name rules are stored under, subcommands follow the rules of their top level command.
+fn rule_name(qualified_name: &str) -> &str {
+    qualified_name.split(' ').next().unwrap_or(qualified_name)
+}
+
+/// Refuses the command if a rule disables it in this channel or for this member.
+pub async fn check(ctx: Context<'_>) -> Result<bool> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(true);
+    };
+
+    let roles = match ctx.author_member().await {
+        Some(member) => member.roles.clone(),
+        None => Vec::new(),
+    };
+    let name = rule_name(&ctx.command().qualified_name);
+    if ctx
+        .data()
+        .command_rules
+        .is_allowed(guild_id, ctx.channel_id(), &roles, name)
+        .await
+    {
+        return Ok(true);
+    }
+
+    ctx.send(ephemeral_reply(denied_message(name))).await?;
+
+    Ok(false)
+}
+
+pub fn denied_message(command: &str) -> String {
+    format!("`/{command}` can't be used here.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(command: &str, target: RuleTarget, allow: bool) -> Rule {
+        Rule {
+            command: command.to_string(),
+            target,
+            allow,
+        }
+    }
+
+    #[test]
+    fn channel_rules_win_over_server_rules() {
+        let serious = ChannelId::new(1);
+        let spam = ChannelId::new(2);
+        let general = ChannelId::new(3);
+        let rules = vec![
+            rule("sudoku", RuleTarget::Channel(serious), false),
+            rule("mixu", RuleTarget::Channel(spam), true),
+            rule("duel", RuleTarget::Guild, false),
+            rule("duel", RuleTarget::Channel(spam), true),
+        ];
+
+        assert!(!is_allowed(&rules, "sudoku", serious, &[]));
+        assert!(is_allowed(&rules, "sudoku", general, &[]));
+        assert!(is_allowed(&rules, "mixu", spam, &[]));
+        assert!(!is_allowed(&rules, "mixu", general, &[]));
+        assert!(is_allowed(&rules, "duel", spam, &[]));
+        assert!(!is_allowed(&rules, "duel", general, &[]));
+        assert!(is_allowed(&rules, "roll", general, &[]));
+    }
+
+    #[test]
+    fn channel_allows_win_over_role_denies() {
+        let spam = ChannelId::new(1);
+        let general = ChannelId::new(2);
+        let muted = RoleId::new(10);
+        let sub = RoleId::new(11);
+        let rules = vec![
+            rule("mixu", RuleTarget::Role(muted), false),
+            rule("mixu", RuleTarget::Channel(spam), true),
+            rule("poll", RuleTarget::Role(sub), true),
+            rule("poll", RuleTarget::Channel(spam), true),
+        ];
+
+        assert!(is_allowed(&rules, "mixu", spam, &[muted]));
+        assert!(!is_allowed(&rules, "mixu", general, &[muted]));
+        assert!(is_allowed(&rules, "poll", spam, &[]));
+        assert!(!is_allowed(&rules, "poll", general, &[]));
+        assert!(!is_allowed(&rules, "poll", general, &[sub]));
+    }
+
+    #[test]
+    fn role_rules_restrict_members() {
+        let channel = ChannelId::new(1);
+        let muted = RoleId::new(10);
+        let sub = RoleId::new(11);
+        let rules = vec![
+            rule("rpg", RuleTarget::Role(muted), false),
+            rule("poll", RuleTarget::Role(sub), true),
+        ];
+
+        assert!(!is_allowed(&rules, "rpg", channel, &[muted]));
+        assert!(is_allowed(&rules, "rpg", channel, &[sub]));
+        assert!(is_allowed(&rules, "poll", channel, &[sub]));
+        assert!(!is_allowed(&rules, "poll", channel, &[]));
+    }
+}
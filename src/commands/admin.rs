@@ -0,0 +1,49 @@
+use poise::serenity_prelude::User;
+
+use super::forgetme::forget_user;
+use crate::{
+    audit::{self, AuditAction, Event},
+    common::bail_reply,
+    metrics::timed_query,
+    Context, Result,
+};
+
+// Purging reaches every server the bot is in, so it's left to the bot's owners
+#[poise::command(
+    guild_only,
+    slash_command,
+    prefix_command,
+    subcommands("purge_user"),
+    owners_only
+)]
+pub async fn admin(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Delete everything the bot knows about someone, like /forgetme
+#[poise::command(
+    guild_only,
+    slash_command,
+    prefix_command,
+    owners_only,
+    rename = "purge-user"
+)]
+async fn purge_user(
+    ctx: Context<'_>,
+    #[description = "The user to forget"] user: User,
+) -> Result<()> {
+    let mut conn = ctx.data().database.acquire().await?;
+    let forgotten = timed_query(
+        "forget_user",
+        forget_user(&mut conn, &ctx.data().scheduler, user.id),
+    )
+    .await?;
+
+    // The purged user's id goes away with the rest of their data
+    let event = Event::from_ctx(ctx, AuditAction::UserPurge, forgotten.describe());
+    audit::record(&ctx.data().database, &event).await?;
+
+    tracing::info!(admin = %ctx.author().id, "Purged a user's data: {forgotten:?}");
+    let msg = format!("{} has been forgotten. {}", user.name, forgotten.describe());
+    bail_reply(ctx, msg).await
+}
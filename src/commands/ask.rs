@@ -1,12 +1,8 @@
-use crate::{common::bail_reply, Context, Result};
+use crate::{common::bail_reply, config::Config, cooldowns::Action, Context, Result};
 
-use std::sync::{
-    atomic::{AtomicI64, Ordering},
-    OnceLock,
-};
+use std::sync::OnceLock;
 
 use anyhow::bail;
-use chrono::Utc;
 use poise::CreateReply;
 use reqwest::{StatusCode, Url};
 use serenity::all::CreateEmbed;
@@ -19,28 +15,40 @@ pub enum Unit {
     Metric,
 }
 
-// This looks dumb but it tells me that I'm using seconds
-// instead of just being a random number
-const ASK_COOLDOWN: i64 = std::time::Duration::from_secs(10).as_secs() as i64;
 static WOLFRAM_APP_ID: OnceLock<String> = OnceLock::new();
 
-pub fn initialize_app_id() -> Result<()> {
-    let app_id = std::env::var("WOLFRAM_APP_ID")?;
+pub fn initialize_app_id(config: &Config) -> Result<()> {
+    let Some(app_id) = config.wolfram_app_id.clone() else {
+        bail!("WOLFRAM_APP_ID was not provided");
+    };
     WOLFRAM_APP_ID.set(app_id).unwrap();
 
     Ok(())
 }
 
 /// Ask a question to Wolfram Alpha
-#[poise::command(slash_command, prefix_command, custom_data = "AtomicI64::new(0)")]
+#[poise::command(slash_command, prefix_command)]
 pub async fn ask(
     ctx: Context<'_>,
     #[description = "The question you want to ask"] question: String,
     #[description = "The units of measurement"] units: Option<Unit>,
 ) -> Result<()> {
-    if let Err(cooldown_msg) = update_cooldown(ctx).await {
-        return bail_reply(ctx, cooldown_msg.to_string()).await;
+    let cooldowns = &ctx.data().cooldowns;
+    let (guild_id, user_id) = (ctx.guild_id(), ctx.author().id);
+    if let Err(e) = cooldowns
+        .ensure_ready(
+            guild_id,
+            user_id,
+            Action::Ask,
+            "The command is on cooldown.",
+        )
+        .await
+    {
+        return bail_reply(ctx, e.to_string()).await;
     }
+    cooldowns
+        .start(&ctx.data().database, guild_id, user_id, Action::Ask)
+        .await?;
 
     let answer = fetch_answer(WOLFRAM_APP_ID.get().unwrap(), &question, units).await?;
 
@@ -73,23 +81,6 @@ async fn fetch_answer(app_id: &str, question: &str, units: Option<Unit>) -> Resu
     }
 }
 
-async fn update_cooldown(ctx: Context<'_>) -> Result<()> {
-    let last_called = ctx
-        .command()
-        .custom_data
-        .downcast_ref::<AtomicI64>()
-        .expect("Expected the command to have the last use timestamp");
-
-    let now = Utc::now().timestamp();
-    let cooldown_end = last_called.load(Ordering::Relaxed) + ASK_COOLDOWN;
-    if cooldown_end > now {
-        bail!("The command will be off cooldown <t:{cooldown_end}:R>");
-    }
-
-    last_called.store(now, Ordering::Relaxed);
-    Ok(())
-}
-
 fn truncate(string: String, max_length: usize) -> String {
     if string.len() <= max_length {
         return string;
@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use poise::serenity_prelude::{CreateEmbed, Mention, User};
+
+use crate::{
+    audit::{self, AuditAction, Entry, Filter},
+    common::{bail_reply, paginate, truncate},
+    metrics::timed_query,
+    Context, Result,
+};
+
+const MAX_ENTRIES: i64 = 250;
+const ENTRIES_PER_PAGE: usize = 10;
+const MAX_DETAILS_LENGTH: usize = 200;
+const PAGINATION_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+#[poise::command(
+    guild_only,
+    slash_command,
+    prefix_command,
+    subcommands("search"),
+    required_permissions = "MODERATE_MEMBERS"
+)]
+pub async fn audit(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Look through who changed what, newest first
+#[poise::command(guild_only, slash_command, prefix_command)]
+async fn search(
+    ctx: Context<'_>,
+    #[description = "Who did it or had it done to them"] user: Option<User>,
+    #[description = "The kind of action"] action: Option<AuditAction>,
+    #[description = "From this day on, as YYYY-MM-DD (UTC)"] after: Option<String>,
+    #[description = "Before this day, as YYYY-MM-DD (UTC)"] before: Option<String>,
+) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /audit search to be guild only.");
+
+    let (since, until) = match (parse_day(after.as_deref()), parse_day(before.as_deref())) {
+        (Ok(since), Ok(until)) => (since, until),
+        (Err(day), _) | (_, Err(day)) => {
+            let msg = format!("`{day}` is not a valid day, use YYYY-MM-DD.");
+            return bail_reply(ctx, msg).await;
+        }
+    };
+
+    let filter = Filter {
+        guild_id,
+        user_id: user.map(|u| u.id),
+        action,
+        since,
+        until,
+    };
+    let entries = timed_query(
+        "search_audit_log",
+        audit::search(&ctx.data().database, &filter, MAX_ENTRIES),
+    )
+    .await?;
+
+    if entries.is_empty() {
+        return bail_reply(ctx, "Nothing in the audit log matches that.").await;
+    }
+
+    let pages = entries
+        .chunks(ENTRIES_PER_PAGE)
+        .map(|chunk| {
+            let lines = chunk.iter().map(describe).collect::<Vec<_>>();
+            CreateEmbed::default()
+                .title("Audit log")
+                .colour(0x77618F)
+                .description(lines.join("\n"))
+        })
+        .collect();
+
+    paginate(ctx, pages, PAGINATION_TIMEOUT).await
+}
+
+/// `Ok(None)` when there's no day, `Err` with the input when it can't be read.
+fn parse_day(day: Option<&str>) -> std::result::Result<Option<NaiveDateTime>, String> {
+    let Some(day) = day else {
+        return Ok(None);
+    };
+
+    NaiveDate::parse_from_str(day.trim(), "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(Some)
+        .ok_or_else(|| day.to_string())
+}
+
+fn describe(entry: &Entry) -> String {
+    let who = entry.user_id.map_or_else(
+        || "someone forgotten".to_string(),
+        |id| Mention::User(id).to_string(),
+    );
+    let action = entry
+        .action
+        .map_or_else(|| "unknown".to_string(), |a| a.to_string());
+    let target = entry
+        .target_id
+        .map(|id| format!(" → {}", Mention::User(id)))
+        .unwrap_or_default();
+
+    format!(
+        "<t:{}:f> **{action}** by {who}{target}: {}",
+        entry.created_at.and_utc().timestamp(),
+        truncate(&entry.details, MAX_DETAILS_LENGTH)
+    )
+}
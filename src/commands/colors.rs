@@ -1,25 +1,23 @@
 use std::borrow::Cow;
-use std::time::Duration;
 
-use anyhow::{bail, Context as Ctx};
-use chrono::{NaiveDateTime, Utc};
+use anyhow::bail;
 use poise::serenity_prelude::{EditRole, Member, Mention, Role};
 use rand::Rng;
-use sqlx::SqlitePool;
 use tokio::sync::Mutex;
 
 use crate::{
-    common::{bail_reply, ephemeral_reply},
-    Context, Result, SUB_ROLE_ID,
+    audit::{self, AuditAction, Event},
+    common::{bail_reply, ephemeral_reply, to_color},
+    cooldowns::Action,
+    metrics::timed_query,
+    settings::Setting,
+    Context, Result,
 };
 
 // These commands were originally made in american english so I'm keeping them
 // that way, there won't be any `ou`s in this module.
 
 const DEFAULT_GAMBLE_FAIL_CHANCE: u8 = 15;
-const RANDOM_COLOR_COOLDOWN: Duration = Duration::from_secs(60 * 60);
-
-const ANCHOR_ROLE_ID: u64 = SUB_ROLE_ID;
 
 #[poise::command(
     guild_only,
@@ -50,14 +48,14 @@ async fn change(
         return bail_reply(ctx, "I could not find your roles.").await;
     };
 
-    if let Err(reason) = reject_non_subs(&member).await {
+    if let Err(reason) = reject_non_subs(ctx, &member).await {
         return bail_reply(ctx, reason.to_string()).await;
     }
 
     let role_name = match change_color(ctx, member, Some(color)).await {
         Ok(name) => name,
         Err(e) => {
-            eprintln!("Error while trying to change color: {e}");
+            tracing::error!("Error while trying to change color: {e}");
             let msg = "Something went wrong while trying to change your color. :(";
             return bail_reply(ctx, msg).await;
         }
@@ -77,18 +75,18 @@ async fn random(ctx: Context<'_>) -> Result<()> {
         return bail_reply(ctx, "I could not find your roles.").await;
     };
 
-    if let Err(reason) = reject_non_subs(&member).await {
+    if let Err(reason) = reject_non_subs(ctx, &member).await {
         return bail_reply(ctx, reason.to_string()).await;
     }
 
     let role_result = change_color(ctx, member, None).await;
     if let Err(e) = role_result {
-        eprintln!("Error while trying to change to a random color: {e}");
+        tracing::error!("Error while trying to change to a random color: {e}");
         let msg = "Something went wrong while trying to change your color :(";
         return bail_reply(ctx, msg).await;
     }
 
-    update_last_random_cooldown(&ctx.data().database, &ctx.author().id.to_string()).await?;
+    start_color_cooldown(ctx).await?;
     ctx.say(format!(
         "Hahaha. Get stuck with {} for an hour.",
         role_result.unwrap()
@@ -109,7 +107,7 @@ async fn gamble(ctx: Context<'_>) -> Result<()> {
         return bail_reply(ctx, "I could not find your roles").await;
     };
 
-    if let Err(reason) = reject_non_subs(&member).await {
+    if let Err(reason) = reject_non_subs(ctx, &member).await {
         return bail_reply(ctx, reason.to_string()).await;
     }
 
@@ -118,22 +116,21 @@ async fn gamble(ctx: Context<'_>) -> Result<()> {
         .downcast_ref::<Mutex<u8>>()
         .expect("Expected to have passed the gamble chance as custom_data");
     let gamble_chance = custom_data.lock().await;
-    let roll: u8 = {
-        let mut rng = rand::thread_rng();
-        rng.gen_range(0..=100)
-    };
+    let mut rng = ctx.data().game_rng("color_gamble");
+    let roll: u8 = rng.gen_range(0..=100);
+    rng.audit(format!("Rolled {roll} against {}", *gamble_chance));
     if roll > *gamble_chance {
         return bail_reply(ctx, "Yay! You get to keep your color!").await;
     }
 
     let role_result = change_color(ctx, member, None).await;
     if let Err(e) = role_result {
-        eprintln!("Error while trying to change to a random color: {e}");
+        tracing::error!("Error while trying to change to a random color: {e}");
         let msg = "Something went wrong while trying to change your color :(";
         return bail_reply(ctx, msg).await;
     }
 
-    update_last_random_cooldown(&ctx.data().database, &ctx.author().id.to_string()).await?;
+    start_color_cooldown(ctx).await?;
     ctx.say(format!(
         "Hahaha. Get stuck with {} for an hour.",
         role_result.unwrap()
@@ -155,12 +152,29 @@ async fn change_color(
         bail!("Could not get guild from guild_id");
     };
 
-    let color = color.unwrap_or_else(generate_random_hex_color);
+    let color = match color {
+        Some(color) => color,
+        None => {
+            let mut rng = ctx.data().game_rng("color_random");
+            let color = generate_random_hex_color(&mut rng);
+            rng.audit(format!("#{color:06X}"));
+            color
+        }
+    };
     let role_name = format!("#{color:06X}");
     let role = match guild.role_by_name(&role_name) {
         Some(role) => role.clone(),
         None => {
-            let Some(anchor_role) = guild.roles.get(&ANCHOR_ROLE_ID.into()) else {
+            // The anchor role defaults to the sub role when it's not configured
+            let anchor_role_id = match ctx
+                .data()
+                .guild_role(guild_id, Setting::ColorAnchorRole)
+                .await
+            {
+                Some(role_id) => Some(role_id),
+                None => ctx.data().guild_role(guild_id, Setting::SubRole).await,
+            };
+            let Some(anchor_role) = anchor_role_id.and_then(|id| guild.roles.get(&id)) else {
                 bail!(
                     "The anchor role was not found, \
                 unable to create a role with at the correct position."
@@ -181,6 +195,9 @@ async fn change_color(
     remove_unused_color_roles(ctx, &mut member).await?;
     member.to_mut().add_role(ctx, role.id).await?;
 
+    let event = Event::from_ctx(ctx, AuditAction::ColorChange, &role_name);
+    audit::record(&ctx.data().database, &event).await?;
+
     Ok(role_name)
 }
 
@@ -196,15 +213,15 @@ pub async fn favorite(
     let color_code = format!("#{color:06X}");
     let author_id = ctx.author().id.to_string();
 
-    sqlx::query!(
+    let query = sqlx::query!(
         r#"INSERT OR IGNORE INTO User (id) VALUES (?);
         UPDATE User SET fav_color = ? WHERE id = ?"#,
         author_id,
         color_code,
         author_id
     )
-    .execute(&ctx.data().database)
-    .await?;
+    .execute(&ctx.data().database);
+    timed_query("set_favorite_color", query).await?;
 
     ctx.send(ephemeral_reply(format!(
         "{color_code} has been set as your favorite color!"
@@ -225,20 +242,20 @@ pub async fn lazy(ctx: Context<'_>) -> Result<()> {
         return bail_reply(ctx, "Could not find your roles").await;
     };
 
-    if let Err(reason) = reject_non_subs(&member).await {
+    if let Err(reason) = reject_non_subs(ctx, &member).await {
         return bail_reply(ctx, reason.to_string()).await;
     }
 
     let author_id = ctx.author().id.to_string();
 
-    let row = sqlx::query!(
+    let query = sqlx::query!(
         r#"INSERT OR IGNORE INTO User (id) VALUES (?);
         SELECT fav_color FROM User WHERE id = ?"#,
         author_id,
         author_id
     )
-    .fetch_one(&ctx.data().database)
-    .await?;
+    .fetch_one(&ctx.data().database);
+    let row = timed_query("get_favorite_color", query).await?;
 
     let Some(color_code) = row.fav_color else {
         let msg = "You're so lazy you haven't even set a favorite color, set one for next time!";
@@ -250,9 +267,9 @@ pub async fn lazy(ctx: Context<'_>) -> Result<()> {
     };
 
     let Some(color) = to_color(&color_code) else {
-        sqlx::query!("UPDATE User SET fav_color = NULL WHERE id = ?", author_id)
-            .execute(&ctx.data().database)
-            .await?;
+        let query = sqlx::query!("UPDATE User SET fav_color = NULL WHERE id = ?", author_id)
+            .execute(&ctx.data().database);
+        timed_query("reset_favorite_color", query).await?;
 
         return bail_reply(
             ctx,
@@ -289,6 +306,10 @@ pub async fn uncolor(
     if !roles_were_removed {
         ctx.send(ephemeral_reply("There were no roles to remove."))
             .await?;
+    } else {
+        let event = Event::from_ctx(ctx, AuditAction::ColorRemove, "Removed every color role")
+            .target(member_id);
+        audit::record(&ctx.data().database, &event).await?;
     }
 
     ctx.send(ephemeral_reply(format!(
@@ -323,7 +344,11 @@ async fn setgamblechance(
         .downcast_ref::<Mutex<u8>>()
         .expect("Expected to have passed the gamble chance as custom_data");
 
-    *custom_data.lock().await = percent;
+    let previous = std::mem::replace(&mut *custom_data.lock().await, percent);
+    let details = format!("{previous}% → {percent}%");
+    let event = Event::from_ctx(ctx, AuditAction::GambleChance, details);
+    audit::record(&ctx.data().database, &event).await?;
+
     ctx.send(ephemeral_reply(format!(
         "Gamble chance has been set to {percent}%"
     )))
@@ -332,15 +357,6 @@ async fn setgamblechance(
     Ok(())
 }
 
-fn to_color(hexcode: &str) -> Option<u32> {
-    let hexcode = hexcode.strip_prefix('#').unwrap_or(hexcode);
-
-    match u32::from_str_radix(hexcode, 16) {
-        Ok(code) if hexcode.len() == 6 && code > 0 => Some(code),
-        _ => None,
-    }
-}
-
 async fn is_role_unused(ctx: Context<'_>, role: &Role) -> Result<bool> {
     let members = role.guild_id.members(ctx, None, None).await?;
     for member in members {
@@ -376,67 +392,56 @@ async fn remove_unused_color_roles(ctx: Context<'_>, member: &mut Cow<'_, Member
     Ok(roles_were_removed)
 }
 
-fn generate_random_hex_color() -> u32 {
-    let mut rng = rand::thread_rng();
+fn generate_random_hex_color(rng: &mut impl Rng) -> u32 {
     rng.gen_range(0..0x1000000)
 }
 
-async fn update_last_random_cooldown(db: &SqlitePool, user_id: &str) -> Result<()> {
-    sqlx::query!(
-        r#"INSERT OR IGNORE INTO User (id) VALUES (?);
-        UPDATE User SET last_random = datetime('now') WHERE id = ?"#,
-        user_id,
-        user_id
-    )
-    .execute(db)
-    .await?;
+async fn start_color_cooldown(ctx: Context<'_>) -> Result<()> {
+    ctx.data()
+        .cooldowns
+        .start(
+            &ctx.data().database,
+            ctx.guild_id(),
+            ctx.author().id,
+            Action::ColorChange,
+        )
+        .await?;
 
     Ok(())
 }
 
-struct UserCooldowns {
-    last_random: NaiveDateTime,
-    last_loss: NaiveDateTime,
-}
-
 async fn reject_on_cooldown(ctx: Context<'_>) -> Result<()> {
-    let user_id = ctx.author().id.to_string();
-    let row = sqlx::query_as!(
-        UserCooldowns,
-        r#"INSERT OR IGNORE INTO User (id) VALUES (?);
-        SELECT last_random, last_loss FROM User WHERE id = ?"#,
-        user_id,
-        user_id
-    )
-    .fetch_one(&ctx.data().database)
-    .await
-    .context("Something went wrong while trying to fetch your cooldowns")?;
-
-    let now = Utc::now().naive_utc();
-    let cooldown_duration = chrono::Duration::from_std(RANDOM_COLOR_COOLDOWN)?;
-
-    let permitted_time_from_random = row.last_random + cooldown_duration;
-    let permitted_time_from_loss = row.last_loss + cooldown_duration;
-
-    if permitted_time_from_random > now {
-        bail!(
-            "You recently randomed/gambled, you can change your color <t:{}:R>",
-            permitted_time_from_random.and_utc().timestamp()
-        );
-    }
-
-    if permitted_time_from_loss > now {
-        bail!(
-            "You recently dueled and lost, you can change your color <t:{}:R>",
-            permitted_time_from_loss.and_utc().timestamp()
-        );
-    }
-
-    Ok(())
+    let cooldowns = &ctx.data().cooldowns;
+    let (guild_id, user_id) = (ctx.guild_id(), ctx.author().id);
+
+    cooldowns
+        .ensure_ready(
+            guild_id,
+            user_id,
+            Action::ColorChange,
+            "You recently randomed/gambled.",
+        )
+        .await?;
+    cooldowns
+        .ensure_ready(
+            guild_id,
+            user_id,
+            Action::DuelLoss,
+            "You recently dueled and lost.",
+        )
+        .await
 }
 
-async fn reject_non_subs(member: &Member) -> Result<()> {
-    if !member.roles.contains(&SUB_ROLE_ID.into()) {
+async fn reject_non_subs(ctx: Context<'_>, member: &Member) -> Result<()> {
+    let Some(sub_role) = ctx
+        .data()
+        .guild_role(member.guild_id, Setting::SubRole)
+        .await
+    else {
+        bail!("Colors haven't been set up on this server, ask an admin to set the sub role.");
+    };
+
+    if !member.roles.contains(&sub_role) {
         bail!("Yay! You get to keep your white color!");
     }
 
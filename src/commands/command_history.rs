@@ -0,0 +1,530 @@
+//! Every version of the simple commands, so a bad edit or removal can be undone.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+use poise::serenity_prelude::{AutocompleteChoice, CreateEmbed, GuildId, Mention, UserId};
+use sqlx::{Connection, SqliteConnection, SqliteExecutor};
+
+use super::dynamic_commands::{
+    create_command, guild_commands, keep_registered, CommandInfo, CommandKind, MAX_GUILD_COMMANDS,
+};
+use super::DEFAULT_COMMANDS;
+use crate::{
+    audit::{self, AuditAction, Event},
+    common::{bail_reply, ephemeral_reply, escape_like, paginate, truncate},
+    metrics::timed_query,
+    Context, Result,
+};
+
+const REVISIONS_PER_PAGE: usize = 10;
+const MAX_CONTENT_LENGTH: usize = 200;
+const PAGINATION_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+pub enum RevisionAction {
+    Add,
+    Edit,
+    Remove,
+    Import,
+    Revert,
+}
+
+impl RevisionAction {
+    fn past_tense(self) -> &'static str {
+        match self {
+            Self::Add => "added",
+            Self::Edit => "edited",
+            Self::Remove => "removed",
+            Self::Import => "imported",
+            Self::Revert => "reverted",
+        }
+    }
+}
+
+/// What a command looked like after a change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    pub revision: i64,
+    pub action: RevisionAction,
+    /// `None` when the command was removed.
+    pub kind: Option<CommandKind>,
+    pub content: Option<String>,
+    /// `None` for the commands from before the history was kept, or when the author used
+    /// `/forgetme`.
+    pub author_id: Option<UserId>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Writes down what the command `name` became, `command` is `None` when it was removed.
+/// Returns the number of the revision.
+pub async fn record_revision(
+    executor: impl SqliteExecutor<'_>,
+    guild_id: GuildId,
+    name: &str,
+    action: RevisionAction,
+    command: Option<&CommandInfo>,
+    attachment: Option<&[u8]>,
+    author_id: Option<UserId>,
+) -> Result<i64> {
+    let guild_id = guild_id.get() as i64;
+    let author_id = author_id.map(|id| id.get() as i64);
+    let kind = command.map(|c| c.kind);
+    let content = command.map(|c| c.content.as_str());
+
+    let revision = sqlx::query_scalar!(
+        r#"INSERT INTO SimpleCommandRevisions
+            (guild_id, name, revision, action, kind, content, attachment, author_id)
+        SELECT ?, ?, COALESCE(MAX(revision), 0) + 1, ?, ?, ?, ?, ?
+        FROM SimpleCommandRevisions WHERE guild_id = ? AND name = ?
+        RETURNING revision"#,
+        guild_id,
+        name,
+        action,
+        kind,
+        content,
+        attachment,
+        author_id,
+        guild_id,
+        name
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(revision)
+}
+
+/// Every revision of a command, newest first.
+pub async fn fetch_revisions(
+    executor: impl SqliteExecutor<'_>,
+    guild_id: GuildId,
+    name: &str,
+) -> Result<Vec<Revision>> {
+    let guild_id = guild_id.get() as i64;
+    let rows = sqlx::query!(
+        r#"SELECT revision, action as "action: RevisionAction", kind as "kind: CommandKind",
+            content, author_id, created_at
+        FROM SimpleCommandRevisions WHERE guild_id = ? AND name = ? ORDER BY revision DESC"#,
+        guild_id,
+        name
+    )
+    .fetch_all(executor)
+    .await?;
+
+    let revisions = rows
+        .into_iter()
+        .map(|row| Revision {
+            revision: row.revision,
+            action: row.action,
+            kind: row.kind,
+            content: row.content,
+            author_id: row.author_id.map(|id| UserId::new(id as u64)),
+            created_at: row.created_at,
+        })
+        .collect();
+
+    Ok(revisions)
+}
+
+/// The names with a history in the guild, removed commands included.
+async fn fetch_names(
+    executor: impl SqliteExecutor<'_>,
+    guild_id: GuildId,
+    partial: &str,
+) -> Result<Vec<String>> {
+    let guild_id = guild_id.get() as i64;
+    let pattern = escape_like(partial);
+    let names = sqlx::query_scalar!(
+        r#"SELECT DISTINCT name FROM SimpleCommandRevisions
+        WHERE guild_id = ? AND name LIKE ? || '%' ESCAPE '\' ORDER BY name LIMIT 25"#,
+        guild_id,
+        pattern
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(names)
+}
+
+/// See every version of a command, removed ones included
+#[poise::command(guild_only, slash_command, prefix_command)]
+pub async fn history(
+    ctx: Context<'_>,
+    #[description = "The name of the command"]
+    #[autocomplete = "autocomplete_names"]
+    name: String,
+) -> Result<()> {
+    let name = name.to_lowercase();
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /commands history to be guild only.");
+
+    let revisions = timed_query(
+        "fetch_command_revisions",
+        fetch_revisions(&ctx.data().database, guild_id, &name),
+    )
+    .await?;
+    if revisions.is_empty() {
+        return bail_reply(ctx, format!("/{name} has no history.")).await;
+    }
+
+    let pages = revisions
+        .chunks(REVISIONS_PER_PAGE)
+        .map(|chunk| {
+            let lines = chunk.iter().map(describe).collect::<Vec<_>>();
+            CreateEmbed::default()
+                .title(format!("History of /{name}"))
+                .colour(0x77618F)
+                .description(lines.join("\n"))
+        })
+        .collect();
+
+    paginate(ctx, pages, PAGINATION_TIMEOUT).await
+}
+
+/// Put a command back the way it was, removed commands included
+#[poise::command(guild_only, slash_command, prefix_command, aliases("restore"))]
+pub async fn revert(
+    ctx: Context<'_>,
+    #[description = "The name of the command"]
+    #[autocomplete = "autocomplete_names"]
+    name: String,
+    #[description = "The revision to go back to, see /commands history"]
+    #[autocomplete = "autocomplete_revisions"]
+    revision: i64,
+) -> Result<()> {
+    let name = name.to_lowercase();
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /commands revert to be guild only.");
+
+    let is_default = DEFAULT_COMMANDS
+        .get()
+        .expect("Expected default commands to be initialized.")
+        .contains(&name);
+    if is_default {
+        let msg = "A default command has that name now, it can't be brought back.";
+        return bail_reply(ctx, msg).await;
+    }
+
+    let data = ctx.data();
+    let _changing = data.command_changes.lock().await;
+    let guild_commands = guild_commands(data, guild_id).await;
+    let found = find_revision(&data.database, &guild_commands, guild_id, &name, revision).await?;
+    let found = match found {
+        Ok(found) => found,
+        Err(msg) => return bail_reply(ctx, msg).await,
+    };
+
+    // The placeholders might need other options than the current version, and the command is
+    // only brought back if Discord takes it
+    let command = create_command(&name, &found.info);
+    guild_id.create_command(ctx, command).await?;
+    let mut conn = data.database.acquire().await?;
+    let author_id = ctx.author().id;
+    let saved = restore_revision(&mut conn, guild_id, &name, revision, &found, author_id).await;
+    let Some(new_revision) = keep_registered(ctx, guild_id, saved.map(Ok)).await? else {
+        return Ok(());
+    };
+
+    let mut map = data.simple_commands.write().await;
+    let guild_commands = map.entry(guild_id.get() as i64).or_default();
+    guild_commands.insert(name.clone(), found.info);
+    drop(map);
+
+    let msg = format!("/{name} is back to revision {revision}, as revision {new_revision}.");
+    ctx.send(ephemeral_reply(msg)).await?;
+
+    Ok(())
+}
+
+/// A revision that can be brought back.
+struct FoundRevision {
+    info: CommandInfo,
+    attachment: Option<Vec<u8>>,
+}
+
+/// Looks up `revision` of the command, or `Ok(Err(..))` with why it can't be brought back.
+async fn find_revision(
+    executor: impl SqliteExecutor<'_>,
+    guild_commands: &HashMap<String, CommandInfo>,
+    guild_id: GuildId,
+    name: &str,
+    revision: i64,
+) -> Result<std::result::Result<FoundRevision, String>> {
+    let guild = guild_id.get() as i64;
+    let Some(row) = sqlx::query!(
+        r#"SELECT kind as "kind: CommandKind", content, attachment FROM SimpleCommandRevisions
+        WHERE guild_id = ? AND name = ? AND revision = ?"#,
+        guild,
+        name,
+        revision
+    )
+    .fetch_optional(executor)
+    .await?
+    else {
+        return Ok(Err(format!("/{name} has no revision {revision}.")));
+    };
+    let (Some(kind), Some(content)) = (row.kind, row.content) else {
+        return Ok(Err(format!(
+            "Revision {revision} is when /{name} was removed, pick one before it."
+        )));
+    };
+    if !guild_commands.contains_key(name) && guild_commands.len() >= MAX_GUILD_COMMANDS {
+        return Ok(Err(format!(
+            "A server can't have more than {MAX_GUILD_COMMANDS} commands, remove one to bring /{name} back."
+        )));
+    }
+
+    Ok(Ok(FoundRevision {
+        info: CommandInfo { kind, content },
+        attachment: row.attachment,
+    }))
+}
+
+/// Writes the revision found by [`find_revision`] back with a new revision and audit entry in one
+/// transaction. Returns the number of the new revision.
+async fn restore_revision(
+    conn: &mut SqliteConnection,
+    guild_id: GuildId,
+    name: &str,
+    revision: i64,
+    found: &FoundRevision,
+    author_id: UserId,
+) -> Result<i64> {
+    let guild = guild_id.get() as i64;
+    let info = &found.info;
+    let mut transaction = conn.begin().await?;
+    sqlx::query!(
+        r#"INSERT INTO SimpleCommands (guild_id, name, kind, content, attachment)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT (guild_id, name) DO UPDATE
+        SET kind = excluded.kind, content = excluded.content, attachment = excluded.attachment"#,
+        guild,
+        name,
+        info.kind,
+        info.content,
+        found.attachment
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    let new_revision = record_revision(
+        &mut *transaction,
+        guild_id,
+        name,
+        RevisionAction::Revert,
+        Some(info),
+        found.attachment.as_deref(),
+        Some(author_id),
+    )
+    .await?;
+
+    let details = format!("/{name} to revision {revision}: {}", info.content);
+    let event = Event::new(
+        Some(guild_id),
+        author_id,
+        AuditAction::CommandRevert,
+        details,
+    );
+    audit::record(&mut *transaction, &event).await?;
+    transaction.commit().await?;
+
+    Ok(new_revision)
+}
+
+fn describe(revision: &Revision) -> String {
+    let who = revision.author_id.map_or_else(
+        || "someone unknown".to_string(),
+        |id| Mention::User(id).to_string(),
+    );
+    let content = match (&revision.kind, &revision.content) {
+        (Some(kind), Some(content)) => {
+            format!(" ({kind:?}): {}", truncate(content, MAX_CONTENT_LENGTH))
+        }
+        _ => String::new(),
+    };
+
+    format!(
+        "**{}.** <t:{}:f> {} by {who}{content}",
+        revision.revision,
+        revision.created_at.and_utc().timestamp(),
+        revision.action.past_tense(),
+    )
+}
+
+async fn autocomplete_names<'a>(ctx: Context<'a>, partial: &'a str) -> Vec<String> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Vec::new();
+    };
+
+    fetch_names(&ctx.data().database, guild_id, &partial.to_lowercase())
+        .await
+        .unwrap_or_default()
+}
+
+/// The revisions of the command picked in the `name` option.
+async fn autocomplete_revisions<'a>(ctx: Context<'a>, partial: &'a str) -> Vec<AutocompleteChoice> {
+    let (Some(guild_id), poise::Context::Application(app_ctx)) = (ctx.guild_id(), ctx) else {
+        return Vec::new();
+    };
+    let name = app_ctx.args.iter().find_map(|option| match option.value {
+        poise::serenity_prelude::ResolvedValue::String(name) if option.name == "name" => Some(name),
+        _ => None,
+    });
+    let Some(name) = name else {
+        return Vec::new();
+    };
+
+    let revisions = fetch_revisions(&ctx.data().database, guild_id, &name.to_lowercase())
+        .await
+        .unwrap_or_default();
+    revisions
+        .into_iter()
+        .filter(|revision| revision.revision.to_string().starts_with(partial))
+        .take(25)
+        .map(|revision| {
+            let content = revision.content.as_deref().unwrap_or_default();
+            let label = format!(
+                "{}. {} {}: {content}",
+                revision.revision,
+                revision.action.past_tense(),
+                revision.created_at.format("%Y-%m-%d"),
+            );
+            AutocompleteChoice::new(truncate(&label, 100), revision.revision)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[tokio::test]
+    async fn revisions_are_numbered_per_command() {
+        let database = testing::database().await;
+        let guild_id = GuildId::new(1);
+        let author = Some(UserId::new(2));
+        let info = |content: &str| CommandInfo {
+            kind: CommandKind::Choice,
+            content: content.to_string(),
+        };
+
+        let changes = [
+            ("hi", RevisionAction::Add, Some(info("Hi|Hello"))),
+            ("bye", RevisionAction::Add, Some(info("Bye"))),
+            ("hi", RevisionAction::Edit, Some(info("Oops"))),
+            ("hi", RevisionAction::Remove, None),
+        ];
+        for (name, action, command) in &changes {
+            record_revision(
+                &database,
+                guild_id,
+                name,
+                *action,
+                command.as_ref(),
+                None,
+                author,
+            )
+            .await
+            .unwrap();
+        }
+
+        let revisions = fetch_revisions(&database, guild_id, "hi").await.unwrap();
+        let summary = revisions
+            .iter()
+            .map(|r| (r.revision, r.action, r.content.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (3, RevisionAction::Remove, None),
+                (2, RevisionAction::Edit, Some("Oops")),
+                (1, RevisionAction::Add, Some("Hi|Hello")),
+            ]
+        );
+        assert_eq!(revisions[0].author_id, author);
+
+        let names = fetch_names(&database, guild_id, "").await.unwrap();
+        assert_eq!(names, ["bye", "hi"]);
+        assert!(fetch_names(&database, guild_id, "h_")
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(fetch_names(&database, guild_id, "%")
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(fetch_names(&database, GuildId::new(9), "")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn removed_commands_only_come_back_within_the_limit() {
+        let database = testing::database().await;
+        let guild_id = GuildId::new(1);
+        let author = UserId::new(2);
+        let info = |content: &str| CommandInfo {
+            kind: CommandKind::Static,
+            content: content.to_string(),
+        };
+
+        let mut conn = database.acquire().await.unwrap();
+        let hi = info("Hi");
+        record_revision(
+            &mut *conn,
+            guild_id,
+            "hi",
+            RevisionAction::Add,
+            Some(&hi),
+            None,
+            Some(author),
+        )
+        .await
+        .unwrap();
+        record_revision(
+            &mut *conn,
+            guild_id,
+            "hi",
+            RevisionAction::Remove,
+            None,
+            None,
+            Some(author),
+        )
+        .await
+        .unwrap();
+
+        let full = (0..MAX_GUILD_COMMANDS)
+            .map(|i| (format!("c{i}"), info("Hey")))
+            .collect::<HashMap<_, _>>();
+        let found = find_revision(&mut *conn, &full, guild_id, "hi", 1)
+            .await
+            .unwrap();
+        assert!(found.is_err());
+
+        let none = HashMap::new();
+        let found = find_revision(&mut *conn, &none, guild_id, "hi", 2)
+            .await
+            .unwrap();
+        assert!(found.is_err(), "revision 2 is the removal");
+
+        let found = find_revision(&mut *conn, &none, guild_id, "hi", 1)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.info.content, "Hi");
+        let revision = restore_revision(&mut conn, guild_id, "hi", 1, &found, author)
+            .await
+            .unwrap();
+        assert_eq!(revision, 3);
+        let content = sqlx::query_scalar!("SELECT content FROM SimpleCommands WHERE name = 'hi'")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(content, "Hi");
+    }
+}
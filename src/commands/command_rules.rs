@@ -0,0 +1,209 @@
+use poise::serenity_prelude::{ChannelId, CreateEmbed, GuildId, RoleId};
+use poise::CreateReply;
+
+use super::DEFAULT_COMMANDS;
+use crate::{
+    audit::{AuditAction, Event},
+    command_rules::{Rule, RuleTarget},
+    common::bail_reply,
+    Context, Result,
+};
+
+#[poise::command(
+    guild_only,
+    slash_command,
+    prefix_command,
+    subcommands("allow", "deny", "remove", "list")
+)]
+pub async fn rules(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Allow a command, only in the given channels or for the given roles if there are any
+#[poise::command(guild_only, slash_command, prefix_command)]
+async fn allow(
+    ctx: Context<'_>,
+    #[description = "The command to allow"]
+    #[autocomplete = "autocomplete_command_names"]
+    command: String,
+    #[description = "Only allow it in this channel"] channel: Option<ChannelId>,
+    #[description = "Only allow it for this role"] role: Option<RoleId>,
+) -> Result<()> {
+    set_rule(ctx, command, channel, role, true).await
+}
+
+/// Disable a command on the server, in a channel or for a role
+#[poise::command(guild_only, slash_command, prefix_command)]
+async fn deny(
+    ctx: Context<'_>,
+    #[description = "The command to disable"]
+    #[autocomplete = "autocomplete_command_names"]
+    command: String,
+    #[description = "Only disable it in this channel"] channel: Option<ChannelId>,
+    #[description = "Only disable it for this role"] role: Option<RoleId>,
+) -> Result<()> {
+    set_rule(ctx, command, channel, role, false).await
+}
+
+/// Remove a rule, leave the channel and role empty for the server wide rule
+#[poise::command(guild_only, slash_command, prefix_command)]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "The command of the rule"]
+    #[autocomplete = "autocomplete_command_names"]
+    command: String,
+    #[description = "The channel of the rule"] channel: Option<ChannelId>,
+    #[description = "The role of the rule"] role: Option<RoleId>,
+) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /commands rules remove to be guild only.");
+
+    let command = command.trim_start_matches('/').to_lowercase();
+    let Some(target) = parse_target(channel, role) else {
+        return bail_reply(
+            ctx,
+            "A rule is either for a channel or for a role, not both.",
+        )
+        .await;
+    };
+
+    let details = format!("/{command} in {target}");
+    let event = Event::from_ctx(ctx, AuditAction::RuleRemove, details);
+    let removed = ctx
+        .data()
+        .command_rules
+        .remove(guild_id, &command, target, Some(&event))
+        .await?;
+
+    let msg = if removed {
+        format!("The rule for `/{command}` in {target} has been removed.")
+    } else {
+        format!("There is no rule for `/{command}` in {target}.")
+    };
+    bail_reply(ctx, msg).await
+}
+
+/// List the command rules of this server
+#[poise::command(guild_only, slash_command, prefix_command)]
+async fn list(ctx: Context<'_>) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /commands rules list to be guild only.");
+
+    let mut rules = ctx.data().command_rules.list(guild_id).await;
+    if rules.is_empty() {
+        return bail_reply(ctx, "Every command can be used everywhere.").await;
+    }
+    rules.sort_by(|a, b| a.command.cmp(&b.command));
+
+    let lines = rules
+        .iter()
+        .map(|rule| {
+            let verb = if rule.allow { "allowed" } else { "denied" };
+            format!("**/{}**: {verb} in {}", rule.command, rule.target)
+        })
+        .collect::<Vec<_>>();
+
+    let embed = CreateEmbed::default()
+        .title("Command rules")
+        .colour(0x77618F)
+        .description(lines.join("\n"));
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+async fn set_rule(
+    ctx: Context<'_>,
+    command: String,
+    channel: Option<ChannelId>,
+    role: Option<RoleId>,
+    allow: bool,
+) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /commands rules to be guild only.");
+
+    let command = command.trim_start_matches('/').to_lowercase();
+    if command == "commands" {
+        return bail_reply(ctx, "Rules can't apply to `/commands` itself.").await;
+    }
+    if !command_exists(ctx, guild_id, &command).await {
+        return bail_reply(ctx, format!("There is no `/{command}` command.")).await;
+    }
+    let Some(target) = parse_target(channel, role) else {
+        return bail_reply(
+            ctx,
+            "A rule is either for a channel or for a role, not both.",
+        )
+        .await;
+    };
+
+    let rule = Rule {
+        command: command.clone(),
+        target,
+        allow,
+    };
+    let verb = if allow { "allowed" } else { "denied" };
+    let details = format!("/{command} {verb} in {target}");
+    let event = Event::from_ctx(ctx, AuditAction::RuleChange, details);
+    ctx.data()
+        .command_rules
+        .set(guild_id, rule, Some(&event))
+        .await?;
+
+    let msg = match (target, allow) {
+        (RuleTarget::Guild, true) => format!("`/{command}` is allowed on the server."),
+        (RuleTarget::Guild, false) => format!("`/{command}` is disabled on the server."),
+        (_, true) => format!("`/{command}` is allowed in {target}."),
+        (_, false) => format!("`/{command}` is disabled in {target}."),
+    };
+    bail_reply(ctx, msg).await
+}
+
+fn parse_target(channel: Option<ChannelId>, role: Option<RoleId>) -> Option<RuleTarget> {
+    match (channel, role) {
+        (None, None) => Some(RuleTarget::Guild),
+        (Some(channel), None) => Some(RuleTarget::Channel(channel)),
+        (None, Some(role)) => Some(RuleTarget::Role(role)),
+        (Some(_), Some(_)) => None,
+    }
+}
+
+fn default_commands() -> &'static [String] {
+    DEFAULT_COMMANDS
+        .get()
+        .expect("Expected default commands to be initialized.")
+}
+
+async fn command_exists(ctx: Context<'_>, guild_id: GuildId, name: &str) -> bool {
+    if default_commands().iter().any(|n| n == name) {
+        return true;
+    }
+
+    let map = ctx.data().simple_commands.read().await;
+    map.get(&(guild_id.get() as i64))
+        .is_some_and(|commands| commands.contains_key(name))
+}
+
+async fn autocomplete_command_names<'a>(
+    ctx: Context<'a>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    let mut names = default_commands().to_vec();
+    if let Some(guild_id) = ctx.guild_id() {
+        let map = ctx.data().simple_commands.read().await;
+        if let Some(commands) = map.get(&(guild_id.get() as i64)) {
+            names.extend(commands.keys().cloned());
+        }
+    }
+    names.sort();
+
+    names
+        .into_iter()
+        .filter(move |name| name.starts_with(partial) && name != "commands")
+        .take(25)
+}
@@ -0,0 +1,355 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use poise::serenity_prelude::{CreateEmbed, GuildId, Mention, UserId};
+use poise::CreateReply;
+use sqlx::{Connection, SqliteConnection};
+
+use super::dynamic_commands::autocomplete_simple_command_names;
+use crate::{
+    common::{bail_reply, truncate},
+    metrics::timed_query,
+    Context, Result,
+};
+
+const DEFAULT_DAYS: u32 = 30;
+const MAX_DAYS: u32 = 365;
+const TOP_LENGTH: i64 = 10;
+/// How many unused commands are listed before just saying how many more there are.
+const MAX_UNUSED_LISTED: usize = 30;
+/// Discord refuses embed fields that are any longer.
+const MAX_FIELD_LENGTH: usize = 1024;
+
+/// How many times a simple command was used, including the use that was just recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Uses {
+    pub total: i64,
+    pub by_user: i64,
+}
+
+/// What `/commands stats` shows.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct UsageStats {
+    /// The most used commands, with how many times they were used.
+    pub top: Vec<(String, i64)>,
+    /// The commands nobody used, sorted by name.
+    pub unused: Vec<String>,
+    /// Who used the commands the most, with how many times they did.
+    pub invokers: Vec<(UserId, i64)>,
+}
+
+/// Records that `user_id` used the simple command `name`.
+pub async fn record_use(
+    conn: &mut SqliteConnection,
+    guild_id: GuildId,
+    name: &str,
+    user_id: UserId,
+) -> Result<Uses> {
+    let guild_id = guild_id.get() as i64;
+    let user_id = user_id.get() as i64;
+
+    let mut transaction = conn.begin().await?;
+    sqlx::query!(
+        "INSERT INTO SimpleCommandUsage (guild_id, name, user_id) VALUES (?, ?, ?)",
+        guild_id,
+        name,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    let uses = sqlx::query!(
+        r#"SELECT SUM(uses) as "total!: i64",
+            SUM(CASE WHEN user_id = ? THEN uses END) as "by_user!: i64"
+        FROM SimpleCommandUsage WHERE guild_id = ? AND name = ?"#,
+        user_id,
+        guild_id,
+        name
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+
+    Ok(Uses {
+        total: uses.total,
+        by_user: uses.by_user,
+    })
+}
+
+/// Folds the uses from before `before` into one row per command and user, they only count
+/// towards `{count}` after that. Rows that are already alone are left as they are. Returns how
+/// many rows were removed.
+pub async fn prune_usage(conn: &mut SqliteConnection, before: NaiveDateTime) -> Result<u64> {
+    let mut transaction = conn.begin().await?;
+    let last_id =
+        sqlx::query_scalar!(r#"SELECT COALESCE(MAX(id), 0) as "id!: i64" FROM SimpleCommandUsage"#)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+    let folded = sqlx::query!(
+        r#"INSERT INTO SimpleCommandUsage (guild_id, name, user_id, used_at, uses)
+        SELECT guild_id, name, user_id, MAX(used_at), SUM(uses) FROM SimpleCommandUsage
+        WHERE used_at < ? GROUP BY guild_id, name, user_id HAVING COUNT(*) > 1"#,
+        before
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    let removed = sqlx::query!(
+        r#"DELETE FROM SimpleCommandUsage WHERE used_at < ? AND id <= ?
+        AND (guild_id, name, user_id) IN (
+            SELECT guild_id, name, user_id FROM SimpleCommandUsage
+            WHERE used_at < ? AND id <= ? GROUP BY guild_id, name, user_id HAVING COUNT(*) > 1
+        )"#,
+        before,
+        last_id,
+        before,
+        last_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+
+    Ok(removed.rows_affected() - folded.rows_affected())
+}
+
+/// Folds the uses older than `/commands stats` can look back, see [`prune_usage`].
+pub async fn prune_old_usage(conn: &mut SqliteConnection) -> Result<u64> {
+    let before = (Utc::now() - Duration::days(MAX_DAYS.into())).naive_utc();
+    prune_usage(conn, before).await
+}
+
+/// How a guild's simple commands were used since `since`, the invokers only count `command`
+/// when there is one.
+pub async fn usage_stats(
+    conn: &mut SqliteConnection,
+    guild_id: GuildId,
+    since: NaiveDateTime,
+    command: Option<&str>,
+) -> Result<UsageStats> {
+    let guild_id = guild_id.get() as i64;
+
+    let top = sqlx::query!(
+        r#"SELECT name as "name!", SUM(uses) as "uses!: i64" FROM SimpleCommandUsage
+        WHERE guild_id = ? AND used_at >= ?
+        GROUP BY name ORDER BY 2 DESC, name LIMIT ?"#,
+        guild_id,
+        since,
+        TOP_LENGTH
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|row| (row.name, row.uses))
+    .collect();
+
+    let unused = sqlx::query_scalar!(
+        r#"SELECT name FROM SimpleCommands AS command WHERE guild_id = ? AND NOT EXISTS (
+            SELECT 1 FROM SimpleCommandUsage AS usage
+            WHERE usage.guild_id = command.guild_id AND usage.name = command.name
+            AND usage.used_at >= ?
+        ) ORDER BY name"#,
+        guild_id,
+        since
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let invokers = sqlx::query!(
+        r#"SELECT user_id as "user_id!", SUM(uses) as "uses!: i64" FROM SimpleCommandUsage
+        WHERE guild_id = ? AND used_at >= ? AND (? IS NULL OR name = ?)
+        GROUP BY user_id ORDER BY 2 DESC, user_id LIMIT ?"#,
+        guild_id,
+        since,
+        command,
+        command,
+        TOP_LENGTH
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|row| (UserId::new(row.user_id as u64), row.uses))
+    .collect();
+
+    Ok(UsageStats {
+        top,
+        unused,
+        invokers,
+    })
+}
+
+/// See which of the server's commands get used, and by whom
+#[poise::command(guild_only, slash_command, prefix_command)]
+pub async fn stats(
+    ctx: Context<'_>,
+    #[description = "How many days to look back, 30 by default"]
+    #[min = 1]
+    #[max = 365]
+    days: Option<u32>,
+    #[description = "Only count who used this command"]
+    #[autocomplete = "autocomplete_simple_command_names"]
+    command: Option<String>,
+) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /commands stats to be guild only.");
+
+    let days = days.unwrap_or(DEFAULT_DAYS).clamp(1, MAX_DAYS);
+    let since = (Utc::now() - Duration::days(days.into())).naive_utc();
+    let command = command.map(|c| c.trim_start_matches('/').to_lowercase());
+
+    let mut conn = ctx.data().database.acquire().await?;
+    let stats = timed_query(
+        "command_usage_stats",
+        usage_stats(&mut conn, guild_id, since, command.as_deref()),
+    )
+    .await?;
+
+    if stats.top.is_empty() && stats.unused.is_empty() {
+        return bail_reply(ctx, "This server has no commands of its own.").await;
+    }
+
+    let top = stats
+        .top
+        .iter()
+        .map(|(name, uses)| format!("**/{name}**: {uses}"))
+        .collect::<Vec<_>>();
+
+    let mut unused = stats
+        .unused
+        .iter()
+        .take(MAX_UNUSED_LISTED)
+        .map(|name| format!("/{name}"))
+        .collect::<Vec<_>>();
+    if stats.unused.len() > MAX_UNUSED_LISTED {
+        unused.push(format!(
+            "and {} more",
+            stats.unused.len() - MAX_UNUSED_LISTED
+        ));
+    }
+
+    let invokers = stats
+        .invokers
+        .iter()
+        .map(|(user_id, uses)| format!("{}: {uses}", Mention::User(*user_id)))
+        .collect::<Vec<_>>();
+    let invokers_title = match &command {
+        Some(command) => format!("Who used /{command} the most"),
+        None => "Who used them the most".to_string(),
+    };
+
+    let or_nothing = |lines: Vec<String>| match lines.is_empty() {
+        true => "Nothing".to_string(),
+        false => lines.join("\n"),
+    };
+    let embed = CreateEmbed::default()
+        .title(format!("Commands used in the last {days} days"))
+        .colour(0x77618F)
+        .field("Most used", or_nothing(top), true)
+        .field(
+            "Never used",
+            truncate(&or_nothing(unused), MAX_FIELD_LENGTH),
+            true,
+        )
+        .field(invokers_title, or_nothing(invokers), false);
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[tokio::test]
+    async fn uses_are_tallied() {
+        let database = testing::database().await;
+        let mut conn = database.acquire().await.unwrap();
+        let guild_id = GuildId::new(1);
+        let (mod_id, member_id) = (UserId::new(2), UserId::new(3));
+        sqlx::query(
+            r#"INSERT INTO SimpleCommands (guild_id, name, kind, content)
+            VALUES (1, 'hi', 'Static', 'Hi'), (1, 'bye', 'Static', 'Bye'),
+                (1, 'old', 'Static', 'Old'), (9, 'hi', 'Static', 'Hi');
+            INSERT INTO SimpleCommandUsage (guild_id, name, user_id, used_at)
+                VALUES (1, 'old', 2, '2020-01-01 00:00:00');"#,
+        )
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+
+        for (name, user_id) in [("hi", mod_id), ("hi", member_id), ("bye", member_id)] {
+            record_use(&mut conn, guild_id, name, user_id)
+                .await
+                .unwrap();
+        }
+        let uses = record_use(&mut conn, guild_id, "hi", member_id)
+            .await
+            .unwrap();
+        assert_eq!(
+            uses,
+            Uses {
+                total: 3,
+                by_user: 2
+            }
+        );
+
+        let since = NaiveDateTime::parse_from_str("2024-01-01 00:00:00", "%F %T").unwrap();
+        let stats = usage_stats(&mut conn, guild_id, since, None).await.unwrap();
+        assert_eq!(
+            stats,
+            UsageStats {
+                top: vec![("hi".to_string(), 3), ("bye".to_string(), 1)],
+                unused: vec!["old".to_string()],
+                invokers: vec![(member_id, 3), (mod_id, 1)],
+            }
+        );
+
+        let stats = usage_stats(&mut conn, guild_id, since, Some("bye"))
+            .await
+            .unwrap();
+        assert_eq!(stats.invokers, [(member_id, 1)]);
+    }
+
+    #[tokio::test]
+    async fn old_uses_are_folded() {
+        let database = testing::database().await;
+        let mut conn = database.acquire().await.unwrap();
+        let guild_id = GuildId::new(1);
+        sqlx::query(
+            r#"INSERT INTO SimpleCommands (guild_id, name, kind, content)
+                VALUES (1, 'hi', 'Static', 'Hi');
+            INSERT INTO SimpleCommandUsage (guild_id, name, user_id, used_at)
+                VALUES (1, 'hi', 2, '2020-01-01 00:00:00'), (1, 'hi', 2, '2020-02-01 00:00:00'),
+                    (1, 'hi', 3, '2020-03-01 00:00:00'), (1, 'hi', 2, '2024-06-01 00:00:00');"#,
+        )
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+
+        let before = NaiveDateTime::parse_from_str("2024-01-01 00:00:00", "%F %T").unwrap();
+        assert_eq!(prune_usage(&mut conn, before).await.unwrap(), 1);
+        let ids = sqlx::query_scalar!("SELECT id FROM SimpleCommandUsage ORDER BY id");
+        let folded = ids.fetch_all(&mut *conn).await.unwrap();
+        assert_eq!(folded.len(), 3);
+        assert_eq!(prune_usage(&mut conn, before).await.unwrap(), 0);
+        let ids = sqlx::query_scalar!("SELECT id FROM SimpleCommandUsage ORDER BY id");
+        assert_eq!(ids.fetch_all(&mut *conn).await.unwrap(), folded);
+
+        let uses = record_use(&mut conn, guild_id, "hi", UserId::new(2))
+            .await
+            .unwrap();
+        assert_eq!(
+            uses,
+            Uses {
+                total: 5,
+                by_user: 4
+            }
+        );
+        let stats = usage_stats(&mut conn, guild_id, before, None)
+            .await
+            .unwrap();
+        assert_eq!(stats.top, [("hi".to_string(), 2)]);
+    }
+}
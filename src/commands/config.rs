@@ -0,0 +1,91 @@
+use poise::serenity_prelude::CreateEmbed;
+use poise::CreateReply;
+
+use crate::{
+    audit::{AuditAction, Event},
+    common::bail_reply,
+    settings::{describe_guild_settings, describe_setting, Setting},
+    Context, Result,
+};
+
+#[poise::command(
+    guild_only,
+    slash_command,
+    prefix_command,
+    subcommands("get", "set", "list"),
+    required_permissions = "ADMINISTRATOR"
+)]
+pub async fn config(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Show the current value of a setting
+#[poise::command(guild_only, slash_command, prefix_command)]
+async fn get(
+    ctx: Context<'_>,
+    #[description = "The setting you want to see"] setting: Setting,
+) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /config get to be guild only.");
+
+    let value = ctx.data().guild_setting(guild_id, setting).await;
+    bail_reply(ctx, describe_setting(setting, value.as_deref())).await
+}
+
+/// Change a setting, leave the value empty to unset it
+#[poise::command(guild_only, slash_command, prefix_command)]
+async fn set(
+    ctx: Context<'_>,
+    #[description = "The setting you want to change"] setting: Setting,
+    #[description = "The new value, a mention or ID for roles and channels"] value: Option<String>,
+) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /config set to be guild only.");
+
+    let Some(value) = value else {
+        let event = Event::from_ctx(ctx, AuditAction::SettingChange, format!("{setting} unset"));
+        ctx.data()
+            .unset_guild_setting(guild_id, setting, Some(&event))
+            .await?;
+        return bail_reply(ctx, format!("**{setting}** has been unset.")).await;
+    };
+
+    let event = Event::from_ctx(
+        ctx,
+        AuditAction::SettingChange,
+        format!("{setting} → {value}"),
+    );
+    match ctx
+        .data()
+        .set_guild_setting(guild_id, setting, &value, Some(&event))
+        .await
+    {
+        Ok(display) => bail_reply(ctx, format!("**{setting}** has been set to {display}")).await,
+        Err(e) => bail_reply(ctx, e.to_string()).await,
+    }
+}
+
+/// List every setting for this server
+#[poise::command(guild_only, slash_command, prefix_command)]
+async fn list(ctx: Context<'_>) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /config list to be guild only.");
+
+    let fields = describe_guild_settings(ctx.data(), guild_id)
+        .await
+        .into_iter()
+        .map(|(name, value)| (name, value, false));
+
+    let embed = CreateEmbed::default()
+        .title("Server settings")
+        .colour(0x77618F)
+        .fields(fields);
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use poise::serenity_prelude::{CreateEmbed, User};
+use poise::{ChoiceParameter, CreateReply};
+
+use crate::{
+    audit::{AuditAction, Event},
+    common::bail_reply,
+    cooldowns::{format_duration, Action},
+    Context, Result,
+};
+
+#[poise::command(
+    guild_only,
+    slash_command,
+    prefix_command,
+    subcommands("reset", "set", "list"),
+    required_permissions = "ADMINISTRATOR"
+)]
+pub async fn cooldowns(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Clear a user's cooldowns
+#[poise::command(guild_only, slash_command, prefix_command)]
+async fn reset(
+    ctx: Context<'_>,
+    #[description = "The user whose cooldowns will be cleared"] user: User,
+    #[description = "Only clear this cooldown"] action: Option<Action>,
+) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /cooldowns reset to be guild only.");
+
+    let details = action.map_or_else(|| "every cooldown".to_string(), |action| action.to_string());
+    let event = Event::from_ctx(ctx, AuditAction::CooldownReset, details).target(user.id);
+    let cooldowns = &ctx.data().cooldowns;
+    let removed = match cooldowns
+        .reset(guild_id, user.id, action, Some(&event))
+        .await
+    {
+        Ok(removed) => removed,
+        Err(e) => return bail_reply(ctx, e.to_string()).await,
+    };
+
+    let msg = match (removed, action) {
+        (0, Some(action)) => format!("{} wasn't on the {action} cooldown.", user.name),
+        (0, None) => format!("{} wasn't on cooldown.", user.name),
+        (_, Some(action)) => format!("The {action} cooldown of {} has been reset.", user.name),
+        (_, None) => format!("Every cooldown of {} has been reset.", user.name),
+    };
+    bail_reply(ctx, msg).await
+}
+
+/// Change how long a cooldown lasts on this server, leave it empty to use the default
+#[poise::command(guild_only, slash_command, prefix_command)]
+async fn set(
+    ctx: Context<'_>,
+    #[description = "The cooldown to change"] action: Action,
+    #[description = "The new duration in seconds"]
+    #[max = 31_536_000]
+    seconds: Option<u64>,
+) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /cooldowns set to be guild only.");
+
+    let cooldowns = &ctx.data().cooldowns;
+    let duration = seconds.map(Duration::from_secs);
+    let details = match duration {
+        Some(duration) => format!("{action} → {}", format_duration(duration)),
+        None => format!("{action} → default"),
+    };
+    let event = Event::from_ctx(ctx, AuditAction::CooldownChange, details);
+    let set = cooldowns.set_duration(guild_id, action, duration, Some(&event));
+    if let Err(e) = set.await {
+        return bail_reply(ctx, e.to_string()).await;
+    }
+
+    let duration = cooldowns.duration(Some(guild_id), action).await;
+    let msg = format!(
+        "The {action} cooldown now lasts {}.",
+        format_duration(duration)
+    );
+    bail_reply(ctx, msg).await
+}
+
+/// List how long every cooldown lasts on this server
+#[poise::command(guild_only, slash_command, prefix_command)]
+async fn list(ctx: Context<'_>) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /cooldowns list to be guild only.");
+
+    let mut lines = Vec::new();
+    for action in Action::list()
+        .into_iter()
+        .filter_map(|choice| Action::from_name(&choice.name))
+    {
+        let duration = match action {
+            Action::DinoHatch => "until midnight UTC".to_string(),
+            _ if action.is_shared() => format!(
+                "{}, shared by every server",
+                format_duration(ctx.data().cooldowns.duration(None, action).await)
+            ),
+            _ => format_duration(ctx.data().cooldowns.duration(Some(guild_id), action).await),
+        };
+        lines.push(format!("**{action}**: {duration}"));
+    }
+
+    let embed = CreateEmbed::default()
+        .title("Cooldowns")
+        .colour(0x77618F)
+        .description(lines.join("\n"));
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
@@ -10,6 +10,7 @@ use sqlx::SqliteConnection;
 
 use crate::commands::dino::{COVET_BUTTON, FAVOURITE_BUTTON, SHUN_BUTTON};
 use crate::common::{embed_message, ephemeral_text_message, response, update_response};
+use crate::metrics::{metrics, timed_query};
 use crate::Data;
 use crate::Result;
 
@@ -45,15 +46,26 @@ impl Display for TransactionType {
 
 pub async fn setup_dino_collector(ctx: &serenity::Context, user_data: &Data) -> Result<()> {
     let mut collector = ComponentInteractionCollector::new(ctx)
-        .filter(|f| f.data.custom_id.starts_with("dino-"))
+        .filter(|f| {
+            let matches = f.data.custom_id.starts_with("dino-");
+            if matches {
+                metrics().collector_received("dino");
+            }
+            matches
+        })
         .stream();
 
-    println!("Setup dino collector");
+    tracing::info!("Setup dino collector");
 
     while let Some(interaction) = collector.next().await {
         if let Err(e) = handle_dino_collector(ctx, user_data, &interaction).await {
-            eprintln!("Error while handling dino collection: {e}");
+            tracing::error!(
+                user = interaction.user.id.get(),
+                custom_id = interaction.data.custom_id,
+                "Error while handling dino collection: {e}"
+            );
         }
+        metrics().collector_handled("dino");
     }
 
     Ok(())
@@ -119,7 +131,8 @@ async fn handle_dino_collector(
                 .title(&dino_name)
                 .footer(CreateEmbedFooter::new(format!(
                     "{dino_name} is worth {} Dino Bucks!\nHotness Rating: {}",
-                    quirkify_worth(gifts + 1), // +1 because the hatcher is also an owner
+                    // +1 because the hatcher is also an owner
+                    quirkify_worth(gifts + 1, &mut user_data.game_rng("dino_worth")),
                     quirkify_hotness(hotness)
                 )));
 
@@ -179,7 +192,7 @@ async fn fetch_transaction(
     transaction_type: &TransactionType,
 ) -> Result<Option<i64>> {
     let transaction_type = transaction_type.to_string();
-    let row = sqlx::query!(
+    let query = sqlx::query!(
         r#"INSERT OR IGNORE INTO DinoUser (id) VALUES (?);
         SELECT id FROM DinoTransactions WHERE type = ? AND dino_id = ? AND user_id = ?"#,
         user_id,
@@ -187,16 +200,16 @@ async fn fetch_transaction(
         dino_id,
         user_id
     )
-    .fetch_optional(conn)
-    .await?;
+    .fetch_optional(conn);
+    let row = timed_query("fetch_transaction", query).await?;
 
     Ok(row.map(|r| r.id))
 }
 
 async fn delete_transaction(conn: &mut SqliteConnection, transaction_id: i64) -> Result<()> {
-    sqlx::query!("DELETE FROM DinoTransactions WHERE id = ?", transaction_id)
-        .execute(conn)
-        .await?;
+    let query =
+        sqlx::query!("DELETE FROM DinoTransactions WHERE id = ?", transaction_id).execute(conn);
+    timed_query("delete_transaction", query).await?;
 
     Ok(())
 }
@@ -208,25 +221,25 @@ async fn create_transaction(
     transaction_type: &TransactionType,
 ) -> Result<()> {
     let transaction_type = transaction_type.to_string();
-    sqlx::query!(
+    let query = sqlx::query!(
         "INSERT INTO DinoTransactions (user_id, dino_id, type) VALUES (?, ?, ?)",
         user_id,
         dino_id,
         transaction_type
     )
-    .execute(conn)
-    .await?;
+    .execute(conn);
+    timed_query("create_transaction", query).await?;
 
     Ok(())
 }
 
 async fn calculate_dino_score(conn: &mut SqliteConnection, dino_id: i64) -> Result<(i64, i64)> {
-    let row = sqlx::query!(
+    let query = sqlx::query!(
         r#"SELECT COUNT(id) as count, type as type_ FROM DinoTransactions WHERE dino_id = ? GROUP BY type"#,
         dino_id
     )
-    .fetch_all(&mut *conn)
-    .await?;
+    .fetch_all(&mut *conn);
+    let row = timed_query("calculate_dino_score", query).await?;
 
     let mut covets = 0;
     let mut shuns = 0;
@@ -248,9 +261,9 @@ async fn calculate_dino_score(conn: &mut SqliteConnection, dino_id: i64) -> Resu
 }
 
 async fn update_dino_score(conn: &mut SqliteConnection, dino_id: i64, hotness: i64) -> Result<()> {
-    sqlx::query!("UPDATE Dino SET hotness = ? WHERE id = ?", hotness, dino_id)
-        .execute(conn)
-        .await?;
+    let query =
+        sqlx::query!("UPDATE Dino SET hotness = ? WHERE id = ?", hotness, dino_id).execute(conn);
+    timed_query("update_dino_score", query).await?;
 
     Ok(())
 }
@@ -259,9 +272,9 @@ async fn fetch_dino_names(
     conn: &mut SqliteConnection,
     dino_id: i64,
 ) -> Result<Option<(String, String)>> {
-    let row = sqlx::query!("SELECT name, filename FROM Dino WHERE id = ?", dino_id)
-        .fetch_optional(conn)
-        .await?;
+    let query =
+        sqlx::query!("SELECT name, filename FROM Dino WHERE id = ?", dino_id).fetch_optional(conn);
+    let row = timed_query("fetch_dino_names", query).await?;
 
     Ok(row.map(|r| (r.name, r.filename)))
 }
@@ -1,25 +1,35 @@
 use anyhow::{bail, Context as AnyhowContext};
 use chrono::{NaiveDateTime, Utc};
-use image::{imageops::overlay, io::Reader, ImageBuffer, ImageOutputFormat, RgbaImage};
+use image::{
+    imageops::overlay, io::Reader, DynamicImage, ImageBuffer, ImageOutputFormat, RgbaImage,
+};
 use poise::serenity_prelude::{
     ButtonStyle, CreateActionRow, CreateAttachment, CreateButton, CreateEmbed, CreateEmbedAuthor,
-    CreateEmbedFooter, User, UserId,
+    CreateEmbedFooter, GuildId, User, UserId,
 };
 use poise::CreateReply;
-use rand::{seq::SliceRandom, thread_rng};
+use rand::{seq::SliceRandom, Rng};
 use sqlx::error::DatabaseError;
 use sqlx::sqlite::SqliteError;
-use sqlx::{FromRow, QueryBuilder, Row, Sqlite, SqliteExecutor, SqlitePool};
+use sqlx::{FromRow, QueryBuilder, Row, Sqlite, SqliteExecutor, SqlitePool, Transaction};
 
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::OnceLock;
 
+use crate::commands::forgetme::UNOWNED;
 use crate::common::{bail_reply, embed_message, ephemeral_text_message, response};
 use crate::{
+    audit::{self, AuditAction, Event},
     common::{avatar_url, ephemeral_reply, name as get_name, pick_best_x_dice_rolls},
-    Context, Result, SUB_ROLE_ID,
+    config::Assets,
+    cooldowns::{try_again, Action, Cooldowns},
+    i18n,
+    metrics::{timed_query, timed_task},
+    settings::Setting,
+    wallet::{self, Reason},
+    Context, Result,
 };
 
 #[derive(Debug, Default)]
@@ -27,6 +37,9 @@ struct Fragments {
     bodies: Vec<PathBuf>,
     mouths: Vec<PathBuf>,
     eyes: Vec<PathBuf>,
+
+    fragment_dir: PathBuf,
+    output_dir: PathBuf,
 }
 
 #[derive(Debug)]
@@ -37,9 +50,6 @@ struct DinoParts {
     name: String,
 }
 
-const FRAGMENT_PATH: &str = "./assets/dino/fragments";
-const OUTPUT_PATH: &str = "./assets/dino/complete";
-
 static DINO_FRAGMENTS: OnceLock<Fragments> = OnceLock::new();
 
 const DINO_IMAGE_SIZE: u32 = 112;
@@ -48,21 +58,24 @@ const ROW_MARGIN: u32 = 2;
 
 const MAX_GENERATION_ATTEMPTS: usize = 20;
 const MAX_FAILED_HATCHES: i64 = 3;
-const HATCH_FAILS_TEXT: &[&str; 3] = &["1st", "2nd", "3rd"];
 const MAX_DINO_WORTH_EXPONENT: f64 = 30.0;
 
-// const GIFTING_COOLDOWN: Duration = Duration::from_secs(60 * 60);
-// const SLURP_COOLDOWN: Duration = Duration::from_secs(60 * 60);
-
 pub const COVET_BUTTON: &str = "dino-covet";
 pub const SHUN_BUTTON: &str = "dino-shun";
 pub const FAVOURITE_BUTTON: &str = "dino-favourite";
 
-pub fn setup_dinos() -> Result<()> {
-    let fragments_dir =
-        std::fs::read_dir(FRAGMENT_PATH).context("Failed to read dino fragment directory")?;
+pub fn setup_dinos(assets: &Assets) -> Result<()> {
+    let fragments_dir = std::fs::read_dir(&assets.dino_fragments)
+        .context("Failed to read dino fragment directory")?;
+
+    std::fs::create_dir_all(&assets.dino_output)
+        .context("Failed to create the dino output directory")?;
 
-    let mut fragments = Fragments::default();
+    let mut fragments = Fragments {
+        fragment_dir: assets.dino_fragments.clone(),
+        output_dir: assets.dino_output.clone(),
+        ..Default::default()
+    };
 
     for entry in fragments_dir {
         let Ok(entry) = entry else {
@@ -88,6 +101,14 @@ pub fn setup_dinos() -> Result<()> {
     Ok(())
 }
 
+fn fragment_path() -> &'static Path {
+    &DINO_FRAGMENTS.get().unwrap().fragment_dir
+}
+
+fn output_path() -> &'static Path {
+    &DINO_FRAGMENTS.get().unwrap().output_dir
+}
+
 #[poise::command(
     slash_command,
     guild_only,
@@ -97,94 +118,32 @@ pub async fn dino(_ctx: Context<'_>) -> Result<()> {
     Ok(())
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Timings {
-    attempt: NaiveDateTime,
-    reset: NaiveDateTime,
-    next_try: i64,
-    kind: UserAction,
+async fn ensure_off_cooldown(ctx: Context<'_>, action: Action, reason: &str) -> Result<()> {
+    ctx.data()
+        .cooldowns
+        .ensure_ready(ctx.guild_id(), ctx.author().id, action, reason)
+        .await
 }
 
-impl Timings {
-    fn hatch(user_record: &UserRecord) -> Self {
-        let attempt = Utc::now().naive_utc();
-        let last_hatch_date = user_record.last_hatch.date();
-        // At midnight the day after the last hatch
-        let reset_time = last_hatch_date.and_hms_opt(0, 0, 0).unwrap() + chrono::Duration::days(1);
-        // At midnight the day after this most recent attempt
-        let next_try = (attempt.date().and_hms_opt(0, 0, 0).unwrap() + chrono::Duration::days(1))
-            .and_utc()
-            .timestamp();
-
-        Timings {
-            attempt,
-            reset: reset_time,
-            next_try,
-            kind: UserAction::Hatch(0),
-        }
-    }
-
-    fn slurp(user_record: &UserRecord) -> Self {
-        let attempt = Utc::now().naive_utc();
-        let slurp_cooldown_duration = chrono::Duration::minutes(60);
-        let time_until_next_slurp = user_record.last_slurp + slurp_cooldown_duration;
-
-        Timings {
-            attempt,
-            reset: time_until_next_slurp,
-            next_try: time_until_next_slurp.and_utc().timestamp(),
-            kind: UserAction::Slurp,
-        }
-    }
-
-    fn gift(user_record: &UserRecord) -> Self {
-        let attempt = Utc::now().naive_utc();
-        let gifting_cooldown_duration = chrono::Duration::minutes(60);
-        let time_until_next_gift = user_record.last_gifting + gifting_cooldown_duration;
-
-        Timings {
-            attempt,
-            reset: time_until_next_gift,
-            next_try: time_until_next_gift.and_utc().timestamp(),
-            kind: UserAction::Gift,
-        }
-    }
-
-    fn ensure_outside_cooldown(&self) -> Result<()> {
-        if self.attempt < self.reset {
-            match self.kind {
-                UserAction::Hatch(_) => bail!(
-                    "Dont be greedy! You can hatch again <t:{}:R>.",
-                    self.next_try
-                ),
-                UserAction::Slurp => bail!(
-                    "Don't be greedy! You can slurp again <t:{}:R>",
-                    self.next_try
-                ),
-                UserAction::Gift => bail!(
-                    "You're too kind, you're gifting too often. You can gift again <t:{}:R>",
-                    self.next_try
-                ),
-            }
-        };
-
-        Ok(())
-    }
+async fn start_cooldown(
+    ctx: Context<'_>,
+    executor: impl SqliteExecutor<'_>,
+    action: Action,
+) -> Result<NaiveDateTime> {
+    ctx.data()
+        .cooldowns
+        .start(executor, ctx.guild_id(), ctx.author().id, action)
+        .await
 }
 
 struct DinoUser {
     id: String,
     record: UserRecord,
-    timings: Timings,
 }
 
 impl DinoUser {
-    fn new(id: String, timings: Timings, record: UserRecord) -> Self {
-        Self {
-            id,
-            record,
-            timings,
-        }
+    fn new(id: String, record: UserRecord) -> Self {
+        Self { id, record }
     }
 }
 
@@ -195,20 +154,26 @@ async fn hatch(ctx: Context<'_>) -> Result<()> {
     let author_id = author.id.to_string();
 
     let db = &ctx.data().database;
-    let hatcher = get_user_record(&ctx.data().database, &author_id).await?;
+    let hatcher = timed_query(
+        "get_user_record",
+        get_user_record(&ctx.data().database, &author_id),
+    )
+    .await?;
 
-    let user = DinoUser::new(author_id, Timings::hatch(&hatcher), hatcher);
+    let user = DinoUser::new(author_id, hatcher);
 
-    if let Err(e) = user.timings.ensure_outside_cooldown() {
+    if let Err(e) = ensure_off_cooldown(ctx, Action::DinoHatch, "Dont be greedy!").await {
         return bail_reply(ctx, e.to_string()).await;
     }
 
-    if let Err(e) = try_hatching(db, ctx, &user).await {
+    let mut rng = ctx.data().game_rng("dino_hatch");
+    if let Err(e) = try_hatching(db, ctx, &user, &mut rng).await {
+        rng.audit(&e);
         ctx.say(e.to_string()).await?;
         return Ok(());
     }
 
-    let Some(parts) = generate_dino(db).await? else {
+    let Some(parts) = timed_task("generate_dino", generate_dino(db, &mut rng)).await? else {
         let msg =
             "I tried really hard but i wasn't able to make a unique dino for you. Sorry... :'(";
         return bail_reply(ctx, msg).await;
@@ -218,8 +183,22 @@ async fn hatch(ctx: Context<'_>) -> Result<()> {
 
     let mut transaction = ctx.data().database.begin().await?;
 
-    let dino = insert_dino(&mut transaction, &user.id, &parts, &image_path, None).await?;
-    update_last_user_action(&mut transaction, &user.id, UserAction::Hatch(0)).await?;
+    let dino = timed_query(
+        "insert_dino",
+        insert_dino(&mut transaction, &user.id, &parts, &image_path, None),
+    )
+    .await?;
+    set_consecutive_fails(&mut *transaction, &user.id, 0).await?;
+    start_cooldown(ctx, &mut *transaction, Action::DinoHatch).await?;
+    wallet::pay_out(
+        &mut transaction,
+        author.id,
+        ctx.data().config.payouts.dino_hatch,
+        Reason::DinoHatch,
+        &dino.id.to_string(),
+    )
+    .await?;
+    rng.audit(format!("Hatched {}", dino.name));
 
     let author_name = get_name(&ctx, author).await;
     let message = send_dino_embed(
@@ -228,7 +207,7 @@ async fn hatch(ctx: Context<'_>) -> Result<()> {
         &author_name,
         &avatar_url(author),
         &image_path,
-        user.timings.attempt,
+        Utc::now().naive_utc(),
     )
     .await?;
 
@@ -320,6 +299,10 @@ async fn rename(
         return Err(e);
     }
 
+    let details = format!("{} → {replacement}", dino.name);
+    let event = Event::from_ctx(ctx, AuditAction::DinoRename, details);
+    audit::record(&ctx.data().database, &event).await?;
+
     ctx.send(ephemeral_reply(format!(
         "**{}** name has been update to **{}**!",
         dino.name, replacement
@@ -342,18 +325,20 @@ async fn view(
         return bail_reply(ctx, msg).await;
     };
 
-    let owner_user_id = UserId::from_str(&dino.owner_id)?;
-    let (user_name, user_avatar) = match owner_user_id.to_user(&ctx).await {
-        Ok(user) => (get_name(&ctx, &user).await, avatar_url(&user)),
-        Err(_) => {
-            eprintln!("Could not find user with id: {owner_user_id}. Using a default owner name for this dino.");
-            (
-                "unknown user".to_string(),
-                "https://cdn.discordapp.com/embed/avatars/0.png".to_string(),
-            )
+    let default_avatar = "https://cdn.discordapp.com/embed/avatars/0.png".to_string();
+    let (user_name, user_avatar) = if dino.owner_id == UNOWNED {
+        ("nobody".to_string(), default_avatar)
+    } else {
+        let owner_user_id = UserId::from_str(&dino.owner_id)?;
+        match owner_user_id.to_user(&ctx).await {
+            Ok(user) => (get_name(&ctx, &user).await, avatar_url(&user)),
+            Err(_) => {
+                tracing::warn!("Could not find user with id: {owner_user_id}. Using a default owner name for this dino.");
+                ("unknown user".to_string(), default_avatar)
+            }
         }
     };
-    let image_path = Path::new(OUTPUT_PATH).join(&dino.filename);
+    let image_path = output_path().join(&dino.filename);
 
     send_dino_embed(
         ctx,
@@ -377,10 +362,8 @@ async fn gift(
     dino: String,
     #[description = "The person who will receive the dino"] recipient: User,
 ) -> Result<()> {
-    let user_record = get_user_record(&ctx.data().database, &ctx.author().id.to_string()).await?;
-    let timings = Timings::gift(&user_record);
-
-    if let Err(e) = timings.ensure_outside_cooldown() {
+    let reason = "You're too kind, you're gifting too often.";
+    if let Err(e) = ensure_off_cooldown(ctx, Action::DinoGift, reason).await {
         return bail_reply(ctx, e.to_string()).await;
     }
 
@@ -402,7 +385,9 @@ async fn gift(
         &recipient.id.to_string(),
     )
     .await?;
-    update_last_user_action(&mut transaction, &author_id, UserAction::Gift).await?;
+    start_cooldown(ctx, &mut *transaction, Action::DinoGift).await?;
+    let event = Event::from_ctx(ctx, AuditAction::DinoGift, &dino).target(recipient.id);
+    audit::record(&mut *transaction, &event).await?;
 
     let sender_name = get_name(&ctx, ctx.author()).await;
     let receiver_name = get_name(&ctx, &recipient).await;
@@ -438,51 +423,48 @@ async fn slurp(
         return bail_reply(ctx, "You can't slurp the same dino twice, you cheater!").await;
     }
 
-    let user_record = get_user_record(&ctx.data().database, &ctx.author().id.to_string()).await?;
-    let timings = Timings::slurp(&user_record);
-
-    if let Err(e) = timings.ensure_outside_cooldown() {
+    if let Err(e) = ensure_off_cooldown(ctx, Action::DinoSlurp, "Don't be greedy!").await {
         return bail_reply(ctx, e.to_string()).await;
     }
 
-    let Some(first_dino) = get_dino_record(&ctx.data().database, &first).await? else {
-        return bail_reply(ctx, format!("Could not find a dino named {first}.")).await;
-    };
-
     let author_id = ctx.author().id.to_string();
-
-    if first_dino.owner_id != author_id {
-        let msg =
-            format!("Doesn't seem you own {first}, are you trying to pull a fast one on me?!");
-        return bail_reply(ctx, msg).await;
-    }
-
-    let Some(second_dino) = get_dino_record(&ctx.data().database, &second).await? else {
-        return bail_reply(ctx, format!("Could not find a dino named {second}.")).await;
+    let first_dino = get_dino_record(&ctx.data().database, &first).await?;
+    let first_dino = match ensure_owned(first_dino, &first, &author_id) {
+        Ok(dino) => dino,
+        Err(msg) => return bail_reply(ctx, msg).await,
+    };
+    let second_dino = get_dino_record(&ctx.data().database, &second).await?;
+    let second_dino = match ensure_owned(second_dino, &second, &author_id) {
+        Ok(dino) => dino,
+        Err(msg) => return bail_reply(ctx, msg).await,
     };
 
-    if second_dino.owner_id != author_id {
-        let msg =
-            format!("Doesn't seem you own {second}, are you trying to pull a fast one on me?!");
-        return bail_reply(ctx, msg).await;
-    }
-
-    let parts = generate_dino(&ctx.data().database).await?;
-    if parts.is_none() {
+    let mut rng = ctx.data().game_rng("dino_slurp");
+    let Some(parts) = timed_task(
+        "generate_dino",
+        generate_dino(&ctx.data().database, &mut rng),
+    )
+    .await?
+    else {
         let msg =
             "I tried really hard but i wasn't able to make a unique dino for you. Sorry... :'(";
         return bail_reply(ctx, msg).await;
-    }
+    };
 
     let mut transaction = ctx.data().database.begin().await?;
-    delete_dino(&mut transaction, first_dino.id).await?;
-    delete_dino(&mut transaction, second_dino.id).await?;
-
-    let parts = parts.unwrap();
-    let image_path = generate_dino_image(&parts)?;
-
-    let dino = insert_dino(&mut transaction, &author_id, &parts, &image_path, None).await?;
-    update_last_user_action(&mut transaction, &author_id, UserAction::Slurp).await?;
+    let (dino, image_path) = slurp_dinos(
+        &mut transaction,
+        &ctx.data().cooldowns,
+        ctx.guild_id(),
+        ctx.author().id,
+        [&first_dino, &second_dino],
+        &parts,
+    )
+    .await?;
+    let details = format!("{first} and {second} into {}", dino.name);
+    let event = Event::from_ctx(ctx, AuditAction::DinoSlurp, &details);
+    audit::record(&mut *transaction, &event).await?;
+    rng.audit(format!("Slurped {details}"));
 
     let author_name = get_name(&ctx, ctx.author()).await;
     let message = send_dino_embed(
@@ -502,14 +484,55 @@ async fn slurp(
     Ok(())
 }
 
+/// The message for the user if they can't slurp this dino.
+fn ensure_owned(
+    dino: Option<DinoRecord>,
+    name: &str,
+    owner_id: &str,
+) -> std::result::Result<DinoRecord, String> {
+    match dino {
+        None => Err(format!("Could not find a dino named {name}.")),
+        Some(dino) if dino.owner_id != owner_id => Err(format!(
+            "Doesn't seem you own {name}, are you trying to pull a fast one on me?!"
+        )),
+        Some(dino) => Ok(dino),
+    }
+}
+
+/// Replaces the sacrificed dinos with a new one made of `parts`, nothing is kept unless the
+/// transaction is committed.
+async fn slurp_dinos(
+    transaction: &mut Transaction<'_, Sqlite>,
+    cooldowns: &Cooldowns,
+    guild_id: Option<GuildId>,
+    user_id: UserId,
+    sacrifices: [&DinoRecord; 2],
+    parts: &DinoParts,
+) -> Result<(DinoRecord, PathBuf)> {
+    for dino in sacrifices {
+        delete_dino(&mut **transaction, dino.id).await?;
+    }
+
+    let image_path = generate_dino_image(parts)?;
+    let owner_id = user_id.to_string();
+
+    let dino = timed_query(
+        "insert_dino",
+        insert_dino(&mut **transaction, &owner_id, parts, &image_path, None),
+    )
+    .await?;
+    cooldowns
+        .start(&mut **transaction, guild_id, user_id, Action::DinoSlurp)
+        .await?;
+
+    Ok((dino, image_path))
+}
+
 /// Sacrifice all your non favourite dinos to create new ones (2 -> 1)
 #[poise::command(guild_only, slash_command, prefix_command)]
 async fn slurpening(ctx: Context<'_>) -> Result<()> {
     let user_id = ctx.author().id.to_string();
-    let user_record = get_user_record(&ctx.data().database, &user_id).await?;
-    let timings = Timings::slurp(&user_record);
-
-    if let Err(e) = timings.ensure_outside_cooldown() {
+    if let Err(e) = ensure_off_cooldown(ctx, Action::DinoSlurp, "Don't be greedy!").await {
         return bail_reply(ctx, e.to_string()).await;
     }
 
@@ -564,6 +587,7 @@ async fn slurpening(ctx: Context<'_>) -> Result<()> {
             continue;
         }
 
+        let mut rng = ctx.data().game_rng("dino_slurpening");
         let mut transaction = ctx.data().database.begin().await?;
 
         for dino in sacrifices.iter() {
@@ -574,7 +598,12 @@ async fn slurpening(ctx: Context<'_>) -> Result<()> {
 
         let mut created_dinos = Vec::with_capacity(num_to_create);
         for _ in 0..num_to_create {
-            let Some(parts) = generate_dino(&ctx.data().database).await? else {
+            let Some(parts) = timed_task(
+                "generate_dino",
+                generate_dino(&ctx.data().database, &mut rng),
+            )
+            .await?
+            else {
                 interaction
                     .create_response(
                         ctx,
@@ -587,12 +616,15 @@ async fn slurpening(ctx: Context<'_>) -> Result<()> {
             };
 
             let file_path = generate_dino_image(&parts)?;
-            let inserted_dino = insert_dino(
-                &mut transaction,
-                &user_id,
-                &parts,
-                &file_path,
-                Some(&message_link),
+            let inserted_dino = timed_query(
+                "insert_dino",
+                insert_dino(
+                    &mut transaction,
+                    &user_id,
+                    &parts,
+                    &file_path,
+                    Some(&message_link),
+                ),
             )
             .await?;
             created_dinos.push(inserted_dino);
@@ -609,6 +641,10 @@ async fn slurpening(ctx: Context<'_>) -> Result<()> {
             .map(|d| d.name.as_ref())
             .collect::<Vec<_>>()
             .join(", ");
+        rng.audit(format!("Slurpened into {new_dino_names}"));
+        let details = format!("{dinos_at_risk} into {new_dino_names}");
+        let event = Event::from_ctx(ctx, AuditAction::DinoSlurp, details);
+        audit::record(&mut *transaction, &event).await?;
         let embed = CreateEmbed::default()
             .colour(0xffbf00)
             .author(CreateEmbedAuthor::new(&author_name).icon_url(author_avatar))
@@ -623,7 +659,7 @@ async fn slurpening(ctx: Context<'_>) -> Result<()> {
             )
             .await?;
 
-        update_last_user_action(&mut transaction, &user_id, UserAction::Slurp).await?;
+        start_cooldown(ctx, &mut *transaction, Action::DinoSlurp).await?;
         transaction.commit().await?;
 
         return Ok(());
@@ -640,48 +676,33 @@ async fn slurpening(ctx: Context<'_>) -> Result<()> {
     Ok(())
 }
 
-#[derive(Debug, Clone, Copy)]
-enum UserAction {
-    Hatch(i64),
-    Slurp,
-    Gift,
-}
-
-impl UserAction {
-    fn to_update_query(self) -> String {
-        match self {
-            UserAction::Hatch(consecutive_fails) => {
-                format!("last_hatch = datetime('now'), consecutive_fails = {consecutive_fails}")
-            }
-            UserAction::Slurp => "last_slurp = datetime('now')".to_string(),
-            UserAction::Gift => "last_gifting = datetime('now')".to_string(),
-        }
-    }
-}
-
-async fn update_last_user_action(
+/// How many hatches failed in a row, the next one gets likelier with each of them.
+async fn set_consecutive_fails(
     executor: impl SqliteExecutor<'_>,
     user_id: &str,
-    action: UserAction,
+    consecutive_fails: i64,
 ) -> Result<()> {
-    let mut query = QueryBuilder::new(format!(
-        "UPDATE DinoUser SET {} WHERE id = ",
-        action.to_update_query()
-    ));
-    query.push_bind(user_id);
-
-    query.build().execute(executor).await?;
+    let query = sqlx::query!(
+        "UPDATE DinoUser SET consecutive_fails = ? WHERE id = ?",
+        consecutive_fails,
+        user_id
+    )
+    .execute(executor);
+    timed_query("set_consecutive_fails", query).await?;
 
     Ok(())
 }
 
-async fn generate_dino(executor: impl SqliteExecutor<'_> + Copy) -> Result<Option<DinoParts>> {
+async fn generate_dino(
+    executor: impl SqliteExecutor<'_> + Copy,
+    rng: &mut impl Rng,
+) -> Result<Option<DinoParts>> {
     let mut tries = 0;
 
     let fragments = DINO_FRAGMENTS.get().unwrap();
 
     loop {
-        let mut generated = choose_parts(fragments);
+        let mut generated = choose_parts(fragments, rng);
         let duplicate_parts = are_parts_duplicate(executor, &generated).await?;
 
         if !duplicate_parts {
@@ -708,41 +729,40 @@ async fn are_parts_duplicate(executor: impl SqliteExecutor<'_>, parts: &DinoPart
     let body = get_file_name(&parts.body);
     let mouth = get_file_name(&parts.mouth);
     let eyes = get_file_name(&parts.eyes);
-    let row = sqlx::query!(
+    let query = sqlx::query!(
         "SELECT id FROM Dino WHERE body = ? AND mouth = ? AND eyes = ?",
         body,
         mouth,
         eyes
     )
-    .fetch_optional(executor)
-    .await?;
+    .fetch_optional(executor);
+    let row = timed_query("are_parts_duplicate", query).await?;
 
     Ok(row.is_some())
 }
 
 async fn is_name_duplicate(executor: impl SqliteExecutor<'_>, parts: &DinoParts) -> Result<bool> {
-    let row = sqlx::query!("SELECT id FROM Dino WHERE name = ?", parts.name)
-        .fetch_optional(executor)
-        .await?;
+    let query =
+        sqlx::query!("SELECT id FROM Dino WHERE name = ?", parts.name).fetch_optional(executor);
+    let row = timed_query("is_name_duplicate", query).await?;
 
     Ok(row.is_some())
 }
 
-fn choose_parts(fragments: &Fragments) -> DinoParts {
-    let mut rng = thread_rng();
+fn choose_parts(fragments: &Fragments, rng: &mut impl Rng) -> DinoParts {
     let body = fragments
         .bodies
-        .choose(&mut rng)
+        .choose(rng)
         .expect("Expected to have at least one body")
         .to_path_buf();
     let mouth = fragments
         .mouths
-        .choose(&mut rng)
+        .choose(rng)
         .expect("Expected to have at least one mouth")
         .to_path_buf();
     let eyes = fragments
         .eyes
-        .choose(&mut rng)
+        .choose(rng)
         .expect("Expected to have at least one set of eyes")
         .to_path_buf();
 
@@ -783,18 +803,55 @@ fn generate_dino_name(parts: &DinoParts) -> String {
 }
 
 fn generate_dino_image(parts: &DinoParts) -> Result<PathBuf> {
+    let path = output_path().join(&parts.name).with_extension("png");
+    save_dino_image(parts, &path)?;
+
+    Ok(path)
+}
+
+fn save_dino_image(parts: &DinoParts, path: &Path) -> Result<()> {
     let mut body = Reader::open(&parts.body)?.decode()?;
     let mouth = Reader::open(&parts.mouth)?.decode()?;
     let eyes = Reader::open(&parts.eyes)?.decode()?;
 
     overlay(&mut body, &mouth, 0, 0);
     overlay(&mut body, &eyes, 0, 0);
+    body.save_with_format(path, image::ImageFormat::Png)?;
+
+    Ok(())
+}
 
-    let output_path = Path::new(OUTPUT_PATH);
-    let path = output_path.join(&parts.name).with_extension("png");
-    body.save_with_format(&path, image::ImageFormat::Png)?;
+/// The fragments a dino is made of, its name is only used for the file.
+fn record_parts(dino: &DinoRecord) -> DinoParts {
+    let fragment_path = fragment_path();
+    DinoParts {
+        body: fragment_path.join(&dino.body),
+        mouth: fragment_path.join(&dino.mouth),
+        eyes: fragment_path.join(&dino.eyes),
+        name: dino.name.clone(),
+    }
+}
 
-    Ok(path)
+/// Draws the dinos whose image is missing from the output directory again, the fragments
+/// must have been set up with [`setup_dinos`]. Returns the names of the dinos redrawn.
+pub async fn regenerate_missing_dino_images(database: &SqlitePool) -> Result<Vec<String>> {
+    let dinos: Vec<DinoRecord> = sqlx::query_as("SELECT * FROM Dino ORDER BY id")
+        .fetch_all(database)
+        .await?;
+
+    let mut regenerated = Vec::new();
+    for dino in dinos {
+        let path = output_path().join(&dino.filename);
+        if path.exists() {
+            continue;
+        }
+
+        save_dino_image(&record_parts(&dino), &path)
+            .with_context(|| format!("Could not draw {}", dino.name))?;
+        regenerated.push(dino.name);
+    }
+
+    Ok(regenerated)
 }
 
 fn generate_dino_collection_image(collection: &[DinoRecord]) -> Result<Vec<u8>> {
@@ -804,26 +861,12 @@ fn generate_dino_collection_image(collection: &[DinoRecord]) -> Result<Vec<u8>>
     let width: u32 = columns * DINO_IMAGE_SIZE + (columns - 1) * COLUMN_MARGIN;
     let height: u32 = rows * DINO_IMAGE_SIZE + (rows - 1) * ROW_MARGIN;
 
-    let output_path = Path::new(OUTPUT_PATH);
-
     let mut image: RgbaImage = ImageBuffer::new(width, height);
     for (i, dino) in collection.iter().enumerate() {
         let x = (i as u32 % columns) * (COLUMN_MARGIN + DINO_IMAGE_SIZE);
         let y = (i as f32 / columns as f32).floor() as u32 * (ROW_MARGIN + DINO_IMAGE_SIZE);
 
-        let dino_image_path = output_path.join(&dino.filename);
-
-        if !dino_image_path.exists() {
-            let fragment_path = Path::new(FRAGMENT_PATH);
-            generate_dino_image(&DinoParts {
-                body: fragment_path.join(&dino.body),
-                mouth: fragment_path.join(&dino.mouth),
-                eyes: fragment_path.join(&dino.eyes),
-                name: dino.name.clone(),
-            })?;
-        }
-
-        let dino_image = Reader::open(&dino_image_path)?.decode()?;
+        let dino_image = load_dino_image(dino)?;
         overlay(&mut image, &dino_image, x.into(), y.into());
     }
 
@@ -833,11 +876,55 @@ fn generate_dino_collection_image(collection: &[DinoRecord]) -> Result<Vec<u8>>
     Ok(bytes)
 }
 
+/// Opens the picture of a dino, generating it again if it went missing.
+fn load_dino_image(dino: &DinoRecord) -> Result<DynamicImage> {
+    let dino_image_path = output_path().join(&dino.filename);
+
+    if !dino_image_path.exists() {
+        save_dino_image(&record_parts(dino), &dino_image_path)?;
+    }
+
+    Ok(Reader::open(&dino_image_path)?.decode()?)
+}
+
+/// A few of the favourite dinos someone owns.
+#[cfg(feature = "profile")]
+pub struct FavouriteDinos {
+    pub names: Vec<String>,
+    /// Empty when the dino commands are disabled.
+    pub images: Vec<DynamicImage>,
+}
+
+#[cfg(feature = "profile")]
+pub async fn favourite_dinos(
+    executor: impl SqliteExecutor<'_>,
+    user_id: &str,
+    limit: i64,
+) -> Result<FavouriteDinos> {
+    let query = sqlx::query_as(
+        r#"SELECT * FROM Dino WHERE owner_id = ?
+        AND id IN (SELECT dino_id FROM DinoTransactions WHERE type = 'FAVOURITE')
+        ORDER BY hotness DESC LIMIT ?"#,
+    )
+    .bind(user_id)
+    .bind(limit)
+    .fetch_all(executor);
+    let dinos: Vec<DinoRecord> = timed_query("favourite_dinos", query).await?;
+
+    let images = if DINO_FRAGMENTS.get().is_some() {
+        dinos.iter().map(load_dino_image).collect::<Result<_>>()?
+    } else {
+        Vec::new()
+    };
+
+    Ok(FavouriteDinos {
+        names: dinos.into_iter().map(|d| d.name).collect(),
+        images,
+    })
+}
+
 #[derive(Debug)]
 struct UserRecord {
-    last_hatch: NaiveDateTime,
-    last_slurp: NaiveDateTime,
-    last_gifting: NaiveDateTime,
     consecutive_fails: i64,
 }
 
@@ -845,7 +932,7 @@ async fn get_user_record(executor: impl SqliteExecutor<'_>, user_id: &str) -> Re
     let row = sqlx::query_as!(
         UserRecord,
         r#"INSERT OR IGNORE INTO DinoUser (id) VALUES (?);
-        SELECT last_hatch, last_slurp, last_gifting, consecutive_fails FROM DinoUser WHERE id = ?"#,
+        SELECT consecutive_fails FROM DinoUser WHERE id = ?"#,
         user_id,
         user_id,
     )
@@ -898,13 +985,13 @@ async fn update_hatch_message(
 ) -> Result<()> {
     // NOTE: sqlx has some issues handling hatch_message being NULL
     // so I just made it default to an empty string
-    sqlx::query!(
+    let query = sqlx::query!(
         "UPDATE Dino SET hatch_message = ? WHERE id = ?",
         message_link,
         dino_id
     )
-    .execute(executor)
-    .await?;
+    .execute(executor);
+    timed_query("update_hatch_message", query).await?;
 
     Ok(())
 }
@@ -1031,21 +1118,21 @@ async fn get_dino_record(
     executor: impl SqliteExecutor<'_>,
     dino_name: &str,
 ) -> Result<Option<DinoRecord>> {
-    let row = sqlx::query_as!(DinoRecord, "SELECT * FROM Dino WHERE name = ?", dino_name)
-        .fetch_optional(executor)
-        .await?;
+    let query = sqlx::query_as!(DinoRecord, "SELECT * FROM Dino WHERE name = ?", dino_name)
+        .fetch_optional(executor);
+    let row = timed_query("get_dino_record", query).await?;
 
     Ok(row)
 }
 
 async fn update_dino_name(db: &SqlitePool, dino_id: i64, new_name: &str) -> Result<()> {
-    sqlx::query!(
+    let query = sqlx::query!(
         "UPDATE OR ABORT Dino SET name = ? WHERE id = ?",
         new_name,
         dino_id
     )
-    .execute(db)
-    .await?;
+    .execute(db);
+    timed_query("update_dino_name", query).await?;
 
     Ok(())
 }
@@ -1084,7 +1171,7 @@ async fn send_dino_embed(
         .footer(CreateEmbedFooter::new(format!(
             "{} is worth {} Dino Bucks!\nHotness Rating: {}",
             &dino.name,
-            quirkify_worth(dino.owners),
+            quirkify_worth(dino.owners, &mut ctx.data().game_rng("dino_worth")),
             quirkify_hotness(dino.hotness)
         )))
         .attachment(image_name);
@@ -1109,7 +1196,7 @@ async fn gift_dino(
     gifter_id: &str,
     recipient_id: &str,
 ) -> Result<()> {
-    sqlx::query!(
+    let query = sqlx::query!(
         r#"INSERT OR IGNORE INTO DinoUser (id) VALUES (?);
         INSERT INTO DinoTransactions (dino_id, user_id, gifter_id, type)
         VALUES (?, ?, ?, 'GIFT');
@@ -1121,18 +1208,18 @@ async fn gift_dino(
         recipient_id,
         dino_id,
     )
-    .execute(executor)
-    .await?;
+    .execute(executor);
+    timed_query("gift_dino", query).await?;
 
     Ok(())
 }
 
 async fn delete_dino(executor: impl SqliteExecutor<'_>, dino_id: i64) -> Result<()> {
-    let row = sqlx::query!("DELETE FROM Dino WHERE id = ? RETURNING filename", dino_id)
-        .fetch_one(executor)
-        .await?;
+    let query = sqlx::query!("DELETE FROM Dino WHERE id = ? RETURNING filename", dino_id)
+        .fetch_one(executor);
+    let row = timed_query("delete_dino", query).await?;
 
-    let file_path = Path::new(OUTPUT_PATH).join(row.filename);
+    let file_path = output_path().join(row.filename);
     if file_path.exists() {
         std::fs::remove_file(file_path)?;
     }
@@ -1144,7 +1231,7 @@ async fn get_non_favourites(
     executor: impl SqliteExecutor<'_>,
     user_id: &str,
 ) -> Result<Vec<DinoRecord>> {
-    let rows = sqlx::query_as!(
+    let query = sqlx::query_as!(
         DinoRecord,
         r#"INSERT OR IGNORE INTO DinoUser (id) VALUES (?);
         SELECT * FROM Dino
@@ -1154,8 +1241,8 @@ async fn get_non_favourites(
         user_id,
         user_id
     )
-    .fetch_all(executor)
-    .await?;
+    .fetch_all(executor);
+    let rows = timed_query("get_non_favourites", query).await?;
 
     Ok(rows)
 }
@@ -1167,17 +1254,18 @@ async fn autocomplete_owned_dinos<'a>(
     let owner_id = ctx.author().id.to_string();
     let partial = format!("%{partial}%");
 
-    let suggestions = sqlx::query!(
+    let query = sqlx::query!(
         "SELECT name FROM Dino WHERE owner_id = ? AND name LIKE ? LIMIT 5",
         owner_id,
         partial
     )
-    .fetch_all(&ctx.data().database)
-    .await
-    .unwrap_or_else(|e| {
-        eprintln!("Error while trying to suggest autocomplete for '{partial}': {e}");
-        vec![]
-    });
+    .fetch_all(&ctx.data().database);
+    let suggestions = timed_query("autocomplete_owned_dinos", query)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Error while trying to suggest autocomplete for '{partial}': {e}");
+            vec![]
+        });
 
     suggestions.into_iter().map(|r| r.name)
 }
@@ -1188,23 +1276,29 @@ async fn autocomplete_all_dinos<'a>(
 ) -> impl Iterator<Item = String> + 'a {
     let partial = format!("%{partial}%");
 
-    let suggestions = sqlx::query!("SELECT name FROM Dino WHERE name LIKE ? LIMIT 5", partial)
-        .fetch_all(&ctx.data().database)
+    let query = sqlx::query!("SELECT name FROM Dino WHERE name LIKE ? LIMIT 5", partial)
+        .fetch_all(&ctx.data().database);
+    let suggestions = timed_query("autocomplete_all_dinos", query)
         .await
         .unwrap_or_else(|e| {
-            eprintln!("Error while trying to suggest autocomplete for '{partial}': {e}");
+            tracing::warn!("Error while trying to suggest autocomplete for '{partial}': {e}");
             vec![]
         });
 
     suggestions.into_iter().map(|r| r.name)
 }
 
-async fn roll_to_hatch(ctx: Context<'_>) -> Result<i64> {
-    let mut hatch_roll = pick_best_x_dice_rolls(4, 1, 1, None) as i64;
+async fn roll_to_hatch(ctx: Context<'_>, rng: &mut impl Rng) -> Result<i64> {
+    let mut hatch_roll = pick_best_x_dice_rolls(4, 1, 1, rng) as i64;
+
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(hatch_roll);
+    };
 
-    if let Some(guild_id) = ctx.guild_id() {
-        if ctx.author().has_role(ctx, guild_id, SUB_ROLE_ID).await? {
-            hatch_roll = hatch_roll.max(pick_best_x_dice_rolls(4, 1, 1, None) as i64);
+    // Subs roll with advantage, nobody does if the sub role isn't configured
+    if let Some(sub_role) = ctx.data().guild_role(guild_id, Setting::SubRole).await {
+        if ctx.author().has_role(ctx, guild_id, sub_role).await? {
+            hatch_roll = hatch_roll.max(pick_best_x_dice_rolls(4, 1, 1, rng) as i64);
         }
     }
 
@@ -1212,26 +1306,25 @@ async fn roll_to_hatch(ctx: Context<'_>) -> Result<i64> {
 }
 
 async fn try_hatching(
-    executor: impl SqliteExecutor<'_>,
+    executor: impl SqliteExecutor<'_> + Copy,
     ctx: Context<'_>,
     user: &DinoUser,
+    rng: &mut impl Rng,
 ) -> Result<()> {
-    let hatch_roll = roll_to_hatch(ctx).await?;
+    let hatch_roll = roll_to_hatch(ctx, rng).await?;
 
     if hatch_roll <= (MAX_FAILED_HATCHES - user.record.consecutive_fails) {
-        update_last_user_action(
-            executor,
-            &ctx.author().id.to_string(),
-            UserAction::Hatch(user.record.consecutive_fails + 1),
-        )
-        .await?;
-
-        bail!(
-            "You failed to hatch the egg ({} attempt), \
-            better luck next time. You can try again <t:{}:R>",
-            HATCH_FAILS_TEXT[user.record.consecutive_fails as usize],
-            user.timings.next_try
-        )
+        let user_id = ctx.author().id.to_string();
+        set_consecutive_fails(executor, &user_id, user.record.consecutive_fails + 1).await?;
+        let next_try = start_cooldown(ctx, executor, Action::DinoHatch).await?;
+
+        let attempt = user.record.consecutive_fails + 1;
+        let reason = i18n::text(
+            &i18n::locale(ctx),
+            "dino-hatch-failed",
+            &[("attempt", attempt.into())],
+        );
+        bail!(try_again(reason, next_try))
     }
 
     Ok(())
@@ -1243,10 +1336,163 @@ pub fn quirkify_hotness(hotness: i64) -> String {
 }
 
 // https://github.com/Brexbot/TwiggyBot/blob/main/src/commands/NFD.ts#L1156
-pub fn quirkify_worth(previous_owners: i64) -> String {
-    let quirkyness = rand::random::<f64>();
+pub fn quirkify_worth(previous_owners: i64, rng: &mut impl Rng) -> String {
+    let quirkyness = rng.gen::<f64>();
     let exponent = f64::tanh((previous_owners as f64 - 1.0 + quirkyness) / MAX_DINO_WORTH_EXPONENT)
         * MAX_DINO_WORTH_EXPONENT;
 
     format!("{:.3}", 2.0_f64.powf(exponent))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::GameRng;
+    use crate::testing;
+    use tempfile::TempDir;
+
+    const OWNER: u64 = 1;
+
+    /// Blank fragments in a temporary directory, there are 8 possible dinos. The fragments can
+    /// only be loaded once, so a single test uses them.
+    fn setup_fragments() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let assets = Assets {
+            dino_fragments: dir.path().join("fragments"),
+            dino_output: dir.path().join("output"),
+        };
+        std::fs::create_dir_all(&assets.dino_fragments).unwrap();
+
+        for name in ["stegosaur", "velociraptor"] {
+            for part in ["b", "m", "e"] {
+                let path = assets.dino_fragments.join(format!("{name}_{part}.png"));
+                RgbaImage::new(4, 4).save(path).unwrap();
+            }
+        }
+
+        setup_dinos(&assets).unwrap();
+
+        dir
+    }
+
+    /// Inserts a dino made of fragments that can't be generated, so they never clash.
+    async fn insert_owned_dino(database: &SqlitePool, name: &str) -> DinoRecord {
+        let owner_id = OWNER.to_string();
+        get_user_record(database, &owner_id).await.unwrap();
+
+        let parts = DinoParts {
+            body: PathBuf::from(format!("{name}_b.png")),
+            mouth: PathBuf::from(format!("{name}_m.png")),
+            eyes: PathBuf::from(format!("{name}_e.png")),
+            name: name.to_string(),
+        };
+        let file_path = PathBuf::from(format!("{name}.png"));
+        insert_dino(database, &owner_id, &parts, &file_path, None)
+            .await
+            .unwrap()
+    }
+
+    async fn slurp(
+        database: &SqlitePool,
+        cooldowns: &Cooldowns,
+        sacrifices: [&DinoRecord; 2],
+        commit: bool,
+    ) -> DinoRecord {
+        let mut rng = GameRng::new("dino_slurp", 1, false);
+        let parts = generate_dino(database, &mut rng).await.unwrap().unwrap();
+
+        let mut transaction = database.begin().await.unwrap();
+        let (dino, _) = slurp_dinos(
+            &mut transaction,
+            cooldowns,
+            Some(GuildId::new(1)),
+            UserId::new(OWNER),
+            sacrifices,
+            &parts,
+        )
+        .await
+        .unwrap();
+
+        if commit {
+            transaction.commit().await.unwrap();
+        }
+
+        dino
+    }
+
+    #[tokio::test]
+    async fn slurping() {
+        let _fragments = setup_fragments();
+
+        slurping_replaces_both_dinos().await;
+        slurping_is_undone_without_a_commit().await;
+    }
+
+    async fn slurping_replaces_both_dinos() {
+        let database = testing::database().await;
+        let cooldowns = testing::cooldowns(&database).await;
+        let first = insert_owned_dino(&database, "first").await;
+        let second = insert_owned_dino(&database, "second").await;
+
+        let dino = slurp(&database, &cooldowns, [&first, &second], true).await;
+
+        assert!(get_dino_record(&database, "first").await.unwrap().is_none());
+        assert!(get_dino_record(&database, "second")
+            .await
+            .unwrap()
+            .is_none());
+        let slurped = get_dino_record(&database, &dino.name).await.unwrap();
+        assert_eq!(slurped.unwrap().owner_id, OWNER.to_string());
+
+        let expiry = cooldowns
+            .expiry(Some(GuildId::new(1)), UserId::new(OWNER), Action::DinoSlurp)
+            .await
+            .unwrap();
+        assert!(expiry.is_some(), "Expected slurping to start the cooldown");
+    }
+
+    async fn slurping_is_undone_without_a_commit() {
+        let database = testing::database().await;
+        let cooldowns = testing::cooldowns(&database).await;
+        let first = insert_owned_dino(&database, "first").await;
+        let second = insert_owned_dino(&database, "second").await;
+
+        let dino = slurp(&database, &cooldowns, [&first, &second], false).await;
+
+        assert!(get_dino_record(&database, "first").await.unwrap().is_some());
+        assert!(get_dino_record(&database, "second")
+            .await
+            .unwrap()
+            .is_some());
+        assert!(get_dino_record(&database, &dino.name)
+            .await
+            .unwrap()
+            .is_none());
+        let expiry = cooldowns
+            .expiry(Some(GuildId::new(1)), UserId::new(OWNER), Action::DinoSlurp)
+            .await
+            .unwrap();
+        assert!(expiry.is_none());
+    }
+
+    #[tokio::test]
+    async fn only_owned_dinos_can_be_slurped() {
+        let database = testing::database().await;
+        let dino = insert_owned_dino(&database, "mine").await;
+        let mine = get_dino_record(&database, "mine").await.unwrap();
+
+        assert_eq!(
+            ensure_owned(mine, "mine", &OWNER.to_string()).unwrap().id,
+            dino.id
+        );
+
+        let mine = get_dino_record(&database, "mine").await.unwrap();
+        assert!(ensure_owned(mine, "mine", "2").is_err());
+
+        let missing = get_dino_record(&database, "missing").await.unwrap();
+        assert!(matches!(
+            ensure_owned(missing, "missing", &OWNER.to_string()),
+            Err(msg) if msg == "Could not find a dino named missing."
+        ));
+    }
+}
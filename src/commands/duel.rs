@@ -1,28 +1,31 @@
-use crate::common::{
-    avatar_url, bail_reply, colour, ephemeral_text_message, name, reply_with_buttons, response,
-    text_message, update_response,
-};
+use crate::audit::{self, AuditAction, Event};
+use crate::common::{avatar_url, bail_reply, colour, name};
+use crate::cooldowns::{Action, Cooldowns};
+use crate::i18n;
+use crate::messaging::{Button, Click, DiscordMessenger, Messenger, Reply};
+use crate::metrics::timed_query;
+use crate::rng::GameRng;
+use crate::shutdown::PendingChallenge;
+use crate::wallet::{self, Reason};
 use crate::Context;
 
 use anyhow::{bail, Context as AnyhowContext, Result};
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, Utc};
+use fluent_bundle::FluentValue;
 use poise::serenity_prelude::{
-    ButtonStyle, CreateActionRow, CreateButton, CreateEmbed, CreateEmbedAuthor, Member, User,
-    UserId,
+    ButtonStyle, CreateEmbed, CreateEmbedAuthor, GuildId, MessageId, User, UserId,
 };
-use poise::{CreateReply, ReplyHandle};
+use poise::CreateReply;
 use rand::Rng;
-use serenity::all::{ComponentInteraction, ComponentInteractionCollector, MessageId};
-use sqlx::{Connection, SqliteExecutor, Transaction};
+use sqlx::{SqliteExecutor, SqlitePool, Transaction};
 use std::cmp::Ordering;
 use std::fmt::Display;
 use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 use std::time::Duration;
 
-// TODO: this should be replaced with a const chrono::Duration when that gets stabilized
-const LOSS_COOLDOWN: i64 = 60;
 const DEAD_DUEL_COOLDOWN: Duration = Duration::from_secs(5 * 60);
 const TIMEOUT_DURATION: Duration = Duration::from_secs(10 * 60);
+const ACCEPT_BUTTON: &str = "duel-btn";
 
 static IN_PROGRESS: AtomicBool = AtomicBool::new(false);
 
@@ -30,86 +33,181 @@ static IN_PROGRESS: AtomicBool = AtomicBool::new(false);
 #[poise::command(slash_command, guild_only)]
 pub async fn duel(ctx: Context<'_>) -> Result<()> {
     let challenger = DuelUser::from(ctx, ctx.author()).await;
+    let data = ctx.data();
+    let duel = Duel {
+        database: &data.database,
+        cooldowns: &data.cooldowns,
+        guild_id: ctx.guild_id(),
+        payout: data.config.payouts.duel_win,
+        locale: i18n::locale(ctx),
+    };
 
     if IN_PROGRESS.load(AtomicOrdering::Acquire) {
         return bail_reply(ctx, "A duel is already in progress").await;
     }
 
-    if let Err(e) = challenger.ensure_outside_cooldown(ctx).await {
+    if let Err(e) = duel.ensure_outside_cooldown(&challenger).await {
         return bail_reply(ctx, e.to_string()).await;
     }
 
-    let reply_content = format!("{challenger} is looking for a duel, press the button to accept.");
-    let reply_handle = ctx
-        .send(reply_with_buttons(
-            reply_content,
-            vec![create_accept_button()],
-        ))
+    let messenger = DiscordMessenger::new(ctx);
+    let reply_content = duel.text(
+        "duel-looking",
+        &[("challenger", challenger.to_string().into())],
+    );
+    let message_id = messenger
+        .send(Reply::new(reply_content).button(accept_button()))
         .await?;
+    let pending = PendingChallenge::track(ctx.channel_id(), message_id);
 
     // Make sure the in_progress status gets updated even on failure
     IN_PROGRESS.store(true, AtomicOrdering::Release);
-    if let Err(e) = run_duel(ctx, challenger, reply_handle).await {
-        eprintln!("Failed to run duel to completiton: {e:?}");
+    let mut rng = ctx.data().game_rng("duel");
+    let scores = pick_scores(&mut rng);
+    if let Err(e) = run_duel(
+        &messenger, &duel, challenger, message_id, pending, scores, &rng,
+    )
+    .await
+    {
+        tracing::error!("Failed to run duel to completion: {e:?}");
     }
     IN_PROGRESS.store(false, AtomicOrdering::Release);
 
     Ok(())
 }
 
+/// Where the duel happens and what it gets recorded in.
+struct Duel<'a> {
+    database: &'a SqlitePool,
+    cooldowns: &'a Cooldowns,
+    guild_id: Option<GuildId>,
+    /// Dino Bucks for the winner.
+    payout: u32,
+    /// What the duel messages are written in.
+    locale: String,
+}
+
+impl Duel<'_> {
+    fn text(&self, id: &str, args: &[(&str, FluentValue)]) -> String {
+        i18n::text(&self.locale, id, args)
+    }
+
+    async fn ensure_outside_cooldown(&self, user: &DuelUser) -> Result<()> {
+        let insert = insert_user(self.database, &user.string_id);
+        if let Err(e) = timed_query("insert_user", insert).await {
+            tracing::error!("Could not add {user} before checking their duel cooldown: {e:?}");
+            bail!("Couldn't check your duel cooldown, no duel for you! :<");
+        }
+
+        let reason = format!("{user} you have recently lost a duel.");
+        self.cooldowns
+            .ensure_ready(self.guild_id, user.id, Action::DuelLoss, reason)
+            .await
+    }
+
+    async fn record_win(
+        &self,
+        executor: &mut Transaction<'_, sqlx::Sqlite>,
+        winner: &DuelUser,
+        loser: &DuelUser,
+        message_id: MessageId,
+    ) -> Result<()> {
+        timed_query(
+            "update_users_win_loss",
+            update_users_win_loss(executor, &winner.string_id, &loser.string_id),
+        )
+        .await?;
+
+        self.cooldowns
+            .start(&mut **executor, self.guild_id, loser.id, Action::DuelLoss)
+            .await?;
+
+        let reference_id = message_id.to_string();
+        wallet::pay_out(
+            executor,
+            winner.id,
+            self.payout,
+            Reason::DuelWin,
+            &reference_id,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
 async fn run_duel(
-    ctx: Context<'_>,
+    messenger: &impl Messenger,
+    duel: &Duel<'_>,
     challenger: DuelUser,
-    reply_handle: ReplyHandle<'_>,
+    message_id: MessageId,
+    pending: PendingChallenge,
+    (challenger_score, accepter_score): (usize, usize),
+    rng: &GameRng,
 ) -> Result<()> {
-    let message = reply_handle.message().await?;
-    let opponent = find_opponent(ctx, message.id, challenger.id.get()).await;
-
-    let Some((interaction, accepter)) = opponent else {
-        let duel_timeout_msg = format!("{challenger} failed to find someone to duel.");
-
-        reply_handle
-            .edit(ctx, reply_with_buttons(duel_timeout_msg, Vec::new()))
+    let opponent = find_opponent(messenger, duel, message_id, challenger.id).await;
+    // The challenge is over, a shutdown must not overwrite the result with a cancellation
+    drop(pending);
+
+    let Some((click, accepter)) = opponent else {
+        let args = [("challenger", challenger.to_string().into())];
+        let duel_timeout_msg = duel.text("duel-no-opponent", &args);
+        messenger
+            .edit(message_id, Reply::new(duel_timeout_msg))
             .await?;
 
         return Ok(());
     };
 
-    let (challenger_score, accepter_score) = pick_scores();
-
-    let mut conn = ctx.data().database.acquire().await?;
-    let mut transaction = conn.begin().await?;
+    let mut transaction = duel.database.begin().await?;
 
     let winner_text = match challenger_score.cmp(&accepter_score) {
         Ordering::Greater => {
-            let (winner_id, loser_id) = (&challenger.string_id, &accepter.string_id);
-            update_users_win_loss(&mut transaction, winner_id, loser_id).await?;
+            duel.record_win(&mut transaction, &challenger, &accepter, message_id)
+                .await?;
 
-            format!("{challenger} has won!")
+            duel.text("duel-won", &[("winner", challenger.to_string().into())])
         }
         Ordering::Less => {
-            let (winner_id, loser_id) = (&accepter.string_id, &challenger.string_id);
-            update_users_win_loss(&mut transaction, winner_id, loser_id).await?;
+            duel.record_win(&mut transaction, &accepter, &challenger, message_id)
+                .await?;
 
-            format!("{accepter} has won!")
+            duel.text("duel-won", &[("winner", accepter.to_string().into())])
         }
         Ordering::Equal => {
             update_users_drawn(&mut transaction, &challenger.string_id, &accepter.string_id)
                 .await?;
 
             let timeout_end_time = Utc::now() + chrono::Duration::from_std(TIMEOUT_DURATION)?;
-            let challenger_member = ctx.author_member().await.map(|m| m.into_owned());
-            timeout_user(ctx, challenger_member, timeout_end_time).await;
-            timeout_user(ctx, interaction.member.clone(), timeout_end_time).await;
-
-            "It's a draw! Now go sit in a corner for 10 mintues and think about your actions..."
-                .into()
+            timeout_user(messenger, &challenger, timeout_end_time).await;
+            timeout_user(messenger, &accepter, timeout_end_time).await;
+            let details = format!(
+                "Drawn duel, both timed out until <t:{}:t>",
+                timeout_end_time.timestamp()
+            );
+            let event = Event::new(
+                duel.guild_id,
+                challenger.id,
+                AuditAction::DuelTimeout,
+                details,
+            )
+            .target(accepter.id);
+            audit::record(&mut *transaction, &event).await?;
+
+            duel.text("duel-draw", &[])
         }
     };
 
-    let final_message = format!("{accepter} has rolled a {accepter_score} and {challenger} has rolled a {challenger_score}. {winner_text}");
-    let update_resp = update_response(text_message(final_message).components(Vec::new()));
-    interaction.create_response(ctx, update_resp).await?;
+    let args = [
+        ("accepter", accepter.to_string().into()),
+        ("accepter_score", accepter_score.into()),
+        ("challenger", challenger.to_string().into()),
+        ("challenger_score", challenger_score.into()),
+        ("outcome", winner_text.into()),
+    ];
+    let final_message = duel.text("duel-result", &args);
+    rng.audit(&final_message);
+    messenger.answer(&click, Reply::new(final_message)).await?;
 
     transaction.commit().await?;
 
@@ -117,41 +215,40 @@ async fn run_duel(
 }
 
 async fn find_opponent(
-    ctx: Context<'_>,
+    messenger: &impl Messenger,
+    duel: &Duel<'_>,
     message_id: MessageId,
-    challenger_id: u64,
-) -> Option<(ComponentInteraction, DuelUser)> {
-    while let Some(interaction) = ComponentInteractionCollector::new(ctx)
-        .message_id(message_id)
-        .filter(move |f| f.data.custom_id == "duel-btn")
-        .timeout(DEAD_DUEL_COOLDOWN)
+    challenger_id: UserId,
+) -> Option<(Click, DuelUser)> {
+    while let Some(click) = messenger
+        .next_click(message_id, ACCEPT_BUTTON, DEAD_DUEL_COOLDOWN)
         .await
     {
         // NOTE: responding with an ephemeral message does not trigger the
         // `iteraction failed` error but I'd like to find a way to just ignore
         // the click entirely with no response.
-        if interaction.user.id == challenger_id {
-            let resp = response(ephemeral_text_message("You cannot join your own duel."));
-            interaction.create_response(ctx, resp).await.ok()?;
+        if click.user_id == challenger_id {
+            let reply = Reply::ephemeral(duel.text("duel-own-challenge", &[]));
+            messenger.answer(&click, reply).await.ok()?;
             continue;
         }
 
         if !IN_PROGRESS.load(AtomicOrdering::Acquire) {
-            let resp = response(ephemeral_text_message(
-                "Someone beat you to the challenge already",
-            ));
-            interaction.create_response(ctx, resp).await.ok()?;
+            let reply = Reply::ephemeral(duel.text("duel-taken", &[]));
+            messenger.answer(&click, reply).await.ok()?;
             continue;
         }
 
-        let accepter = DuelUser::from(ctx, &interaction.user).await;
-        if let Err(e) = accepter.ensure_outside_cooldown(ctx).await {
-            let resp = response(ephemeral_text_message(e.to_string()));
-            interaction.create_response(ctx, resp).await.ok()?;
+        let accepter = DuelUser::new(click.user_id, click.user_name.clone());
+        if let Err(e) = duel.ensure_outside_cooldown(&accepter).await {
+            messenger
+                .answer(&click, Reply::ephemeral(e.to_string()))
+                .await
+                .ok()?;
             continue;
         }
 
-        return Some((interaction, accepter));
+        return Some((click, accepter));
     }
 
     None
@@ -163,7 +260,9 @@ pub async fn duelstats(ctx: Context<'_>) -> Result<()> {
     let user = ctx.author();
     let conn = &mut ctx.data().database.acquire().await?;
 
-    let Some(stats) = get_duel_stats(conn, user.id.to_string()).await? else {
+    let Some(stats) =
+        timed_query("get_duel_stats", get_duel_stats(conn, user.id.to_string())).await?
+    else {
         return bail_reply(ctx, "You have never dueled before.").await;
     };
 
@@ -190,22 +289,15 @@ pub async fn duelstats(ctx: Context<'_>) -> Result<()> {
     Ok(())
 }
 
-async fn get_last_loss(executor: impl SqliteExecutor<'_>, user_id: &str) -> Result<NaiveDateTime> {
+async fn insert_user(executor: impl SqliteExecutor<'_>, user_id: &str) -> Result<()> {
     // Insert a new User so that DuelStats always has a user to reference when
     // we set the wins/losses/draws after the duel
-    let row = sqlx::query!(
-        r#"
-        INSERT OR IGNORE INTO User (id) VALUES (?);
-        SELECT last_loss From User WHERE id = ?
-        "#,
-        user_id,
-        user_id
-    )
-    .fetch_one(executor)
-    .await
-    .with_context(|| format!("Failed to get {user_id}'s last loss"))?;
+    sqlx::query!("INSERT OR IGNORE INTO User (id) VALUES (?)", user_id)
+        .execute(executor)
+        .await
+        .with_context(|| format!("Failed to insert {user_id}"))?;
 
-    Ok(row.last_loss)
+    Ok(())
 }
 
 async fn update_users_win_loss(
@@ -306,26 +398,19 @@ async fn get_duel_stats(
     Ok(stats)
 }
 
-async fn timeout_user(ctx: Context<'_>, member: Option<Member>, until: DateTime<Utc>) {
-    let Some(mut member) = member else {
-        return;
-    };
-
-    if let Err(e) = member
-        .disable_communication_until_datetime(ctx, until.into())
-        .await
-    {
-        eprintln!("Failed to timeout {}, reason: {e:?}", member.user.name);
+async fn timeout_user(messenger: &impl Messenger, user: &DuelUser, until: DateTime<Utc>) {
+    if let Err(e) = messenger.timeout_member(user.id, until).await {
+        tracing::warn!("Failed to timeout {user}, reason: {e:?}");
     }
 }
 
-fn create_accept_button() -> CreateActionRow {
-    let btn = CreateButton::new("duel-btn")
-        .emoji('🎲')
-        .label("Accept Duel".to_string())
-        .style(ButtonStyle::Primary);
-
-    CreateActionRow::Buttons(vec![btn])
+fn accept_button() -> Button {
+    Button {
+        custom_id: ACCEPT_BUTTON.to_string(),
+        label: "Accept Duel".to_string(),
+        emoji: '🎲',
+        style: ButtonStyle::Primary,
+    }
 }
 
 struct DuelUser {
@@ -335,34 +420,16 @@ struct DuelUser {
 }
 
 impl DuelUser {
-    async fn from(ctx: Context<'_>, user: &User) -> Self {
-        let id = user.id;
-
+    fn new(id: UserId, name: String) -> Self {
         Self {
             id,
             string_id: id.to_string(),
-            name: name(&ctx, user).await,
+            name,
         }
     }
 
-    async fn ensure_outside_cooldown(&self, ctx: Context<'_>) -> Result<()> {
-        let last_loss = match get_last_loss(&ctx.data().database, &self.string_id).await {
-            Ok(last_loss) => last_loss,
-            Err(e) => {
-                eprintln!("Could not get {self}'s last loss: {e:?}");
-                bail!("Couldn't get your last loss, no duel for you! :<");
-            }
-        };
-
-        let now = Utc::now().naive_utc();
-
-        let loss_cooldown_duration = chrono::Duration::minutes(LOSS_COOLDOWN);
-        if last_loss + loss_cooldown_duration > now {
-            let time_until_duel = (last_loss + loss_cooldown_duration).and_utc().timestamp();
-            bail!("{self} you have recently lost a duel. Please try again <t:{time_until_duel}:R>.")
-        }
-
-        Ok(())
+    async fn from(ctx: Context<'_>, user: &User) -> Self {
+        Self::new(user.id, name(&ctx, user).await)
     }
 }
 
@@ -372,7 +439,155 @@ impl Display for DuelUser {
     }
 }
 
-fn pick_scores() -> (usize, usize) {
-    let mut rng = rand::thread_rng();
+fn pick_scores(rng: &mut impl Rng) -> (usize, usize) {
     (rng.gen_range(0..=100), rng.gen_range(0..=100))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Event, FakeMessenger};
+    use poise::serenity_prelude::ChannelId;
+
+    const CHALLENGER: u64 = 1;
+    const ACCEPTER: u64 = 2;
+
+    /// Only one duel can be in progress, the tests run in parallel
+    static DUEL_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    async fn stats(database: &SqlitePool, user_id: u64) -> DuelStats {
+        get_duel_stats(database, user_id.to_string())
+            .await
+            .unwrap()
+            .expect("Expected the user to have dueled")
+    }
+
+    /// Runs a whole duel where the accepter presses the button right away.
+    async fn play(duel: &Duel<'_>, scores: (usize, usize)) -> Vec<Event> {
+        let challenger = DuelUser::new(UserId::new(CHALLENGER), "challenger".to_string());
+        duel.ensure_outside_cooldown(&challenger).await.unwrap();
+
+        let messenger = FakeMessenger::default();
+        messenger.click(ACCEPTER, "accepter");
+
+        let _lock = DUEL_LOCK.lock().await;
+        IN_PROGRESS.store(true, AtomicOrdering::Release);
+        let rng = GameRng::new("duel", 1, false);
+        run_duel(
+            &messenger,
+            duel,
+            challenger,
+            MessageId::new(1),
+            PendingChallenge::track(ChannelId::new(1), MessageId::new(1)),
+            scores,
+            &rng,
+        )
+        .await
+        .unwrap();
+        IN_PROGRESS.store(false, AtomicOrdering::Release);
+
+        messenger.events()
+    }
+
+    #[tokio::test]
+    async fn wins_and_losses_are_recorded() {
+        let database = testing::database().await;
+        let cooldowns = testing::cooldowns(&database).await;
+        let duel = Duel {
+            database: &database,
+            cooldowns: &cooldowns,
+            guild_id: Some(GuildId::new(1)),
+            payout: 10,
+            locale: i18n::FALLBACK.to_string(),
+        };
+
+        play(&duel, (80, 20)).await;
+        let winner = stats(&database, CHALLENGER).await;
+        let loser = stats(&database, ACCEPTER).await;
+        assert_eq!((winner.wins, winner.losses, winner.win_streak), (1, 0, 1));
+        assert_eq!((loser.wins, loser.losses, loser.loss_streak), (0, 1, 1));
+        let winnings = wallet::balance(&database, UserId::new(CHALLENGER)).await;
+        assert_eq!(winnings.unwrap(), 10);
+
+        let expiry = cooldowns
+            .expiry(duel.guild_id, UserId::new(ACCEPTER), Action::DuelLoss)
+            .await
+            .unwrap();
+        assert!(expiry.is_some(), "Expected the loser to be on cooldown");
+
+        // The loser is on cooldown, so they get turned away until the duel times out
+        let events = play(&duel, (80, 20)).await;
+        assert!(matches!(&events[0], Event::Answered(_, reply) if reply.ephemeral));
+        assert!(matches!(&events[1], Event::Edited(_, reply) if reply.content.contains("failed")));
+        assert_eq!(stats(&database, CHALLENGER).await.wins, 1);
+
+        cooldowns
+            .reset(GuildId::new(1), UserId::new(ACCEPTER), None, None)
+            .await
+            .unwrap();
+        play(&duel, (10, 20)).await;
+        let challenger = stats(&database, CHALLENGER).await;
+        let accepter = stats(&database, ACCEPTER).await;
+        assert_eq!((challenger.wins, challenger.losses), (1, 1));
+        assert_eq!((challenger.win_streak, challenger.loss_streak), (0, 1));
+        assert_eq!(challenger.win_streak_max, 1);
+        assert_eq!(
+            (accepter.wins, accepter.losses, accepter.loss_streak),
+            (1, 1, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn draws_time_out_both_duelists() {
+        let database = testing::database().await;
+        let cooldowns = testing::cooldowns(&database).await;
+        let duel = Duel {
+            database: &database,
+            cooldowns: &cooldowns,
+            guild_id: Some(GuildId::new(1)),
+            payout: 10,
+            locale: i18n::FALLBACK.to_string(),
+        };
+
+        let events = play(&duel, (50, 50)).await;
+        assert!(events.contains(&Event::TimedOut(UserId::new(CHALLENGER))));
+        assert!(events.contains(&Event::TimedOut(UserId::new(ACCEPTER))));
+        assert_eq!(stats(&database, CHALLENGER).await.draws, 1);
+        assert_eq!(stats(&database, ACCEPTER).await.draws, 1);
+    }
+
+    #[tokio::test]
+    async fn challengers_cannot_accept_their_own_duel() {
+        let database = testing::database().await;
+        let cooldowns = testing::cooldowns(&database).await;
+        let duel = Duel {
+            database: &database,
+            cooldowns: &cooldowns,
+            guild_id: Some(GuildId::new(1)),
+            payout: 10,
+            locale: i18n::FALLBACK.to_string(),
+        };
+        let messenger = FakeMessenger::default();
+        messenger.click(CHALLENGER, "challenger");
+
+        let _lock = DUEL_LOCK.lock().await;
+        IN_PROGRESS.store(true, AtomicOrdering::Release);
+        let opponent = find_opponent(
+            &messenger,
+            &duel,
+            MessageId::new(1),
+            UserId::new(CHALLENGER),
+        )
+        .await;
+        IN_PROGRESS.store(false, AtomicOrdering::Release);
+
+        assert!(opponent.is_none());
+        assert_eq!(
+            messenger.events(),
+            vec![Event::Answered(
+                UserId::new(CHALLENGER),
+                Reply::ephemeral("You cannot join your own duel.")
+            )]
+        );
+    }
+}
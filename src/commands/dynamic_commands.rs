@@ -1,21 +1,56 @@
-use std::collections::{hash_map::Entry, HashMap};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
-use poise::serenity_prelude::{Context as SerenityContext, CreateCommand, GuildId};
-use rand::seq::IteratorRandom;
+use poise::serenity_prelude::{
+    Attachment, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    Context as SerenityContext, CreateAttachment, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateInteractionResponseMessage, GuildId, UserId,
+};
+use poise::CreateReply;
+use rand::{seq::IteratorRandom, Rng};
+use serde::{Deserialize, Serialize};
 use serenity::all::Interaction;
+use sqlx::{Connection, SqliteConnection, SqliteExecutor};
 
+use super::command_history::{record_revision, RevisionAction};
+use super::command_stats::{record_use, Uses};
+use super::template::{Template, Values};
 use super::DEFAULT_COMMANDS;
 use crate::{
-    common::{bail_reply, ephemeral_reply, response, text_message},
-    Context, Data, Result,
+    audit::{self, AuditAction, Event},
+    command_rules,
+    common::{
+        bail_reply, ephemeral_reply, ephemeral_text_message, response, text_message, to_color,
+        truncate,
+    },
+    metrics::timed_query,
+    shutdown, Context, Data, Result,
 };
 
-#[derive(Debug, sqlx::Type, poise::ChoiceParameter, Clone, Copy)]
+#[derive(
+    Debug, sqlx::Type, poise::ChoiceParameter, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
 pub enum CommandKind {
     Static,
     Choice,
+    /// `content` is an [`EmbedContent`] as JSON.
+    Embed,
+    /// Posts the image uploaded with the command, `content` is its file name.
+    Attachment,
 }
 
+const MODAL_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+const MAX_ATTACHMENT_SIZE: u32 = 8 * 1024 * 1024;
+const MAX_IMPORT_SIZE: u32 = 1024 * 1024;
+/// Discord refuses longer command names and more commands in a server.
+const MAX_NAME_LENGTH: usize = 32;
+pub(super) const MAX_GUILD_COMMANDS: usize = 100;
+/// Discord refuses embeds with longer titles and descriptions.
+const MAX_EMBED_TITLE_LENGTH: usize = 256;
+const MAX_EMBED_DESCRIPTION_LENGTH: usize = 4096;
+
+#[derive(Clone)]
 pub struct CommandInfo {
     pub kind: CommandKind,
     pub content: String,
@@ -23,40 +58,422 @@ pub struct CommandInfo {
 
 pub type SimpleCommands = HashMap<i64, HashMap<String, CommandInfo>>;
 
+/// The slash command for a simple command, with the options its placeholders need.
+pub fn create_command(name: &str, info: &CommandInfo) -> CreateCommand {
+    let template = Template::parse(&info.content);
+    let mut command = CreateCommand::new(name).description("A simple text command");
+    if template.uses_target() {
+        let option = CreateCommandOption::new(CommandOptionType::User, "target", "Who it's for");
+        command = command.add_option(option);
+    }
+    if template.uses_arg() {
+        let option =
+            CreateCommandOption::new(CommandOptionType::String, "arg", "Something to add to it");
+        command = command.add_option(option);
+    }
+
+    command
+}
+
+/// What an embed command shows, every field is optional but one has to be there.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbedContent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Written like `#77618F`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+impl EmbedContent {
+    /// Reads the content of an embed command, the error says what's wrong with it.
+    pub fn parse(json: &str) -> std::result::Result<Self, String> {
+        let embed: Self =
+            serde_json::from_str(json).map_err(|e| format!("The embed is not valid JSON: {e}"))?;
+
+        if embed.title.is_none() && embed.description.is_none() && embed.image.is_none() {
+            return Err("The embed needs a title, a description or an image.".to_string());
+        }
+        let title_length = embed.title.as_deref().map_or(0, |t| t.chars().count());
+        if title_length > MAX_EMBED_TITLE_LENGTH {
+            let max = MAX_EMBED_TITLE_LENGTH;
+            return Err(format!("The title can't be longer than {max} characters."));
+        }
+        let description_length = embed
+            .description
+            .as_deref()
+            .map_or(0, |d| d.chars().count());
+        if description_length > MAX_EMBED_DESCRIPTION_LENGTH {
+            let max = MAX_EMBED_DESCRIPTION_LENGTH;
+            return Err(format!(
+                "The description can't be longer than {max} characters."
+            ));
+        }
+        if let Some(color) = &embed.color {
+            to_color(color).ok_or_else(|| format!("`{color}` is not a color like #77618F."))?;
+        }
+        if let Some(image) = &embed.image {
+            if !image.starts_with("https://") && !image.starts_with("http://") {
+                return Err(format!("`{image}` is not a link to an image."));
+            }
+        }
+
+        Ok(embed)
+    }
+
+    /// The embed with the placeholders of the title and description filled in.
+    fn render(&self, values: &Values, rng: &mut impl Rng) -> CreateEmbed {
+        let mut embed = CreateEmbed::default();
+        if let Some(title) = &self.title {
+            let title = Template::parse(title).render(values, rng);
+            embed = embed.title(truncate(&title, MAX_EMBED_TITLE_LENGTH));
+        }
+        if let Some(description) = &self.description {
+            let description = Template::parse(description).render(values, rng);
+            embed = embed.description(truncate(&description, MAX_EMBED_DESCRIPTION_LENGTH));
+        }
+        if let Some(color) = self.color.as_deref().and_then(to_color) {
+            embed = embed.colour(color);
+        }
+        if let Some(image) = &self.image {
+            embed = embed.image(image);
+        }
+
+        embed
+    }
+}
+
+/// The form `/commands add` shows when no content is given.
+#[derive(Debug, poise::Modal)]
+#[name = "New command"]
+struct ContentModal {
+    #[name = "What the command says, | between choices"]
+    #[placeholder = "Hi {user}, you rolled {random:1-100}!"]
+    #[paragraph]
+    #[max_length = 2000]
+    content: String,
+}
+
+/// The form `/commands add` shows for an embed when no content is given.
+#[derive(Debug, poise::Modal)]
+#[name = "New embed command"]
+struct EmbedModal {
+    #[name = "Title"]
+    #[max_length = 256]
+    title: Option<String>,
+    #[name = "Description"]
+    #[paragraph]
+    #[max_length = 4000]
+    description: Option<String>,
+    #[name = "Color"]
+    #[placeholder = "#77618F"]
+    color: Option<String>,
+    #[name = "Image link"]
+    #[placeholder = "https://..."]
+    image: Option<String>,
+}
+
+/// A guild command as it's written to and read from a JSON file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedCommand {
+    pub name: String,
+    pub kind: CommandKind,
+    pub content: String,
+}
+
+impl From<&ExportedCommand> for CommandInfo {
+    fn from(command: &ExportedCommand) -> Self {
+        Self {
+            kind: command.kind,
+            content: command.content.clone(),
+        }
+    }
+}
+
+/// The commands by name, the way they are kept in [`SimpleCommands`].
+fn by_name(commands: &[ExportedCommand]) -> HashMap<String, CommandInfo> {
+    commands
+        .iter()
+        .map(|command| (command.name.clone(), CommandInfo::from(command)))
+        .collect()
+}
+
+/// Every simple command of a guild, sorted by name.
+pub async fn export_commands(
+    executor: impl SqliteExecutor<'_>,
+    guild_id: GuildId,
+) -> Result<Vec<ExportedCommand>> {
+    let guild_id = guild_id.get() as i64;
+    let commands = sqlx::query_as!(
+        ExportedCommand,
+        r#"SELECT name, kind as "kind: CommandKind", content FROM SimpleCommands
+        WHERE guild_id = ? ORDER BY name"#,
+        guild_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(commands)
+}
+
+/// Adds the commands to a guild in one transaction, replacing the ones with the same name.
+/// `event` is written to the audit log with them when someone on Discord imported them, and
+/// they are its author.
+pub async fn import_commands(
+    conn: &mut SqliteConnection,
+    guild_id: GuildId,
+    commands: &[ExportedCommand],
+    event: Option<&Event>,
+) -> Result<()> {
+    let guild = guild_id.get() as i64;
+    let author_id = event.map(|event| event.user_id);
+
+    let mut transaction = conn.begin().await?;
+    for command in commands {
+        let name = command.name.to_lowercase();
+        sqlx::query!(
+            r#"INSERT INTO SimpleCommands (guild_id, name, kind, content) VALUES (?, ?, ?, ?)
+            ON CONFLICT (guild_id, name) DO UPDATE SET kind = excluded.kind, content = excluded.content"#,
+            guild,
+            name,
+            command.kind,
+            command.content
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        let info = CommandInfo::from(command);
+        let action = RevisionAction::Import;
+        record_revision(
+            &mut *transaction,
+            guild_id,
+            &name,
+            action,
+            Some(&info),
+            None,
+            author_id,
+        )
+        .await?;
+    }
+    if let Some(event) = event {
+        audit::record(&mut *transaction, event).await?;
+    }
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+/// What `/commands import` does with a file, worked out before anything is written.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportPlan {
+    /// The commands to write, with their names in lowercase.
+    pub commands: Vec<ExportedCommand>,
+    /// The commands of the server that get replaced.
+    pub replaced: Vec<String>,
+    /// The commands left out, with the reason.
+    pub rejected: Vec<(String, String)>,
+}
+
+impl ImportPlan {
+    /// Sorts the commands of an export into the ones that can be imported and the ones that
+    /// can't, such as those named like a default command or past the number of commands a
+    /// server can have. Images aren't part of an export, so image commands are left out too.
+    pub fn new(
+        commands: Vec<ExportedCommand>,
+        default_commands: &[String],
+        existing: Option<&HashMap<String, CommandInfo>>,
+    ) -> Self {
+        let mut plan = Self::default();
+        let mut total = existing.map_or(0, HashMap::len);
+        for mut command in commands {
+            command.name = command.name.to_lowercase();
+            let name = &command.name;
+            let is_new = !existing.is_some_and(|commands| commands.contains_key(name));
+
+            let rejection = if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+                Some("it is not a single word".to_string())
+            } else if name.chars().count() > MAX_NAME_LENGTH {
+                Some(format!(
+                    "its name is longer than {MAX_NAME_LENGTH} characters"
+                ))
+            } else if default_commands.contains(name) {
+                Some("a default command has that name".to_string())
+            } else if plan.commands.iter().any(|c| c.name == *name) {
+                Some("it is in the file twice".to_string())
+            } else if command.kind == CommandKind::Attachment {
+                Some("its image is not in the file, add it with /commands add".to_string())
+            } else if command.kind == CommandKind::Embed {
+                EmbedContent::parse(&command.content).err()
+            } else if is_new && total >= MAX_GUILD_COMMANDS {
+                Some(format!(
+                    "a server can't have more than {MAX_GUILD_COMMANDS} commands"
+                ))
+            } else {
+                None
+            };
+
+            match rejection {
+                Some(reason) => plan.rejected.push((command.name, reason)),
+                None => {
+                    match is_new {
+                        true => total += 1,
+                        false => plan.replaced.push(name.clone()),
+                    }
+                    plan.commands.push(command);
+                }
+            }
+        }
+
+        plan
+    }
+
+    /// What happened, for the moderator who imported the file.
+    pub fn summary(&self) -> String {
+        let mut summary = format!("Imported {} commands.", self.commands.len());
+        if !self.replaced.is_empty() {
+            let names = self.replaced.iter().map(|name| format!("/{name}"));
+            summary.push_str(&format!(
+                "\nReplaced {}.",
+                names.collect::<Vec<_>>().join(", ")
+            ));
+        }
+        if !self.rejected.is_empty() {
+            summary.push_str("\nLeft out:");
+            for (name, reason) in &self.rejected {
+                summary.push_str(&format!("\n- /{name}: {reason}"));
+            }
+        }
+
+        truncate(&summary, 2000)
+    }
+}
+
 #[poise::command(
     guild_only,
     slash_command,
     prefix_command,
-    subcommands("add", "edit", "remove"),
+    subcommands(
+        "add",
+        "edit",
+        "remove",
+        "export",
+        "import",
+        "super::command_history::history",
+        "super::command_history::revert",
+        "super::command_stats::stats",
+        "super::command_rules::rules"
+    ),
     required_permissions = "MODERATE_MEMBERS"
 )]
 pub async fn commands(_ctx: Context<'_>) -> Result<()> {
     Ok(())
 }
 
+/// Add a command, leave out the content to write it in a form
 #[poise::command(guild_only, slash_command, prefix_command, aliases("create"))]
 pub async fn add(
     ctx: Context<'_>,
     #[description = "The name of the command"] name: String,
-    #[description = "What the command should say"] content: String,
-    #[description = "Whether it should just say that text or choose one of the comma separated choices"]
+    #[description = "What the command should say, {user} or {random:1-6} are filled in"]
+    content: Option<String>,
+    #[description = "Text, a choice between | separated texts, an embed as JSON or an image"]
     kind: Option<CommandKind>,
+    #[description = "The image an attachment command posts"] image: Option<Attachment>,
 ) -> Result<()> {
     let name = name.to_lowercase();
-    let kind = kind.unwrap_or(CommandKind::Static);
+    let kind = match (kind, &image) {
+        (Some(kind), _) => kind,
+        (None, Some(_)) => CommandKind::Attachment,
+        (None, None) => CommandKind::Static,
+    };
 
-    ensure_single_word(ctx, &name).await?;
-    ensure_not_default_command(ctx, &name).await?;
+    if !ensure_single_word(ctx, &name).await? || !ensure_not_default_command(ctx, &name).await? {
+        return Ok(());
+    }
 
     let guild = ctx
         .guild_id()
         .expect("Expected /commands add to be guild only.");
 
+    let (content, attachment) = match (kind, content, image) {
+        (CommandKind::Attachment, _, Some(image)) => {
+            if let Err(e) = ensure_image(&image) {
+                return bail_reply(ctx, e).await;
+            }
+            let bytes = image.download().await?;
+            (image.filename, Some(bytes))
+        }
+        (CommandKind::Attachment, _, None) => {
+            return bail_reply(ctx, "Upload the image the command should post with it.").await;
+        }
+        (_, Some(content), _) => (content, None),
+        (_, None, _) => match ask_for_content(ctx, kind).await? {
+            Some(content) => (content, None),
+            None => return Ok(()),
+        },
+    };
+
+    if kind == CommandKind::Embed {
+        if let Err(e) = EmbedContent::parse(&content) {
+            return bail_reply(ctx, e).await;
+        }
+    }
+
     let new_command = CommandInfo { kind, content };
-    insert_command(ctx, &guild, &name, new_command).await?;
-    register_command(ctx, &guild, name).await?;
+    if insert_command(ctx, &guild, &name, new_command, attachment).await? {
+        ctx.send(ephemeral_reply("Command added")).await?;
+    }
+
+    Ok(())
+}
+
+/// Shows the form for the content of a new command. `None` when it's not filled in in time or
+/// the command wasn't a slash command.
+async fn ask_for_content(ctx: Context<'_>, kind: CommandKind) -> Result<Option<String>> {
+    let poise::Context::Application(ctx) = ctx else {
+        let msg = "Give the command some content, or use the slash command to fill in a form.";
+        bail_reply(ctx, msg).await?;
+        return Ok(None);
+    };
+
+    let content = match kind {
+        CommandKind::Embed => {
+            let modal = poise::execute_modal::<_, _, EmbedModal>(ctx, None, Some(MODAL_TIMEOUT));
+            modal.await?.map(|form| {
+                let embed = EmbedContent {
+                    title: form.title,
+                    description: form.description,
+                    color: form.color,
+                    image: form.image,
+                };
+                serde_json::to_string(&embed).expect("Expected an embed to serialize")
+            })
+        }
+        _ => {
+            let modal = poise::execute_modal::<_, _, ContentModal>(ctx, None, Some(MODAL_TIMEOUT));
+            modal.await?.map(|form| form.content)
+        }
+    };
 
-    ctx.send(ephemeral_reply("Command added")).await?;
+    Ok(content)
+}
+
+fn ensure_image(attachment: &Attachment) -> std::result::Result<(), String> {
+    let is_image = attachment
+        .content_type
+        .as_deref()
+        .is_some_and(|t| t.starts_with("image/"));
+    if !is_image {
+        return Err(format!("{} is not an image.", attachment.filename));
+    }
+    if attachment.size > MAX_ATTACHMENT_SIZE {
+        let megabytes = MAX_ATTACHMENT_SIZE / 1024 / 1024;
+        return Err(format!("Images can't be bigger than {megabytes} MB."));
+    }
 
     Ok(())
 }
@@ -65,8 +482,9 @@ pub async fn add(
 pub async fn edit(
     ctx: Context<'_>,
     #[description = "The name of the command"] name: String,
-    #[description = "What the command should say"] content: String,
-    #[description = "Whether it should just say that text or choose one of the comma separated choices"]
+    #[description = "What the command should say, {user} or {random:1-6} are filled in"]
+    content: String,
+    #[description = "Text, a choice between | separated texts or an embed as JSON"]
     kind: CommandKind,
 ) -> Result<()> {
     let name = name.to_lowercase();
@@ -74,10 +492,24 @@ pub async fn edit(
         .guild_id()
         .expect("Expected /commands edit to be guild only.");
 
+    match kind {
+        CommandKind::Attachment => {
+            let msg = "Images can't be changed, remove the command and add it again.";
+            return bail_reply(ctx, msg).await;
+        }
+        CommandKind::Embed => {
+            if let Err(e) = EmbedContent::parse(&content) {
+                return bail_reply(ctx, e).await;
+            }
+        }
+        CommandKind::Static | CommandKind::Choice => {}
+    }
+
     let updated_command = CommandInfo { kind, content };
-    update_command(ctx, &guild, &name, updated_command).await?;
-    ctx.send(ephemeral_reply("The command has been updated."))
-        .await?;
+    if update_command(ctx, &guild, &name, updated_command).await? {
+        ctx.send(ephemeral_reply("The command has been updated."))
+            .await?;
+    }
 
     Ok(())
 }
@@ -92,112 +524,468 @@ pub async fn remove(
         .guild_id()
         .expect("Expected /commands edit to be guild only.");
 
-    delete_command(ctx, &guild, &name).await?;
-    unregister_command(ctx, &guild, &name).await?;
+    if delete_command(ctx, &guild, &name).await? {
+        ctx.send(ephemeral_reply("The command has been removed."))
+            .await?;
+    }
 
-    ctx.send(ephemeral_reply("The command has been removed."))
-        .await?;
+    Ok(())
+}
+
+/// Get every command of the server as a JSON file, for /commands import
+#[poise::command(guild_only, slash_command, prefix_command)]
+pub async fn export(ctx: Context<'_>) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /commands export to be guild only.");
+
+    let commands = export_commands(&ctx.data().database, guild_id).await?;
+    if commands.is_empty() {
+        return bail_reply(ctx, "This server has no commands of its own.").await;
+    }
+
+    let mut message = format!("Here are the {} commands of the server.", commands.len());
+    let images = commands
+        .iter()
+        .filter(|command| command.kind == CommandKind::Attachment)
+        .map(|command| format!("/{}", command.name))
+        .collect::<Vec<_>>();
+    if !images.is_empty() {
+        message.push_str(&format!(
+            " The images of {} are not in the file.",
+            images.join(", ")
+        ));
+    }
+
+    let json = serde_json::to_string_pretty(&commands)?;
+    let file = CreateAttachment::bytes(json, format!("commands-{guild_id}.json"));
+    ctx.send(
+        CreateReply::default()
+            .content(truncate(&message, 2000))
+            .attachment(file)
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Add the commands of a file made by /commands export, replacing the ones with the same name
+#[poise::command(guild_only, slash_command, prefix_command)]
+pub async fn import(
+    ctx: Context<'_>,
+    #[description = "A file made by /commands export"] file: Attachment,
+) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /commands import to be guild only.");
+
+    if file.size > MAX_IMPORT_SIZE {
+        return bail_reply(ctx, "The file is too big to be a command export.").await;
+    }
+    let bytes = file.download().await?;
+    let commands: Vec<ExportedCommand> = match serde_json::from_slice(&bytes) {
+        Ok(commands) => commands,
+        Err(e) => return bail_reply(ctx, format!("The file is not a command export: {e}")).await,
+    };
+
+    let data = ctx.data();
+    let default_commands = DEFAULT_COMMANDS
+        .get()
+        .expect("Expected default commands to be initialized.");
+    let _changing = data.command_changes.lock().await;
+    let guild_commands = guild_commands(data, guild_id).await;
+
+    let plan = ImportPlan::new(commands, default_commands, Some(&guild_commands));
+    if plan.commands.is_empty() {
+        return bail_reply(ctx, plan.summary()).await;
+    }
+
+    let imported = by_name(&plan.commands);
+    let commands = guild_commands
+        .iter()
+        .filter(|(name, _)| !imported.contains_key(*name))
+        .chain(&imported)
+        .map(|(name, info)| create_command(name, info))
+        .collect::<Vec<_>>();
+
+    // Nothing is imported unless Discord takes every command
+    if let Err(e) = guild_id.set_commands(ctx, commands).await {
+        tracing::error!(
+            guild = guild_id.get(),
+            "Could not register the imported simple commands: {e}"
+        );
+        let msg = format!("Discord refused the commands, nothing was imported: {e}");
+        return bail_reply(ctx, msg).await;
+    }
+
+    let names = plan
+        .commands
+        .iter()
+        .map(|command| format!("/{}", command.name));
+    let details = names.collect::<Vec<_>>().join(", ");
+    let event = Event::from_ctx(ctx, AuditAction::CommandImport, details);
+    let mut conn = data.database.acquire().await?;
+    let saved = import_commands(&mut conn, guild_id, &plan.commands, Some(&event)).await;
+    if keep_registered(ctx, guild_id, saved.map(Ok))
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    let mut map = data.simple_commands.write().await;
+    map.entry(guild_id.get() as i64)
+        .or_default()
+        .extend(imported);
+    drop(map);
+
+    ctx.send(ephemeral_reply(plan.summary())).await?;
 
     Ok(())
 }
 
+/// The commands of a guild as they are now. Changes are checked against a copy, so the lock
+/// isn't held while Discord registers them.
+pub(super) async fn guild_commands(data: &Data, guild_id: GuildId) -> HashMap<String, CommandInfo> {
+    let map = data.simple_commands.read().await;
+    map.get(&(guild_id.get() as i64))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Returns what was saved once Discord took the change, `None` when it was refused after all and
+/// the author has been told why. The guild's commands are registered again the way they are
+/// saved when the change wasn't.
+pub(super) async fn keep_registered<T>(
+    ctx: Context<'_>,
+    guild_id: GuildId,
+    saved: Result<std::result::Result<T, String>>,
+) -> Result<Option<T>> {
+    match saved {
+        Ok(Ok(saved)) => Ok(Some(saved)),
+        Ok(Err(msg)) => {
+            register_saved_commands(ctx, guild_id).await;
+            bail_reply(ctx, msg).await?;
+            Ok(None)
+        }
+        Err(e) => {
+            register_saved_commands(ctx, guild_id).await;
+            Err(e)
+        }
+    }
+}
+
+async fn register_saved_commands(ctx: Context<'_>, guild_id: GuildId) {
+    let commands = guild_commands(ctx.data(), guild_id)
+        .await
+        .iter()
+        .map(|(name, info)| create_command(name, info))
+        .collect::<Vec<_>>();
+    if let Err(e) = guild_id.set_commands(ctx, commands).await {
+        tracing::error!(
+            guild = guild_id.get(),
+            "Could not register the saved simple commands again: {e}"
+        );
+    }
+}
+
+/// Registers the command with Discord and adds it. Returns whether it was added, the author has
+/// been told why when it wasn't.
 async fn insert_command(
     ctx: Context<'_>,
     guild_id: &GuildId,
     name: &str,
     new_command: CommandInfo,
-) -> Result<()> {
+    attachment: Option<Vec<u8>>,
+) -> Result<bool> {
     let data = ctx.data();
+    let _changing = data.command_changes.lock().await;
+    let guild_commands = guild_commands(data, *guild_id).await;
+    if let Err(msg) = check_new_command(&guild_commands, name) {
+        bail_reply(ctx, msg).await?;
+        return Ok(false);
+    }
+
+    // The command is only kept if Discord takes it
+    register_command(ctx, guild_id, create_command(name, &new_command)).await?;
+    let mut conn = data.database.acquire().await?;
+    let saved = save_new_command(
+        &mut conn,
+        &guild_commands,
+        *guild_id,
+        name,
+        &new_command,
+        attachment.as_deref(),
+        ctx.author().id,
+    )
+    .await;
+    if keep_registered(ctx, *guild_id, saved).await?.is_none() {
+        return Ok(false);
+    }
+
     let mut map = data.simple_commands.write().await;
+    let guild_commands = map.entry(guild_id.get() as i64).or_default();
+    guild_commands.insert(name.to_owned(), new_command);
 
-    let guild_id = guild_id.get() as i64;
-    let guild_commands = map.entry(guild_id).or_default();
-    let Entry::Vacant(entry) = guild_commands.entry(name.to_owned()) else {
-        // TODO: Should this be outside?
-        return bail_reply(ctx, "The command already exists.").await;
-    };
+    Ok(true)
+}
+
+/// Why a command with that name can't be added, if it can't.
+fn check_new_command(
+    guild_commands: &HashMap<String, CommandInfo>,
+    name: &str,
+) -> std::result::Result<(), String> {
+    if guild_commands.contains_key(name) {
+        return Err("The command already exists.".to_string());
+    }
+    if guild_commands.len() >= MAX_GUILD_COMMANDS {
+        return Err(format!(
+            "A server can't have more than {MAX_GUILD_COMMANDS} commands."
+        ));
+    }
+
+    Ok(())
+}
+
+/// Writes a new command with its revision and audit entry in one transaction. `Ok(Err(..))`
+/// says why it can't be added.
+async fn save_new_command(
+    conn: &mut SqliteConnection,
+    guild_commands: &HashMap<String, CommandInfo>,
+    guild_id: GuildId,
+    name: &str,
+    command: &CommandInfo,
+    attachment: Option<&[u8]>,
+    author_id: UserId,
+) -> Result<std::result::Result<(), String>> {
+    if let Err(msg) = check_new_command(guild_commands, name) {
+        return Ok(Err(msg));
+    }
 
-    let content = &new_command.content;
-    let kind = new_command.kind;
+    let guild = guild_id.get() as i64;
+    let mut transaction = conn.begin().await?;
     sqlx::query!(
-        "INSERT INTO SimpleCommands (guild_id, name, kind, content) VALUES (?, ?, ?, ?)",
+        "INSERT INTO SimpleCommands (guild_id, name, kind, content, attachment) VALUES (?, ?, ?, ?, ?)",
+        guild,
+        name,
+        command.kind,
+        command.content,
+        attachment
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    record_revision(
+        &mut *transaction,
         guild_id,
         name,
-        kind,
-        content
+        RevisionAction::Add,
+        Some(command),
+        attachment,
+        Some(author_id),
     )
-    .execute(&data.database)
     .await?;
 
-    entry.insert(new_command);
+    let details = format!("/{name} ({:?}): {}", command.kind, command.content);
+    let event = Event::new(Some(guild_id), author_id, AuditAction::CommandAdd, details);
+    audit::record(&mut *transaction, &event).await?;
+    transaction.commit().await?;
 
-    Ok(())
+    Ok(Ok(()))
 }
+
+/// Registers the new version of the command with Discord and changes it. Returns whether it was
+/// changed, the author has been told why when it wasn't.
 async fn update_command(
     ctx: Context<'_>,
     guild_id: &GuildId,
     name: &str,
     command: CommandInfo,
-) -> Result<()> {
+) -> Result<bool> {
     let data = ctx.data();
+    let _changing = data.command_changes.lock().await;
+    let guild_commands = guild_commands(data, *guild_id).await;
+    if let Err(msg) = find_command(&guild_commands, name) {
+        bail_reply(ctx, msg).await?;
+        return Ok(false);
+    }
+
+    // The placeholders might need other options now
+    register_command(ctx, guild_id, create_command(name, &command)).await?;
+    let mut conn = data.database.acquire().await?;
+    let saved = save_update(
+        &mut conn,
+        &guild_commands,
+        *guild_id,
+        name,
+        &command,
+        ctx.author().id,
+    )
+    .await;
+    if keep_registered(ctx, *guild_id, saved).await?.is_none() {
+        return Ok(false);
+    }
+
     let mut map = data.simple_commands.write().await;
+    let guild_commands = map.entry(guild_id.get() as i64).or_default();
+    guild_commands.insert(name.to_owned(), command);
 
-    let guild_id = guild_id.get() as i64;
-    let Some(guild_commands) = map.get_mut(&guild_id) else {
-        return bail_reply(ctx, "This guild does not have this command.").await;
-    };
-    let Some(entry) = guild_commands.get_mut(name) else {
-        return bail_reply(ctx, "The command does not exist.").await;
+    Ok(true)
+}
+
+/// The command with that name, or why there is none.
+fn find_command<'a>(
+    guild_commands: &'a HashMap<String, CommandInfo>,
+    name: &str,
+) -> std::result::Result<&'a CommandInfo, String> {
+    guild_commands
+        .get(name)
+        .ok_or_else(|| "The command does not exist.".to_string())
+}
+
+/// Writes the new version of a command with its revision and audit entry in one transaction.
+/// `Ok(Err(..))` says why it can't be changed.
+async fn save_update(
+    conn: &mut SqliteConnection,
+    guild_commands: &HashMap<String, CommandInfo>,
+    guild_id: GuildId,
+    name: &str,
+    command: &CommandInfo,
+    author_id: UserId,
+) -> Result<std::result::Result<(), String>> {
+    let previous = match find_command(guild_commands, name) {
+        Ok(previous) => previous,
+        Err(msg) => return Ok(Err(msg)),
     };
 
-    let content = &command.content;
-    let kind = &command.kind;
+    let mut transaction = conn.begin().await?;
+    save_edit(&mut *transaction, guild_id, name, command).await?;
+
+    record_revision(
+        &mut *transaction,
+        guild_id,
+        name,
+        RevisionAction::Edit,
+        Some(command),
+        None,
+        Some(author_id),
+    )
+    .await?;
+
+    let details = format!(
+        "/{name} ({:?}): {} → {}",
+        command.kind, previous.content, command.content
+    );
+    let event = Event::new(Some(guild_id), author_id, AuditAction::CommandEdit, details);
+    audit::record(&mut *transaction, &event).await?;
+    transaction.commit().await?;
+
+    Ok(Ok(()))
+}
+
+/// Replaces what the command `name` does, an edited command loses its image.
+async fn save_edit(
+    executor: impl SqliteExecutor<'_>,
+    guild_id: GuildId,
+    name: &str,
+    command: &CommandInfo,
+) -> Result<()> {
+    let guild_id = guild_id.get() as i64;
     sqlx::query!(
-        "UPDATE OR IGNORE SimpleCommands SET kind = ?, content = ? WHERE guild_id = ? AND name = ?",
+        r#"UPDATE OR IGNORE SimpleCommands SET kind = ?, content = ?, attachment = NULL
+        WHERE guild_id = ? AND name = ?"#,
+        command.kind,
+        command.content,
         guild_id,
-        kind,
-        content,
         name
     )
-    .execute(&data.database)
+    .execute(executor)
     .await?;
 
-    *entry = command;
-
     Ok(())
 }
-async fn delete_command(ctx: Context<'_>, guild_id: &GuildId, name: &str) -> Result<()> {
+
+/// Unregisters the command from Discord and removes it. Returns whether it was removed, the
+/// author has been told why when it wasn't.
+async fn delete_command(ctx: Context<'_>, guild_id: &GuildId, name: &str) -> Result<bool> {
     let data = ctx.data();
+    let _changing = data.command_changes.lock().await;
+    let guild_commands = guild_commands(data, *guild_id).await;
+    let Some(command) = guild_commands.get(name) else {
+        bail_reply(ctx, "This command name does not exist.").await?;
+        return Ok(false);
+    };
+
+    unregister_command(ctx, guild_id, name).await?;
+    let details = format!("/{name}: {}", command.content);
+    let event = Event::from_ctx(ctx, AuditAction::CommandRemove, details);
+    let mut conn = data.database.acquire().await?;
+    let saved = save_removal(&mut conn, *guild_id, name, &event).await;
+    if keep_registered(ctx, *guild_id, saved.map(Ok))
+        .await?
+        .is_none()
+    {
+        return Ok(false);
+    }
+
     let mut map = data.simple_commands.write().await;
+    if let Some(guild_commands) = map.get_mut(&(guild_id.get() as i64)) {
+        guild_commands.remove(name);
+    }
 
-    let guild_id = guild_id.get() as i64;
-    let Some(guild_commands) = map.get_mut(&guild_id) else {
-        return bail_reply(ctx, "This guild does not have this command.").await;
-    };
-    let Entry::Occupied(entry) = guild_commands.entry(name.to_owned()) else {
-        return bail_reply(ctx, "This command name does not exist.").await;
-    };
+    Ok(true)
+}
 
+/// Deletes the command and its uses with its revision and audit entry in one transaction, a
+/// command added later under the same name starts counting from scratch. `event` says who
+/// removed it.
+async fn save_removal(
+    conn: &mut SqliteConnection,
+    guild_id: GuildId,
+    name: &str,
+    event: &Event,
+) -> Result<()> {
+    let guild = guild_id.get() as i64;
+    let mut transaction = conn.begin().await?;
     sqlx::query!(
         "DELETE FROM SimpleCommands WHERE guild_id = ? AND name = ?",
-        guild_id,
+        guild,
         name
     )
-    .execute(&data.database)
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM SimpleCommandUsage WHERE guild_id = ? AND name = ?",
+        guild,
+        name
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    record_revision(
+        &mut *transaction,
+        guild_id,
+        name,
+        RevisionAction::Remove,
+        None,
+        None,
+        Some(event.user_id),
+    )
     .await?;
 
-    entry.remove_entry();
+    audit::record(&mut *transaction, event).await?;
+    transaction.commit().await?;
 
     Ok(())
 }
 
-async fn register_command(ctx: Context<'_>, guild_id: &GuildId, name: String) -> Result<()> {
-    guild_id
-        .create_command(
-            ctx,
-            CreateCommand::new(name).description("A simple text command"),
-        )
-        .await?;
+async fn register_command(
+    ctx: Context<'_>,
+    guild_id: &GuildId,
+    command: CreateCommand,
+) -> Result<()> {
+    guild_id.create_command(ctx, command).await?;
 
     Ok(())
 }
@@ -208,7 +996,7 @@ async fn unregister_command(ctx: Context<'_>, guild_id: &GuildId, name: &str) ->
         .into_iter()
         .find(|c| c.name == name)
     else {
-        eprintln!("Command {name} was present in the hashmap but in the guild commands");
+        tracing::warn!("Command {name} was present in the hashmap but not in the guild commands");
         return Ok(());
     };
 
@@ -217,7 +1005,8 @@ async fn unregister_command(ctx: Context<'_>, guild_id: &GuildId, name: &str) ->
     Ok(())
 }
 
-async fn ensure_not_default_command(ctx: Context<'_>, name: &str) -> Result<()> {
+/// Whether no default command has that name, the author is told when one does.
+async fn ensure_not_default_command(ctx: Context<'_>, name: &str) -> Result<bool> {
     if DEFAULT_COMMANDS
         .get()
         .expect("Expected default commands to be initialized.")
@@ -227,17 +1016,40 @@ async fn ensure_not_default_command(ctx: Context<'_>, name: &str) -> Result<()>
         let msg =
             "Cannot add command with that name because it's already taken by a default command.";
         ctx.send(ephemeral_reply(msg)).await?;
+        return Ok(false);
     }
 
-    Ok(())
+    Ok(true)
 }
-async fn ensure_single_word(ctx: Context<'_>, name: &str) -> Result<()> {
+/// Whether the name is a single word, the author is told when it isn't.
+async fn ensure_single_word(ctx: Context<'_>, name: &str) -> Result<bool> {
     if !name.chars().all(|c| c.is_ascii_alphanumeric()) {
         ctx.send(ephemeral_reply("Command name must be a single word."))
             .await?;
+        return Ok(false);
     };
 
-    Ok(())
+    Ok(true)
+}
+
+/// The server's own commands that start with `partial`.
+pub(super) async fn autocomplete_simple_command_names<'a>(
+    ctx: Context<'a>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    let mut names = Vec::new();
+    if let Some(guild_id) = ctx.guild_id() {
+        let map = ctx.data().simple_commands.read().await;
+        if let Some(commands) = map.get(&(guild_id.get() as i64)) {
+            names.extend(commands.keys().cloned());
+        }
+    }
+    names.sort();
+
+    names
+        .into_iter()
+        .filter(move |name| name.starts_with(partial))
+        .take(25)
 }
 
 pub async fn try_intercepting_command_call(
@@ -260,26 +1072,422 @@ pub async fn try_intercepting_command_call(
     };
 
     if let Some(dynamic_command) = guild_commands.get(&command.data.name) {
-        let text = respond_to_command_call(dynamic_command);
-        command
-            .create_response(ctx, response(text_message(text)))
-            .await?;
+        if shutdown::is_shutting_down() {
+            let message = ephemeral_text_message(shutdown::RESTARTING_MESSAGE);
+            command.create_response(ctx, response(message)).await?;
+            return Ok(());
+        }
+
+        let roles = command
+            .member
+            .as_ref()
+            .map(|member| member.roles.as_slice())
+            .unwrap_or_default();
+        if !user_data
+            .command_rules
+            .is_allowed(guild_id, command.channel_id, roles, &command.data.name)
+            .await
+        {
+            let msg = command_rules::denied_message(&command.data.name);
+            command
+                .create_response(ctx, response(ephemeral_text_message(msg)))
+                .await?;
+            return Ok(());
+        }
+
+        let start = Instant::now();
+        let uses = {
+            let mut conn = user_data.database.acquire().await?;
+            let record = record_use(&mut conn, guild_id, &command.data.name, command.user.id);
+            timed_query("record_simple_command_use", record).await?
+        };
+        let values = placeholder_values(&command, uses);
+        let mut rng = user_data.game_rng("dynamic_command");
+        let message = match dynamic_command.kind {
+            CommandKind::Static | CommandKind::Choice => {
+                text_message(respond_to_command_call(dynamic_command, &values, &mut rng))
+            }
+            CommandKind::Embed => match EmbedContent::parse(&dynamic_command.content) {
+                Ok(embed) => {
+                    CreateInteractionResponseMessage::new().embed(embed.render(&values, &mut rng))
+                }
+                Err(e) => ephemeral_text_message(e),
+            },
+            CommandKind::Attachment => {
+                let name = &command.data.name;
+                let fetch = fetch_attachment(&user_data.database, guild_id, name);
+                match timed_query("fetch_simple_command_attachment", fetch).await? {
+                    Some(bytes) => CreateInteractionResponseMessage::new()
+                        .add_file(CreateAttachment::bytes(bytes, &dynamic_command.content)),
+                    None => ephemeral_text_message("This command lost its image."),
+                }
+            }
+        };
+        let message = message.allowed_mentions(values.allowed_mentions());
+        command.create_response(ctx, response(message)).await?;
+        tracing::info!(
+            latency_ms = start.elapsed().as_millis() as u64,
+            "Dynamic command finished"
+        );
     };
 
     Ok(())
 }
 
-fn respond_to_command_call(dynamic_command: &CommandInfo) -> String {
-    match dynamic_command.kind {
-        CommandKind::Static => dynamic_command.content.clone(),
-        CommandKind::Choice => {
-            let mut rng = rand::thread_rng();
-            dynamic_command
-                .content
-                .split('|')
-                .choose(&mut rng)
-                .unwrap()
-                .to_string()
+async fn fetch_attachment(
+    executor: impl SqliteExecutor<'_>,
+    guild_id: GuildId,
+    name: &str,
+) -> Result<Option<Vec<u8>>> {
+    let guild_id = guild_id.get() as i64;
+    let attachment = sqlx::query_scalar!(
+        "SELECT attachment FROM SimpleCommands WHERE guild_id = ? AND name = ?",
+        guild_id,
+        name
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(attachment.flatten())
+}
+
+fn placeholder_values(command: &CommandInteraction, uses: Uses) -> Values {
+    let mut values = Values {
+        user: command.user.id,
+        target: None,
+        channel: command.channel_id,
+        count: uses.total,
+        user_count: uses.by_user,
+        arg: None,
+    };
+    for option in &command.data.options {
+        match (option.name.as_str(), &option.value) {
+            ("target", CommandDataOptionValue::User(id)) => values.target = Some(*id),
+            ("arg", CommandDataOptionValue::String(arg)) => values.arg = Some(arg.clone()),
+            _ => {}
+        }
+    }
+
+    values
+}
+
+fn respond_to_command_call(
+    dynamic_command: &CommandInfo,
+    values: &Values,
+    rng: &mut impl Rng,
+) -> String {
+    let content = match dynamic_command.kind {
+        CommandKind::Choice => dynamic_command.content.split('|').choose(rng).unwrap(),
+        _ => dynamic_command.content.as_str(),
+    };
+
+    Template::parse(content).render(values, rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::GameRng;
+    use crate::testing;
+    use poise::serenity_prelude::ChannelId;
+
+    fn exported(name: &str, kind: CommandKind, content: &str) -> ExportedCommand {
+        ExportedCommand {
+            name: name.to_string(),
+            kind,
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn embeds_are_validated() {
+        let embed = EmbedContent::parse(r##"{"title": "Rules", "color": "#77618F"}"##).unwrap();
+        assert_eq!(embed.title.as_deref(), Some("Rules"));
+
+        assert!(EmbedContent::parse("Rules").is_err());
+        assert!(EmbedContent::parse(r##"{"color": "#77618F"}"##).is_err());
+        assert!(EmbedContent::parse(r#"{"title": "Rules", "color": "purple"}"#).is_err());
+        assert!(EmbedContent::parse(r#"{"image": "file:///etc/passwd"}"#).is_err());
+
+        let title = "a".repeat(MAX_EMBED_TITLE_LENGTH + 1);
+        assert!(EmbedContent::parse(&format!(r#"{{"title": "{title}"}}"#)).is_err());
+        let description = "a".repeat(MAX_EMBED_DESCRIPTION_LENGTH + 1);
+        assert!(EmbedContent::parse(&format!(r#"{{"description": "{description}"}}"#)).is_err());
+    }
+
+    #[tokio::test]
+    async fn uses_are_counted() {
+        let database = testing::database().await;
+        let guild_id = GuildId::new(1);
+        let hi = exported(
+            "hi",
+            CommandKind::Choice,
+            "Hi {target}, #{count}|Hello {target}, #{usercount}",
+        );
+        let mut conn = database.acquire().await.unwrap();
+        import_commands(&mut conn, guild_id, std::slice::from_ref(&hi), None)
+            .await
+            .unwrap();
+
+        let (user, other) = (UserId::new(1), UserId::new(4));
+        record_use(&mut conn, guild_id, "hi", other).await.unwrap();
+        let uses = record_use(&mut conn, guild_id, "hi", user).await.unwrap();
+        let values = Values {
+            user,
+            target: Some(UserId::new(2)),
+            channel: ChannelId::new(3),
+            count: uses.total,
+            user_count: uses.by_user,
+            arg: None,
+        };
+        let info = CommandInfo::from(&hi);
+        let mut rng = GameRng::new("dynamic_command", 1, false);
+        let text = respond_to_command_call(&info, &values, &mut rng);
+        assert!(text == "Hi <@2>, #2" || text == "Hello <@2>, #1", "{text}");
+    }
+
+    #[tokio::test]
+    async fn removed_commands_take_their_uses_with_them() {
+        let database = testing::database().await;
+        let guild_id = GuildId::new(1);
+        let user = UserId::new(2);
+        let hi = [exported("hi", CommandKind::Static, "Hi")];
+
+        let mut conn = database.acquire().await.unwrap();
+        import_commands(&mut conn, guild_id, &hi, None)
+            .await
+            .unwrap();
+        record_use(&mut conn, guild_id, "hi", user).await.unwrap();
+        record_use(&mut conn, guild_id, "hi", user).await.unwrap();
+
+        let event = Event::new(Some(guild_id), user, AuditAction::CommandRemove, "/hi");
+        save_removal(&mut conn, guild_id, "hi", &event)
+            .await
+            .unwrap();
+        import_commands(&mut conn, guild_id, &hi, None)
+            .await
+            .unwrap();
+
+        let uses = record_use(&mut conn, guild_id, "hi", user).await.unwrap();
+        assert_eq!(
+            uses,
+            Uses {
+                total: 1,
+                by_user: 1
+            }
+        );
+    }
+
+    #[test]
+    fn imports_leave_out_what_cannot_be_added() {
+        let existing = by_name(&[exported("hi", CommandKind::Static, "Hello")]);
+        let file = vec![
+            exported("Hi", CommandKind::Static, "Hey"),
+            exported("duel", CommandKind::Static, "Fight"),
+            exported("hi", CommandKind::Static, "Hey again"),
+            exported("two words", CommandKind::Static, "Nope"),
+            exported("cat", CommandKind::Attachment, "cat.png"),
+            exported("rules", CommandKind::Embed, "Be nice"),
+            exported("bye", CommandKind::Choice, "Bye|Cya"),
+        ];
+
+        let plan = ImportPlan::new(file, &["duel".to_string()], Some(&existing));
+        assert_eq!(
+            plan.commands,
+            [
+                exported("hi", CommandKind::Static, "Hey"),
+                exported("bye", CommandKind::Choice, "Bye|Cya"),
+            ]
+        );
+        assert_eq!(plan.replaced, ["hi"]);
+        let rejected = plan.rejected.iter().map(|(name, _)| name.as_str());
+        assert_eq!(
+            rejected.collect::<Vec<_>>(),
+            ["duel", "hi", "two words", "cat", "rules"]
+        );
+
+        let long_name = "a".repeat(MAX_NAME_LENGTH + 1);
+        let plan = ImportPlan::new(
+            vec![exported(&long_name, CommandKind::Static, "Hi")],
+            &[],
+            None,
+        );
+        assert!(plan.commands.is_empty());
+
+        let full = (0..MAX_GUILD_COMMANDS)
+            .map(|i| exported(&format!("c{i}"), CommandKind::Static, "Hi"))
+            .collect::<Vec<_>>();
+        let full = by_name(&full);
+        let file = vec![
+            exported("c1", CommandKind::Static, "Hey"),
+            exported("new", CommandKind::Static, "Hey"),
+        ];
+        let plan = ImportPlan::new(file, &[], Some(&full));
+        assert_eq!(plan.commands, [exported("c1", CommandKind::Static, "Hey")]);
+        assert_eq!(plan.rejected.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn imports_are_audited_with_the_commands() {
+        let database = testing::database().await;
+        let guild_id = GuildId::new(1);
+        let hi = exported("hi", CommandKind::Static, "Hi");
+        let event = Event::new(
+            Some(guild_id),
+            UserId::new(2),
+            AuditAction::CommandImport,
+            "/hi",
+        );
+
+        let mut conn = database.acquire().await.unwrap();
+        import_commands(&mut conn, guild_id, &[hi], Some(&event))
+            .await
+            .unwrap();
+
+        let audited = sqlx::query_scalar!("SELECT COUNT(*) FROM AuditLog")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(audited, 1);
+    }
+
+    #[tokio::test]
+    async fn imports_are_dropped_with_the_outer_transaction() {
+        let database = testing::database().await;
+        let guild_id = GuildId::new(1);
+
+        let mut conn = database.acquire().await.unwrap();
+        let mut transaction = conn.begin().await.unwrap();
+        let commands = [exported("hi", CommandKind::Static, "Hello")];
+        import_commands(&mut transaction, guild_id, &commands, None)
+            .await
+            .unwrap();
+        transaction.rollback().await.unwrap();
+
+        assert!(export_commands(&mut *conn, guild_id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn edits_only_change_the_named_command() {
+        let database = testing::database().await;
+
+        let mut conn = database.acquire().await.unwrap();
+        let commands = [
+            exported("hi", CommandKind::Static, "Hello"),
+            exported("bye", CommandKind::Static, "Goodbye"),
+        ];
+        for guild_id in [GuildId::new(1), GuildId::new(2)] {
+            import_commands(&mut conn, guild_id, &commands, None)
+                .await
+                .unwrap();
         }
+
+        let edit = CommandInfo {
+            kind: CommandKind::Choice,
+            content: "Hey|Yo".to_string(),
+        };
+        save_edit(&mut *conn, GuildId::new(1), "hi", &edit)
+            .await
+            .unwrap();
+
+        let edited = export_commands(&mut *conn, GuildId::new(1)).await.unwrap();
+        let hi = exported("hi", CommandKind::Choice, "Hey|Yo");
+        assert_eq!(
+            edited,
+            [exported("bye", CommandKind::Static, "Goodbye"), hi]
+        );
+        let untouched = export_commands(&mut *conn, GuildId::new(2)).await.unwrap();
+        assert_eq!(
+            untouched,
+            [
+                exported("bye", CommandKind::Static, "Goodbye"),
+                exported("hi", CommandKind::Static, "Hello")
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn imports_replace_commands_with_the_same_name() {
+        let database = testing::database().await;
+        let guild_id = GuildId::new(1);
+
+        let mut conn = database.acquire().await.unwrap();
+        let first = [
+            exported("hi", CommandKind::Static, "Hello"),
+            exported("bye", CommandKind::Static, "Goodbye"),
+        ];
+        import_commands(&mut conn, guild_id, &first, None)
+            .await
+            .unwrap();
+        let second = [exported("HI", CommandKind::Static, "Hey")];
+        import_commands(&mut conn, guild_id, &second, None)
+            .await
+            .unwrap();
+
+        let saved = export_commands(&mut *conn, guild_id).await.unwrap();
+        assert_eq!(
+            saved,
+            [
+                exported("bye", CommandKind::Static, "Goodbye"),
+                exported("hi", CommandKind::Static, "Hey")
+            ]
+        );
+        assert!(export_commands(&mut *conn, GuildId::new(2))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn refused_changes_write_nothing() {
+        let database = testing::database().await;
+        let guild_id = GuildId::new(1);
+        let author = UserId::new(2);
+
+        let mut conn = database.acquire().await.unwrap();
+        let existing = [exported("hi", CommandKind::Static, "Hello")];
+        import_commands(&mut conn, guild_id, &existing, None)
+            .await
+            .unwrap();
+        let guild_commands = by_name(&existing);
+        let hey = CommandInfo::from(&exported("hi", CommandKind::Static, "Hey"));
+
+        let added = save_new_command(
+            &mut conn,
+            &guild_commands,
+            guild_id,
+            "hi",
+            &hey,
+            None,
+            author,
+        )
+        .await
+        .unwrap();
+        assert_eq!(added, Err("The command already exists.".to_string()));
+
+        let edited = save_update(&mut conn, &guild_commands, guild_id, "bye", &hey, author)
+            .await
+            .unwrap();
+        assert_eq!(edited, Err("The command does not exist.".to_string()));
+
+        let full = (0..MAX_GUILD_COMMANDS)
+            .map(|i| exported(&format!("c{i}"), CommandKind::Static, "Hi"))
+            .collect::<Vec<_>>();
+        let full = by_name(&full);
+        let added = save_new_command(&mut conn, &full, guild_id, "new", &hey, None, author)
+            .await
+            .unwrap();
+        assert!(added.is_err());
+
+        let exported = export_commands(&mut *conn, guild_id).await.unwrap();
+        assert_eq!(exported, existing);
+        let audited = sqlx::query_scalar!("SELECT COUNT(*) FROM AuditLog")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(audited, 0);
     }
 }
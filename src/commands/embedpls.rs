@@ -1,8 +1,6 @@
-use serenity::all::{Member, RoleId};
+use serenity::all::Member;
 
-use crate::{common::bail_reply, Context, Result};
-
-const EMBED_ROLE: RoleId = RoleId::new(930791790490030100);
+use crate::{common::bail_reply, settings::Setting, Context, Result};
 
 /// Toggle the ability of embedding images/videos
 #[poise::command(guild_only, slash_command, prefix_command)]
@@ -20,11 +18,19 @@ pub async fn embedpls(
             .into_owned(),
     };
 
-    if member.roles.contains(&EMBED_ROLE) {
-        member.remove_role(ctx, EMBED_ROLE).await?;
+    let Some(embed_role) = ctx
+        .data()
+        .guild_role(member.guild_id, Setting::EmbedRole)
+        .await
+    else {
+        return bail_reply(ctx, "The embed role hasn't been set up on this server.").await;
+    };
+
+    if member.roles.contains(&embed_role) {
+        member.remove_role(ctx, embed_role).await?;
         bail_reply(ctx, "The embed role has been removed.").await
     } else {
-        member.add_role(ctx, EMBED_ROLE).await?;
+        member.add_role(ctx, embed_role).await?;
         bail_reply(ctx, "The embed role has been added.").await
     }
 }
@@ -0,0 +1,227 @@
+use poise::serenity_prelude::{
+    ButtonStyle, CreateActionRow, CreateButton, CreateInteractionResponseMessage, UserId,
+};
+use poise::CreateReply;
+use sqlx::{Connection, SqliteConnection};
+
+use crate::{common::update_response, metrics::timed_query, scheduler::Scheduler, Context, Result};
+
+const CONFIRM_BUTTON: &str = "forgetme-confirm";
+/// Owns the dinos of the users who asked to be forgotten.
+pub const UNOWNED: &str = "0";
+
+/// What was removed when forgetting someone.
+#[derive(Debug)]
+pub struct Forgotten {
+    pub dinos_released: u64,
+    pub rows_deleted: u64,
+}
+
+impl Forgotten {
+    pub fn describe(&self) -> String {
+        format!(
+            "{} records were deleted and {} dinos were released into the wild.",
+            self.rows_deleted, self.dinos_released
+        )
+    }
+}
+
+/// Delete everything the bot knows about you
+#[poise::command(slash_command, prefix_command)]
+pub async fn forgetme(ctx: Context<'_>) -> Result<()> {
+    let confirm_button = CreateButton::new(CONFIRM_BUTTON)
+        .emoji('🗑')
+        .label("Forget me")
+        .style(ButtonStyle::Danger);
+
+    let content = "This deletes your duel and RPG records, your Dino Bucks, your best mixus, \
+        your reminders and cooldowns, and releases all your dinos. It can't be undone.\n\
+        Are you SURE you want to do this?";
+    let reply_handle = ctx
+        .send(
+            CreateReply::default()
+                .components(vec![CreateActionRow::Buttons(vec![confirm_button])])
+                .content(content)
+                .ephemeral(true),
+        )
+        .await?;
+
+    let interaction = reply_handle
+        .message()
+        .await?
+        .await_component_interaction(ctx)
+        .author_id(ctx.author().id)
+        .custom_ids(vec![CONFIRM_BUTTON.to_string()])
+        .timeout(std::time::Duration::from_secs(30))
+        .await;
+
+    let Some(interaction) = interaction else {
+        reply_handle
+            .edit(
+                ctx,
+                CreateReply::default()
+                    .components(Vec::new())
+                    .content("Looks like you decided to stay, nothing was deleted."),
+            )
+            .await?;
+        return Ok(());
+    };
+
+    let mut conn = ctx.data().database.acquire().await?;
+    let forgotten = timed_query(
+        "forget_user",
+        forget_user(&mut conn, &ctx.data().scheduler, ctx.author().id),
+    )
+    .await?;
+
+    let message = CreateInteractionResponseMessage::new()
+        .components(Vec::new())
+        .content(format!("You have been forgotten. {}", forgotten.describe()));
+    interaction
+        .create_response(ctx, update_response(message))
+        .await?;
+
+    Ok(())
+}
+
+/// Deletes every row about `user_id` in one transaction. Their dinos go to [`UNOWNED`] so
+/// the names and images stay unique, and the gifts they made no longer point at them.
+pub async fn forget_user(
+    conn: &mut SqliteConnection,
+    scheduler: &Scheduler,
+    user_id: UserId,
+) -> Result<Forgotten> {
+    // The older tables store ids as text, the newer ones as integers
+    let text_id = user_id.to_string();
+    let int_id = user_id.get() as i64;
+
+    let mut transaction = conn.begin().await?;
+    let dinos_released = sqlx::query!(
+        "UPDATE Dino SET owner_id = ? WHERE owner_id = ?",
+        UNOWNED,
+        text_id
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    sqlx::query!(
+        "UPDATE DinoTransactions SET gifter_id = NULL WHERE gifter_id = ?",
+        text_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    let mut forgotten = Forgotten {
+        dinos_released,
+        rows_deleted: 0,
+    };
+    let task_ids = sqlx::query_scalar!(
+        "DELETE FROM Reminders WHERE user_id = ? RETURNING task_id",
+        int_id
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+    forgotten.rows_deleted += task_ids.len() as u64;
+    for task_id in task_ids.into_iter().flatten() {
+        scheduler.cancel(&mut *transaction, task_id).await?;
+    }
+
+    let queries = [
+        sqlx::query!("DELETE FROM DinoTransactions WHERE user_id = ?", text_id),
+        sqlx::query!("DELETE FROM DinoUser WHERE id = ?", text_id),
+        sqlx::query!("DELETE FROM DuelStats WHERE user_id = ?", text_id),
+        sqlx::query!("DELETE FROM RPGCharacter WHERE user_id = ?", text_id),
+        sqlx::query!("DELETE FROM User WHERE id = ?", text_id),
+        sqlx::query!("DELETE FROM BestMixu WHERE user_id = ?", int_id),
+        sqlx::query!("DELETE FROM Ledger WHERE user_id = ?", int_id),
+        sqlx::query!("DELETE FROM Wallet WHERE user_id = ?", int_id),
+        sqlx::query!("DELETE FROM Cooldowns WHERE user_id = ?", int_id),
+        sqlx::query!("DELETE FROM SimpleCommandUsage WHERE user_id = ?", int_id),
+    ];
+    for query in queries {
+        forgotten.rows_deleted += query.execute(&mut *transaction).await?.rows_affected();
+    }
+
+    // The audit log and the command history are kept for the mods, without saying who it was
+    sqlx::query!(
+        r#"UPDATE AuditLog SET user_id = 0 WHERE user_id = ?;
+        UPDATE AuditLog SET target_id = NULL WHERE target_id = ?;
+        UPDATE SimpleCommandRevisions SET author_id = NULL WHERE author_id = ?"#,
+        int_id,
+        int_id,
+        int_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(forgotten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[tokio::test]
+    async fn forgotten_users_leave_nothing_behind() {
+        let database = testing::database().await;
+        let scheduler = Scheduler::new(&database);
+        let mut conn = database.acquire().await.unwrap();
+        sqlx::query(
+            r#"INSERT INTO User (id, fav_color) VALUES ('1', '#00FF00'), ('2', NULL);
+            INSERT INTO DuelStats (user_id, wins) VALUES ('1', 3), ('2', 1);
+            INSERT INTO RPGCharacter (user_id) VALUES ('1');
+            INSERT INTO DinoUser (id) VALUES ('1'), ('2');
+            INSERT INTO Dino (id, owner_id, name, filename, created_at, body, mouth, eyes)
+                VALUES (1, '1', 'Rex', 'rex.png', 0, 'b', 'm', 'e');
+            INSERT INTO DinoTransactions (dino_id, user_id, gifter_id, type)
+                VALUES (1, '1', NULL, 'FAVOURITE'), (1, '2', '1', 'GIFT');
+            INSERT INTO BestMixu (user_id, score, tiles) VALUES (1, 12, '');
+            INSERT INTO Wallet (user_id, balance) VALUES (1, 50);
+            INSERT INTO Reminders (user_id, channel_id, message, remind_at, by_dm)
+                VALUES (1, 1, 'Feed Rex', 0, TRUE);
+            INSERT INTO AuditLog (guild_id, user_id, action, target_id, details)
+                VALUES (1, 1, 'dino_gift', 2, 'Rex');"#,
+        )
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+
+        let forgotten = forget_user(&mut conn, &scheduler, UserId::new(1))
+            .await
+            .unwrap();
+        assert_eq!(forgotten.dinos_released, 1);
+        assert_eq!(forgotten.rows_deleted, 8);
+
+        let owner = sqlx::query_scalar!("SELECT owner_id FROM Dino WHERE id = 1")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(owner, UNOWNED);
+
+        let mentions = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count: i64" FROM DinoTransactions
+            WHERE user_id = '1' OR gifter_id = '1'"#
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+        assert_eq!(mentions, 0);
+
+        let audited = sqlx::query_scalar!("SELECT user_id FROM AuditLog")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(audited, 0);
+
+        // Someone else's records are left alone
+        let wins = sqlx::query_scalar!("SELECT wins FROM DuelStats WHERE user_id = '2'")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(wins, 1);
+    }
+}
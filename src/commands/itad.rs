@@ -1,9 +1,9 @@
 use std::sync::OnceLock;
 
-use anyhow::{ensure, Context as AnyhowContext};
+use anyhow::{bail, ensure, Context as AnyhowContext};
 use reqwest::Client;
 
-use crate::{common::bail_reply, Context, Result};
+use crate::{common::bail_reply, config::Config, cooldowns::Action, Context, Result};
 
 const URL_GAME_SEARCH: &str = "https://api.isthereanydeal.com/games/search/v1";
 const URL_GAME_PRICES: &str = "https://api.isthereanydeal.com/games/prices/v3";
@@ -11,18 +11,37 @@ const BASE_GAME_PAGE_URL: &str = "https://isthereanydeal.com/game";
 
 pub static ITAD_CLIENT_ID: OnceLock<String> = OnceLock::new();
 
-pub fn initialize_client_id() -> Result<()> {
-    let client_id = std::env::var("ITAD_CLIENT_ID")?;
+pub fn initialize_client_id(config: &Config) -> Result<()> {
+    let Some(client_id) = config.itad_client_id.clone() else {
+        bail!("ITAD_CLIENT_ID was not provided");
+    };
     ITAD_CLIENT_ID.set(client_id).unwrap();
 
     Ok(())
 }
 
-#[poise::command(slash_command, prefix_command, global_cooldown = 30)]
+#[poise::command(slash_command, prefix_command)]
 pub async fn itad(
     ctx: Context<'_>,
     #[description = "The game you want to search"] game: String,
 ) -> Result<()> {
+    let cooldowns = &ctx.data().cooldowns;
+    let (guild_id, user_id) = (ctx.guild_id(), ctx.author().id);
+    if let Err(e) = cooldowns
+        .ensure_ready(
+            guild_id,
+            user_id,
+            Action::Itad,
+            "The command is on cooldown.",
+        )
+        .await
+    {
+        return bail_reply(ctx, e.to_string()).await;
+    }
+    cooldowns
+        .start(&ctx.data().database, guild_id, user_id, Action::Itad)
+        .await?;
+
     let deals = match get_deals(&game, ITAD_CLIENT_ID.get().unwrap()).await {
         Ok(deals) => deals,
         Err(err) => return bail_reply(ctx, err.to_string()).await,
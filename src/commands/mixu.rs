@@ -1,9 +1,11 @@
 use std::sync::atomic::{AtomicI64, Ordering};
 
+use crate::metrics::timed_query;
+use crate::wallet::{self, Reason};
 use crate::{Context, Result};
 
 use poise::serenity_prelude::{Emoji, Mention, UserId};
-use rand::{seq::SliceRandom, thread_rng};
+use rand::seq::SliceRandom;
 use serenity::futures::TryFutureExt;
 use sqlx::SqlitePool;
 use tokio::sync::OnceCell;
@@ -18,9 +20,9 @@ static MIXU_PIECES: OnceCell<Vec<Emoji>> = OnceCell::const_new();
 static BEST_MIXU_SCORE: AtomicI64 = AtomicI64::new(0);
 
 pub async fn set_initial_best_mixu_score(db: &SqlitePool) -> Result<()> {
-    let row = sqlx::query!("SELECT score FROM BestMixu ORDER BY rowid DESC LIMIT 1")
-        .fetch_optional(db)
-        .await?;
+    let query =
+        sqlx::query!("SELECT score FROM BestMixu ORDER BY rowid DESC LIMIT 1").fetch_optional(db);
+    let row = timed_query("get_best_mixu_score", query).await?;
 
     let score = row.map(|r| r.score).unwrap_or_default();
     BEST_MIXU_SCORE.store(score, Ordering::Relaxed);
@@ -31,14 +33,16 @@ pub async fn set_initial_best_mixu_score(db: &SqlitePool) -> Result<()> {
 /// Generate a random mixu
 #[poise::command(guild_only, slash_command, prefix_command)]
 pub async fn mixu(ctx: Context<'_>) -> Result<()> {
+    let mut rng = ctx.data().game_rng("mixu");
     let mut positions = MIKU_POSITIONS;
-    positions.shuffle(&mut thread_rng());
+    positions.shuffle(&mut rng);
 
     let pieces = MIXU_PIECES
         .get_or_try_init(|| retrieve_mixu_emojis(ctx))
         .await?;
     let mixu = stringify_mixu(pieces, &positions, MIXU_BANNER);
     let score = count_points(&positions);
+    rng.audit(format!("Scored {score}"));
 
     tokio::try_join!(
         update_max_score(ctx, score, &positions),
@@ -64,9 +68,9 @@ pub async fn mikustare(ctx: Context<'_>) -> Result<()> {
 /// See who sits on top of the mixu leaderboard
 #[poise::command(guild_only, slash_command, prefix_command)]
 pub async fn bestmixu(ctx: Context<'_>) -> Result<()> {
-    let record = sqlx::query!("SELECT user_id, tiles FROM BestMixu ORDER BY rowid DESC LIMIT 1")
-        .fetch_optional(&ctx.data().database)
-        .await?;
+    let query = sqlx::query!("SELECT user_id, tiles FROM BestMixu ORDER BY rowid DESC LIMIT 1")
+        .fetch_optional(&ctx.data().database);
+    let record = timed_query("get_best_mixu", query).await?;
 
     let Some((user_id, tiles)) = record.map(|r| (r.user_id, r.tiles)) else {
         ctx.say("The best Mixu is yet to come").await?;
@@ -169,14 +173,25 @@ async fn update_max_score(ctx: Context<'_>, score: i64, tiles: &[usize]) -> Resu
         .join(",");
 
     let user_id = ctx.author().id.get() as i64;
-    sqlx::query!(
+    let mut transaction = ctx.data().database.begin().await?;
+    let query = sqlx::query!(
         "INSERT INTO BestMixu (user_id, score, tiles) VALUES (?, ?, ?)",
         user_id,
         score,
         tiles
     )
-    .execute(&ctx.data().database)
+    .execute(&mut transaction);
+    let record = timed_query("update_max_score", query).await?;
+
+    wallet::pay_out(
+        &mut transaction,
+        ctx.author().id,
+        ctx.data().config.payouts.best_mixu,
+        Reason::BestMixu,
+        &record.last_insert_rowid().to_string(),
+    )
     .await?;
+    transaction.commit().await?;
 
     Ok(())
 }
@@ -1,35 +1,107 @@
+mod admin;
+#[cfg(feature = "ask")]
 mod ask;
+mod audit;
+#[cfg(feature = "colors")]
 mod colors;
+mod command_history;
+mod command_rules;
+mod command_stats;
+mod config;
+mod cooldowns;
+#[cfg(feature = "dino")]
 mod dino;
+#[cfg(feature = "duel")]
 mod duel;
 mod dynamic_commands;
+#[cfg(feature = "eightball")]
 mod eightball;
+#[cfg(feature = "embedpls")]
 mod embedpls;
+mod forgetme;
+#[cfg(feature = "itad")]
 mod itad;
+#[cfg(feature = "mixu")]
 mod mixu;
+#[cfg(feature = "poll")]
 mod poll;
+#[cfg(feature = "profile")]
+mod profile;
+#[cfg(feature = "quote")]
 mod quote;
+mod remind;
+#[cfg(feature = "rockpaperscissors")]
 mod rockpaperscissors;
+#[cfg(feature = "roll")]
 mod roll;
+#[cfg(feature = "rpg")]
 mod rpg;
+#[cfg(feature = "sudoku")]
 mod sudoku;
+mod template;
+#[cfg(feature = "wallet")]
+mod wallet;
 
-use crate::{Data, Error};
-use dino::setup_dinos;
-use dynamic_commands::CommandInfo;
-use poise::serenity_prelude::{all::CreateCommand, Context as SerenityContext};
+use crate::{config::Config, Data, Error};
+use anyhow::ensure;
+use poise::serenity_prelude::Context as SerenityContext;
 use poise::Command;
 use std::{collections::HashMap, sync::OnceLock};
 
-pub use dynamic_commands::{try_intercepting_command_call, CommandKind, SimpleCommands};
+pub use command_stats::prune_old_usage;
+#[cfg(feature = "dino")]
+pub use dino::{regenerate_missing_dino_images, setup_dinos};
+pub use dynamic_commands::{
+    export_commands, import_commands, try_intercepting_command_call, CommandInfo, CommandKind,
+    ExportedCommand, ImportPlan, SimpleCommands,
+};
+pub use remind::{deliver_reminder, forget_reminder};
 
 pub static DEFAULT_COMMANDS: OnceLock<Vec<String>> = OnceLock::new();
 
+/// Every top level command the bot knows about, whether it ends up enabled or not.
+pub const KNOWN_COMMANDS: &[&str] = &[
+    "admin",
+    "ask",
+    "audit",
+    "bestmixu",
+    "color",
+    "commands",
+    "config",
+    "cooldowns",
+    "dino",
+    "duel",
+    "duelstats",
+    "eightball",
+    "embedpls",
+    "forgetme",
+    "itad",
+    "mikustare",
+    "mixu",
+    "poll",
+    "profile",
+    "quote",
+    "remind",
+    "roll",
+    "rpg",
+    "rps",
+    "sudoku",
+    "uncolor",
+    "wallet",
+];
+
+#[cfg(uses_collectors)]
 pub async fn setup_collectors(ctx: &SerenityContext, user_data: &Data) {
-    tokio::select! {
-        _ = rpg::setup_rpg_summary(ctx, user_data) => {}
-        _ = dino::setup_dino_collector(ctx, user_data) => {}
-    }
+    tokio::join!(
+        async {
+            #[cfg(feature = "rpg")]
+            let _ = rpg::setup_rpg_summary(ctx, user_data).await;
+        },
+        async {
+            #[cfg(feature = "dino")]
+            let _ = dino::setup_dino_collector(ctx, user_data).await;
+        },
+    );
 }
 
 pub fn set_system_commands(commands: &[Command<Data, Error>]) {
@@ -37,64 +109,108 @@ pub fn set_system_commands(commands: &[Command<Data, Error>]) {
 }
 
 pub async fn register_dynamic_commands_for_every_guild(ctx: &SerenityContext, user_data: &Data) {
-    let commands_map = fetch_guild_commands(user_data)
-        .await
-        .expect("Could not fetch simple guild commands");
+    let commands_map = match fetch_guild_commands(user_data).await {
+        Ok(commands_map) => commands_map,
+        Err(e) => {
+            tracing::error!("Could not fetch the simple guild commands: {e}");
+            return;
+        }
+    };
 
-    register_guild_commands(ctx, &commands_map)
-        .await
-        .expect("Could not register simple guild commands");
+    register_guild_commands(ctx, &commands_map).await;
 
     let mut data_commands = user_data.simple_commands.write().await;
     *data_commands = commands_map;
 }
 
-pub async fn initialize_commands(database: &sqlx::SqlitePool) -> Vec<Command<Data, Error>> {
+pub async fn initialize_commands(
+    #[cfg(feature = "mixu")] database: &sqlx::SqlitePool,
+    config: &Config,
+) -> anyhow::Result<Vec<Command<Data, Error>>> {
     let mut commands = vec![
-        colors::color(),
-        colors::uncolor(),
-        duel::duel(),
-        duel::duelstats(),
+        admin::admin(),
+        audit::audit(),
+        config::config(),
+        cooldowns::cooldowns(),
         dynamic_commands::commands(),
-        eightball::eightball(),
-        embedpls::embedpls(),
-        mixu::mikustare(),
-        poll::poll(),
-        quote::quote(),
-        rockpaperscissors::rps(),
-        roll::roll(),
-        rpg::rpg(),
-        sudoku::sudoku(),
+        forgetme::forgetme(),
+        remind::remind(),
     ];
 
-    match ask::initialize_app_id() {
+    #[cfg(feature = "colors")]
+    commands.extend([colors::color(), colors::uncolor()]);
+    #[cfg(feature = "duel")]
+    commands.extend([duel::duel(), duel::duelstats()]);
+    #[cfg(feature = "eightball")]
+    commands.push(eightball::eightball());
+    #[cfg(feature = "embedpls")]
+    commands.push(embedpls::embedpls());
+    #[cfg(feature = "mixu")]
+    commands.push(mixu::mikustare());
+    #[cfg(feature = "poll")]
+    commands.push(poll::poll());
+    #[cfg(feature = "profile")]
+    commands.push(profile::profile());
+    #[cfg(feature = "quote")]
+    commands.push(quote::quote());
+    #[cfg(feature = "rockpaperscissors")]
+    commands.push(rockpaperscissors::rps());
+    #[cfg(feature = "roll")]
+    commands.push(roll::roll());
+    #[cfg(feature = "rpg")]
+    commands.push(rpg::rpg());
+    #[cfg(feature = "sudoku")]
+    commands.push(sudoku::sudoku());
+    #[cfg(feature = "wallet")]
+    commands.push(wallet::wallet());
+
+    #[cfg(feature = "ask")]
+    match ask::initialize_app_id(config) {
         Ok(_) => commands.push(ask::ask()),
-        Err(_) => eprintln!("[WARNING] /ask was disabled because WOLFRAM_APP_ID was not provided."),
+        Err(_) => tracing::warn!("/ask was disabled because WOLFRAM_APP_ID was not provided."),
     }
 
-    match itad::initialize_client_id() {
+    #[cfg(feature = "itad")]
+    match itad::initialize_client_id(config) {
         Ok(_) => commands.push(itad::itad()),
-        Err(_) => eprintln!(
-            "[WARNING] /itad command was disabled because ITAD_CLIENT_ID was not provided."
-        ),
+        Err(_) => {
+            tracing::warn!("/itad command was disabled because ITAD_CLIENT_ID was not provided.")
+        }
     }
 
+    #[cfg(feature = "mixu")]
     match mixu::set_initial_best_mixu_score(database).await {
         Ok(_) => {
             commands.push(mixu::mixu());
             commands.push(mixu::bestmixu())
         },
-        Err(e) => eprintln!(
-            "[WARNING] /mixu and /bestmixu commands were disabled because the bot failed to retrieve max mixu score: {e}"
+        Err(e) => tracing::warn!(
+            "/mixu and /bestmixu commands were disabled because the bot failed to retrieve max mixu score: {e}"
         ),
     }
 
-    match setup_dinos() {
+    #[cfg(feature = "dino")]
+    match setup_dinos(&config.assets) {
         Ok(_) => commands.push(dino::dino()),
-        Err(e) => eprintln!("[WARNING] /dino commands were disabled because something went wrong while setting the fragments: {e}")
+        Err(e) => tracing::warn!("/dino commands were disabled because something went wrong while setting the fragments: {e}")
     }
 
-    commands
+    if let Some(enabled) = &config.enabled_commands {
+        let unknown = enabled
+            .iter()
+            .filter(|name| !KNOWN_COMMANDS.contains(&name.as_str()))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        ensure!(
+            unknown.is_empty(),
+            "Unknown commands in enabled_commands: {}",
+            unknown.join(", ")
+        );
+
+        commands.retain(|c| config.is_command_enabled(&c.name));
+    }
+
+    Ok(commands)
 }
 
 #[derive(Debug)]
@@ -127,24 +243,22 @@ async fn fetch_guild_commands(user_data: &Data) -> anyhow::Result<SimpleCommands
     Ok(commands_map)
 }
 
-async fn register_guild_commands(
-    ctx: &SerenityContext,
-    commands_map: &SimpleCommands,
-) -> anyhow::Result<()> {
+/// Sets every guild's commands, a guild that refuses them doesn't stop the others.
+async fn register_guild_commands(ctx: &SerenityContext, commands_map: &SimpleCommands) {
     for id in ctx.cache.guilds() {
-        let Some(names) = commands_map.get(&(id.get() as i64)) else {
-            // Reset commands if there aren't any for this guild
-            id.set_commands(&ctx.http, Vec::new()).await?;
-            continue;
-        };
-
-        let commands = names
-            .iter()
-            .map(|(name, _content)| CreateCommand::new(name).description("A simple text command"))
+        // Reset commands if there aren't any for this guild
+        let commands = commands_map
+            .get(&(id.get() as i64))
+            .into_iter()
+            .flatten()
+            .map(|(name, info)| dynamic_commands::create_command(name, info))
             .collect::<Vec<_>>();
 
-        id.set_commands(ctx, commands).await?;
+        if let Err(e) = id.set_commands(ctx, commands).await {
+            tracing::error!(
+                guild = id.get(),
+                "Could not register the simple guild commands: {e}"
+            );
+        }
     }
-
-    Ok(())
 }
@@ -1,9 +1,9 @@
-use crate::common::{bail_reply, ephemeral_reply};
+use crate::audit::{self, AuditAction, Event};
+use crate::messaging::{DiscordMessenger, Messenger, Reply};
 use crate::{Context, Result};
 
-use poise::serenity_prelude::MessageId;
-use poise::{ChoiceParameter, CreateReply};
-use serenity::all::{CreateEmbed, EditMessage, Message, ReactionType};
+use poise::serenity_prelude::{ChannelId, MessageId};
+use poise::ChoiceParameter;
 use tokio::sync::Mutex;
 
 const ICONS_LEN: usize = 20;
@@ -21,11 +21,66 @@ struct Choice {
 #[derive(Default)]
 struct Poll {
     message_id: MessageId,
+    channel_id: ChannelId,
     question: String,
     choices: Vec<Choice>,
     available_icons: Vec<&'static str>,
 }
 
+impl Poll {
+    fn new(message_id: MessageId, channel_id: ChannelId, question: String) -> Self {
+        Self {
+            message_id,
+            channel_id,
+            question,
+            choices: Vec::new(),
+            available_icons: ICONS.to_vec(),
+        }
+    }
+
+    /// Adds the choice and returns its icon, `None` once every icon is taken.
+    fn add_choice(&mut self, owner: u64, text: String) -> Option<&'static str> {
+        let icon = self.available_icons.pop()?;
+        self.choices.push(Choice { icon, owner, text });
+
+        Some(icon)
+    }
+
+    /// Removes a choice submitted by `owner`, the error is meant for the user.
+    fn remove_choice(&mut self, owner: u64, text: &str) -> Result<Choice, &'static str> {
+        let text = text.to_lowercase();
+        let Some(position) = self
+            .choices
+            .iter()
+            .position(|c| c.text.to_lowercase() == text)
+        else {
+            return Err("I couldn't find the choice.");
+        };
+
+        if self.choices[position].owner != owner {
+            return Err("That wasn't a choice you submitted.");
+        }
+
+        let choice = self.choices.remove(position);
+        self.available_icons.push(choice.icon);
+
+        Ok(choice)
+    }
+
+    /// The most voted choice with its vote count, from the bot's reactions and their counts.
+    fn winner(&self, reactions: impl Iterator<Item = (String, u64)>) -> Option<(&Choice, u64)> {
+        let (icon, count) = reactions.max_by_key(|(_, count)| *count)?;
+        let choice = self
+            .choices
+            .iter()
+            .find(|c| c.icon == icon)
+            .expect("Expected a reaction sent by the bot to be in the poll in memory.");
+
+        // The bot's own reaction isn't a vote
+        Some((choice, count - 1))
+    }
+}
+
 type CustomData = Mutex<Option<Poll>>;
 
 #[poise::command(
@@ -43,26 +98,28 @@ async fn new(
     ctx: Context<'_>,
     #[description = "What you want to ask"] question: String,
 ) -> Result<()> {
-    let custom_data = unwrap_custom_data(ctx);
-    let mut poll = custom_data.lock().await;
+    let mut poll = unwrap_custom_data(ctx).lock().await;
+    let messenger = DiscordMessenger::new(ctx);
+
+    open_poll(&messenger, &mut poll, ctx.channel_id(), question).await
+}
 
-    if (*poll).is_some() {
+async fn open_poll(
+    messenger: &impl Messenger,
+    poll: &mut Option<Poll>,
+    channel_id: ChannelId,
+    question: String,
+) -> Result<()> {
+    if poll.is_some() {
         let msg = "There's a poll running already. Close it before creating a new one.";
-        return bail_reply(ctx, msg).await;
+        messenger.send(Reply::ephemeral(msg)).await?;
+        return Ok(());
     }
 
-    let embed = CreateEmbed::new()
-        .title(&question)
-        .description("Add an option with /poll add_option <option>");
-    let msg = ctx.send(CreateReply::default().embed(embed)).await?;
-    let message_id = msg.into_message().await?.id;
+    let reply = Reply::embed(&question, "Add an option with /poll add_option <option>");
+    let message_id = messenger.send(reply).await?;
 
-    *poll = Some(Poll {
-        message_id,
-        question,
-        choices: Vec::new(),
-        available_icons: ICONS.to_vec(),
-    });
+    *poll = Some(Poll::new(message_id, channel_id, question));
 
     Ok(())
 }
@@ -78,58 +135,62 @@ async fn close(
     ctx: Context<'_>,
     #[description = "Whether to announce the winner or not"] kind: Option<CloseKind>,
 ) -> Result<()> {
-    let custom_data = unwrap_custom_data(ctx);
-    let mut poll = custom_data.lock().await;
+    let mut poll = unwrap_custom_data(ctx).lock().await;
+    let messenger = DiscordMessenger::new(ctx);
 
-    let Some(found_poll) = &mut *poll else {
-        return bail_reply(ctx, "There's no poll to close").await;
+    let kind = kind.unwrap_or(CloseKind::Silent);
+    let Some(closed) = close_poll(&messenger, &mut poll, ctx.channel_id(), &kind).await? else {
+        return Ok(());
+    };
+
+    let details = format!("{} ({})", closed.question, kind.name());
+    let event = Event::from_ctx(ctx, AuditAction::PollClose, details);
+    audit::record(&ctx.data().database, &event).await?;
+
+    Ok(())
+}
+
+/// Returns the poll that was closed, if there was one.
+async fn close_poll(
+    messenger: &impl Messenger,
+    poll: &mut Option<Poll>,
+    channel_id: ChannelId,
+    kind: &CloseKind,
+) -> Result<Option<Poll>> {
+    let Some(found_poll) = &*poll else {
+        messenger
+            .send(Reply::ephemeral("There's no poll to close"))
+            .await?;
+        return Ok(None);
     };
 
-    let kind = kind.unwrap_or(CloseKind::Silent);
     let reply = match kind {
-        CloseKind::Silent => ephemeral_reply("The poll has been closed!"),
+        CloseKind::Silent => Reply::ephemeral("The poll has been closed!"),
         CloseKind::Announce => {
-            let Ok(message) = ctx.channel_id().message(ctx, found_poll.message_id).await else {
+            if found_poll.channel_id != channel_id {
                 let msg = "This channel is not the same as the one with the poll.";
-                return bail_reply(ctx, msg).await;
-            };
-            announce_winner(found_poll, message).await
+                messenger.send(Reply::ephemeral(msg)).await?;
+                return Ok(None);
+            }
+            let reactions = messenger.own_reactions(found_poll.message_id).await?;
+            announce_winner(found_poll, reactions)
         }
     };
 
-    *poll = None;
-    ctx.send(reply).await?;
+    messenger.send(reply).await?;
 
-    Ok(())
+    Ok(poll.take())
 }
 
-async fn announce_winner(poll: &Poll, message: Message) -> CreateReply {
-    let Some(reaction) = message
-        .reactions
-        .iter()
-        .filter(|r| r.me)
-        .max_by(|a, b| a.count.cmp(&b.count))
-    else {
-        return ephemeral_reply("There were no choices for this poll, I closed it now though.");
+fn announce_winner(poll: &Poll, reactions: Vec<(String, u64)>) -> Reply {
+    let Some((choice, votes)) = poll.winner(reactions.into_iter()) else {
+        return Reply::ephemeral("There were no choices for this poll, I closed it now though.");
     };
 
-    let reaction_str = reaction.reaction_type.to_string();
-    let choice = poll
-        .choices
-        .iter()
-        .find(|c| c.icon == reaction_str)
-        .expect("Expected a reaction sent by the bot to be in the poll in memory.");
-
-    let embed = CreateEmbed::default()
-        .title(format!("{} winner:", poll.question))
-        .description(format!(
-            "{} {} with {} votes!",
-            choice.icon,
-            choice.text,
-            reaction.count - 1
-        ));
-
-    CreateReply::default().embed(embed)
+    Reply::embed(
+        format!("{} winner:", poll.question),
+        format!("{} {} with {votes} votes!", choice.icon, choice.text),
+    )
 }
 
 #[poise::command(guild_only, slash_command)]
@@ -139,35 +200,43 @@ async fn choice(
     #[max_length = 25]
     choice: String,
 ) -> Result<()> {
-    let custom_data = unwrap_custom_data(ctx);
-    let mut poll = custom_data.lock().await;
+    let mut poll = unwrap_custom_data(ctx).lock().await;
+    let messenger = DiscordMessenger::new(ctx);
+
+    let owner = ctx.author().id.get();
+    submit_choice(&messenger, &mut poll, ctx.channel_id(), owner, choice).await
+}
 
-    let Some(poll) = &mut *poll else {
+async fn submit_choice(
+    messenger: &impl Messenger,
+    poll: &mut Option<Poll>,
+    channel_id: ChannelId,
+    owner: u64,
+    choice: String,
+) -> Result<()> {
+    let Some(poll) = poll else {
         let msg = "There's no poll running, create one with /poll new <question>";
-        return bail_reply(ctx, msg).await;
+        messenger.send(Reply::ephemeral(msg)).await?;
+        return Ok(());
     };
 
-    let Ok(mut message) = ctx.channel_id().message(ctx, poll.message_id).await else {
-        return bail_reply(ctx, "Couldn't find the poll in this channel").await;
-    };
+    if poll.channel_id != channel_id {
+        let msg = "Couldn't find the poll in this channel";
+        messenger.send(Reply::ephemeral(msg)).await?;
+        return Ok(());
+    }
 
-    let Some(icon) = poll.available_icons.pop() else {
-        return bail_reply(ctx, "Sorry buddy but there are enough options already.").await;
+    let Some(icon) = poll.add_choice(owner, choice) else {
+        let msg = "Sorry buddy but there are enough options already.";
+        messenger.send(Reply::ephemeral(msg)).await?;
+        return Ok(());
     };
 
-    poll.choices.push(Choice {
-        icon,
-        owner: ctx.author().id.get(),
-        text: choice,
-    });
-
-    message
-        .edit(ctx, EditMessage::default().embed(poll_embed(poll)))
-        .await?;
+    messenger.edit(poll.message_id, poll_embed(poll)).await?;
 
     tokio::try_join!(
-        message.react(ctx, ReactionType::Unicode(icon.to_string())),
-        ctx.send(ephemeral_reply("Choice added."))
+        messenger.react(poll.message_id, icon),
+        messenger.send(Reply::ephemeral("Choice added."))
     )?;
 
     Ok(())
@@ -178,41 +247,47 @@ async fn whoops(
     ctx: Context<'_>,
     #[description = "The choice you want to remove"] choice: String,
 ) -> Result<()> {
-    let custom_data = unwrap_custom_data(ctx);
-    let mut poll = custom_data.lock().await;
-
-    let Some(poll) = &mut *poll else {
-        return bail_reply(ctx, "There's no poll available my guy.").await;
-    };
+    let mut poll = unwrap_custom_data(ctx).lock().await;
+    let messenger = DiscordMessenger::new(ctx);
 
-    let Ok(mut message) = ctx.channel_id().message(ctx, poll.message_id).await else {
-        return bail_reply(ctx, "Couldn't find the poll in this channel.").await;
-    };
+    let owner = ctx.author().id.get();
+    withdraw_choice(&messenger, &mut poll, ctx.channel_id(), owner, &choice).await
+}
 
-    let choice = choice.to_lowercase();
-    let Some(position) = poll
-        .choices
-        .iter()
-        .position(|c| c.text.to_lowercase() == choice)
-    else {
-        return bail_reply(ctx, "I couldn't find the choice.").await;
+async fn withdraw_choice(
+    messenger: &impl Messenger,
+    poll: &mut Option<Poll>,
+    channel_id: ChannelId,
+    owner: u64,
+    choice: &str,
+) -> Result<()> {
+    let Some(poll) = poll else {
+        let msg = "There's no poll available my guy.";
+        messenger.send(Reply::ephemeral(msg)).await?;
+        return Ok(());
     };
 
-    if poll.choices[position].owner != ctx.author().id.get() {
-        return bail_reply(ctx, "That wasn't a choice you submitted.").await;
+    if poll.channel_id != channel_id {
+        let msg = "Couldn't find the poll in this channel.";
+        messenger.send(Reply::ephemeral(msg)).await?;
+        return Ok(());
     }
 
-    let choice = poll.choices.remove(position);
-    poll.available_icons.push(choice.icon);
+    let choice = match poll.remove_choice(owner, choice) {
+        Ok(choice) => choice,
+        Err(msg) => {
+            messenger.send(Reply::ephemeral(msg)).await?;
+            return Ok(());
+        }
+    };
 
-    message
-        .edit(ctx, EditMessage::default().embed(poll_embed(poll)))
-        .await?;
-    message
-        .delete_reaction_emoji(ctx, ReactionType::Unicode(choice.icon.to_string()))
+    messenger.edit(poll.message_id, poll_embed(poll)).await?;
+    messenger
+        .remove_reactions(poll.message_id, choice.icon)
         .await?;
 
-    ctx.send(ephemeral_reply("That choice has been removed."))
+    messenger
+        .send(Reply::ephemeral("That choice has been removed."))
         .await?;
 
     Ok(())
@@ -226,7 +301,7 @@ fn unwrap_custom_data(ctx: Context<'_>) -> &CustomData {
         .expect("Expected to have passed the poll data as custom_data")
 }
 
-fn poll_embed(poll: &Poll) -> CreateEmbed {
+fn poll_embed(poll: &Poll) -> Reply {
     let description = poll
         .choices
         .iter()
@@ -234,7 +309,138 @@ fn poll_embed(poll: &Poll) -> CreateEmbed {
         .collect::<Vec<_>>()
         .join("\n");
 
-    CreateEmbed::default()
-        .title(&poll.question)
-        .description(description)
+    Reply::embed(&poll.question, description)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{Event, FakeMessenger};
+
+    const ALICE: u64 = 1;
+    const BOB: u64 = 2;
+
+    #[test]
+    fn poll_lifecycle() {
+        let mut poll = Poll::new(
+            MessageId::new(1),
+            ChannelId::new(1),
+            "Best dino?".to_string(),
+        );
+
+        let stego = poll.add_choice(ALICE, "Stegosaurus".to_string()).unwrap();
+        let rex = poll.add_choice(BOB, "T-Rex".to_string()).unwrap();
+        let ptero = poll.add_choice(BOB, "Pterodactyl".to_string()).unwrap();
+        assert_ne!(stego, rex);
+
+        assert!(poll.remove_choice(ALICE, "t-rex").is_err());
+        assert!(poll.remove_choice(ALICE, "Raptor").is_err());
+        assert_eq!(poll.remove_choice(BOB, "pterodactyl").unwrap().icon, ptero);
+
+        // The removed choice's icon is handed out again
+        let raptor = poll.add_choice(ALICE, "Raptor".to_string()).unwrap();
+        assert_eq!(raptor, ptero);
+
+        let reactions = [(stego, 3), (rex, 5), (raptor, 1)];
+        let reactions = reactions
+            .iter()
+            .map(|(icon, count)| (icon.to_string(), *count));
+        let (winner, votes) = poll.winner(reactions).unwrap();
+        assert_eq!((winner.text.as_str(), votes), ("T-Rex", 4));
+    }
+
+    #[test]
+    fn polls_run_out_of_icons() {
+        let mut poll = Poll::new(
+            MessageId::new(1),
+            ChannelId::new(1),
+            "Pick a number".to_string(),
+        );
+        for i in 0..ICONS_LEN {
+            assert!(poll.add_choice(ALICE, i.to_string()).is_some());
+        }
+
+        assert!(poll.add_choice(ALICE, "one more".to_string()).is_none());
+        assert!(poll.winner(std::iter::empty()).is_none());
+    }
+
+    #[tokio::test]
+    async fn polls_are_run_through_the_messenger() {
+        let messenger = FakeMessenger::default();
+        let channel_id = ChannelId::new(1);
+        let mut poll = None;
+
+        let question = "Best dino?".to_string();
+        open_poll(&messenger, &mut poll, channel_id, question.clone())
+            .await
+            .unwrap();
+        open_poll(&messenger, &mut poll, channel_id, question)
+            .await
+            .unwrap();
+        let message_id = poll.as_ref().unwrap().message_id;
+
+        for (owner, text) in [(ALICE, "Stegosaurus"), (BOB, "T-Rex"), (BOB, "Raptor")] {
+            submit_choice(&messenger, &mut poll, channel_id, owner, text.to_string())
+                .await
+                .unwrap();
+        }
+        let elsewhere = ChannelId::new(2);
+        submit_choice(&messenger, &mut poll, elsewhere, ALICE, "Ptero".to_string())
+            .await
+            .unwrap();
+        withdraw_choice(&messenger, &mut poll, channel_id, ALICE, "t-rex")
+            .await
+            .unwrap();
+        withdraw_choice(&messenger, &mut poll, channel_id, BOB, "raptor")
+            .await
+            .unwrap();
+
+        messenger.vote(message_id, "🎉");
+        messenger.vote(message_id, "🎉");
+        messenger.vote(message_id, "✨");
+        close_poll(&messenger, &mut poll, channel_id, &CloseKind::Announce)
+            .await
+            .unwrap()
+            .expect("Expected the poll to be closed");
+        assert!(poll.is_none());
+
+        let choices = |lines: &str| Reply::embed("Best dino?", lines);
+        let events = messenger.events();
+        let reply = |event: &Event| match event {
+            Event::Sent(_, reply) => reply.clone(),
+            _ => panic!("Expected a message, not {event:?}"),
+        };
+        assert_eq!(
+            reply(&events[1]),
+            Reply::ephemeral("There's a poll running already. Close it before creating a new one.")
+        );
+        assert_eq!(
+            events[2],
+            Event::Edited(message_id, choices("✨ Stegosaurus"))
+        );
+        assert_eq!(events[3], Event::Reacted(message_id, "✨".to_string()));
+        assert_eq!(
+            events[8],
+            Event::Edited(message_id, choices("✨ Stegosaurus\n🎉 T-Rex\n🎊 Raptor"))
+        );
+        assert_eq!(
+            reply(&events[11]),
+            Reply::ephemeral("Couldn't find the poll in this channel")
+        );
+        assert_eq!(
+            reply(&events[12]),
+            Reply::ephemeral("That wasn't a choice you submitted.")
+        );
+        assert_eq!(
+            events[13..15],
+            [
+                Event::Edited(message_id, choices("✨ Stegosaurus\n🎉 T-Rex")),
+                Event::Unreacted(message_id, "🎊".to_string()),
+            ]
+        );
+        assert_eq!(
+            reply(events.last().unwrap()),
+            Reply::embed("Best dino? winner:", "🎉 T-Rex with 2 votes!")
+        );
+    }
 }
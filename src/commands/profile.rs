@@ -0,0 +1,268 @@
+use std::io::Cursor;
+
+use image::{imageops::overlay, DynamicImage, ImageOutputFormat, Rgba, RgbaImage};
+use poise::serenity_prelude::{CreateAttachment, CreateEmbed, CreateEmbedAuthor, User, UserId};
+use poise::CreateReply;
+use sqlx::SqliteConnection;
+
+use super::dino::{favourite_dinos, FavouriteDinos};
+use super::rpg::find_ladder_rank;
+use crate::{
+    common::{avatar_url, name, to_color},
+    metrics::timed_query,
+    wallet::{self, CURRENCY},
+    Context, Result,
+};
+
+const BANNER_WIDTH: u32 = 600;
+const BANNER_HEIGHT: u32 = 140;
+const SWATCH_SIZE: u32 = 100;
+const BANNER_DINOS: usize = 3;
+const DEFAULT_COLOUR: u32 = 0x77618F;
+
+struct DuelRecord {
+    wins: i64,
+    losses: i64,
+    draws: i64,
+    win_streak: i64,
+    loss_streak: i64,
+}
+
+struct RpgRecord {
+    wins: i64,
+    losses: i64,
+    draws: i64,
+    elo_rank: i64,
+}
+
+/// Everything the bot keeps about a user, gathered from every game.
+struct Profile {
+    duels: Option<DuelRecord>,
+    rpg: Option<RpgRecord>,
+    dino_count: i64,
+    favourite_dinos: FavouriteDinos,
+    fav_color: Option<u32>,
+    best_mixu: Option<i64>,
+    balance: i64,
+}
+
+impl Profile {
+    fn fields(&self) -> Vec<(&'static str, String, bool)> {
+        let duels = match &self.duels {
+            Some(d) => {
+                let streak = match (d.win_streak, d.loss_streak) {
+                    (0, 0) => "No streak".to_string(),
+                    (0, losses) => format!("Streak: **{losses} losses**"),
+                    (wins, _) => format!("Streak: **{wins} wins**"),
+                };
+                format!("{}W {}L {}D\n{streak}", d.wins, d.losses, d.draws)
+            }
+            None => "Never dueled".to_string(),
+        };
+
+        let rpg = match &self.rpg {
+            Some(r) => {
+                let rank = find_ladder_rank(r.elo_rank);
+                format!(
+                    "{} - {} *{} League*\n{}W {}L {}D",
+                    r.elo_rank, rank.icon, rank.name, r.wins, r.losses, r.draws
+                )
+            }
+            None => "Yet to test their steel".to_string(),
+        };
+
+        let dinos = match (self.dino_count, self.favourite_dinos.names.is_empty()) {
+            (0, _) => "No dinos".to_string(),
+            (1, true) => "1 dino".to_string(),
+            (count, true) => format!("{count} dinos"),
+            (count, false) => format!(
+                "{count} dinos\nFavourites: {}",
+                self.favourite_dinos.names.join(", ")
+            ),
+        };
+
+        let color = self
+            .fav_color
+            .map_or_else(|| "Not set".to_string(), |c| format!("#{c:06X}"));
+        let mixu = self
+            .best_mixu
+            .map_or_else(|| "No record".to_string(), |s| format!("{s} points"));
+
+        vec![
+            ("Duels", duels, true),
+            ("RPG", rpg, true),
+            ("Dinos", dinos, true),
+            ("Favorite color", color, true),
+            ("Best mixu", mixu, true),
+            (CURRENCY, self.balance.to_string(), true),
+        ]
+    }
+}
+
+/// Everything about you, or someone else, in one place
+#[poise::command(guild_only, slash_command, prefix_command)]
+pub async fn profile(
+    ctx: Context<'_>,
+    #[description = "Whose profile to show"] user: Option<User>,
+) -> Result<()> {
+    ctx.defer().await?;
+    let user = user.as_ref().unwrap_or_else(|| ctx.author());
+
+    let mut conn = ctx.data().database.acquire().await?;
+    let profile = timed_query("fetch_profile", fetch_profile(&mut conn, user.id)).await?;
+
+    let banner = render_banner(profile.fav_color, &profile.favourite_dinos.images);
+    let mut bytes: Vec<u8> = Vec::new();
+    DynamicImage::ImageRgba8(banner)
+        .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)?;
+
+    let filename = format!("{}_profile.png", user.id);
+    let user_name = name(&ctx, user).await;
+    let embed = CreateEmbed::default()
+        .colour(profile.fav_color.unwrap_or(DEFAULT_COLOUR))
+        .author(CreateEmbedAuthor::new(format!("{user_name}'s profile")).icon_url(avatar_url(user)))
+        .fields(profile.fields())
+        .image(format!("attachment://{filename}"));
+
+    ctx.send(
+        CreateReply::default()
+            .embed(embed)
+            .attachment(CreateAttachment::bytes(bytes, filename)),
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn fetch_profile(conn: &mut SqliteConnection, user_id: UserId) -> Result<Profile> {
+    // The older tables store ids as text, the newer ones as integers
+    let text_id = user_id.to_string();
+    let int_id = user_id.get() as i64;
+
+    let duels = sqlx::query_as!(
+        DuelRecord,
+        "SELECT wins, losses, draws, win_streak, loss_streak FROM DuelStats WHERE user_id = ?",
+        text_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let rpg = sqlx::query_as!(
+        RpgRecord,
+        "SELECT wins, losses, draws, elo_rank FROM RPGCharacter WHERE user_id = ?",
+        text_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let dino_count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count: i64" FROM Dino WHERE owner_id = ?"#,
+        text_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let fav_color = sqlx::query_scalar!("SELECT fav_color FROM User WHERE id = ?", text_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .flatten();
+
+    let best_mixu = sqlx::query_scalar!(
+        r#"SELECT MAX(score) as "score: i64" FROM BestMixu WHERE user_id = ?"#,
+        int_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let favourite_dinos = favourite_dinos(&mut *conn, &text_id, BANNER_DINOS as i64).await?;
+
+    Ok(Profile {
+        duels,
+        rpg,
+        dino_count,
+        favourite_dinos,
+        fav_color: fav_color.as_deref().and_then(to_color),
+        best_mixu,
+        balance: wallet::balance(&mut *conn, user_id).await?,
+    })
+}
+
+fn rgba(colour: u32, shade: f32) -> Rgba<u8> {
+    let channel = |shift: u32| (((colour >> shift) & 0xFF) as f32 * shade) as u8;
+    Rgba([channel(16), channel(8), channel(0), 255])
+}
+
+fn fill_square(image: &mut RgbaImage, x: u32, y: u32, size: u32, colour: Rgba<u8>) {
+    for dx in 0..size {
+        for dy in 0..size {
+            image.put_pixel(x + dx, y + dy, colour);
+        }
+    }
+}
+
+/// A banner fading out of the favourite color, with a swatch of it on the left and the
+/// favourite dinos on the right.
+fn render_banner(fav_color: Option<u32>, dinos: &[DynamicImage]) -> RgbaImage {
+    let base = fav_color.unwrap_or(DEFAULT_COLOUR);
+    let mut banner = RgbaImage::from_fn(BANNER_WIDTH, BANNER_HEIGHT, |x, _| {
+        rgba(base, 1.0 - 0.6 * x as f32 / BANNER_WIDTH as f32)
+    });
+
+    let margin = (BANNER_HEIGHT - SWATCH_SIZE) / 2;
+    if let Some(colour) = fav_color {
+        let border = Rgba([255, 255, 255, 255]);
+        fill_square(&mut banner, margin - 2, margin - 2, SWATCH_SIZE + 4, border);
+        fill_square(&mut banner, margin, margin, SWATCH_SIZE, rgba(colour, 1.0));
+    }
+
+    let mut x = BANNER_WIDTH as i64;
+    for dino in dinos.iter().take(BANNER_DINOS) {
+        x -= (dino.width() + margin) as i64;
+        let y = BANNER_HEIGHT.saturating_sub(dino.height()) / 2;
+        overlay(&mut banner, dino, x, y.into());
+    }
+
+    banner
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn banner_shows_the_favourite_color_and_dinos() {
+        let dino = DynamicImage::ImageRgba8(RgbaImage::from_pixel(10, 10, Rgba([0, 255, 0, 255])));
+        let banner = render_banner(Some(0xFF0000), &[dino]);
+
+        assert_eq!(banner.dimensions(), (BANNER_WIDTH, BANNER_HEIGHT));
+        assert_eq!(banner.get_pixel(70, 70), &Rgba([255, 0, 0, 255]));
+        assert_eq!(
+            banner.get_pixel(BANNER_WIDTH - 25, 70),
+            &Rgba([0, 255, 0, 255])
+        );
+    }
+
+    #[tokio::test]
+    async fn profiles_gather_every_game() {
+        let database = testing::database().await;
+        let mut conn = database.acquire().await.unwrap();
+        sqlx::query(
+            r#"INSERT INTO User (id, fav_color) VALUES ('1', '#00FF00');
+            INSERT INTO DuelStats (user_id, wins, losses, win_streak) VALUES ('1', 3, 1, 2);
+            INSERT INTO BestMixu (user_id, score, tiles) VALUES (1, 12, '');"#,
+        )
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+
+        let profile = fetch_profile(&mut conn, UserId::new(1)).await.unwrap();
+        assert_eq!(profile.fav_color, Some(0x00FF00));
+        assert_eq!(profile.best_mixu, Some(12));
+        assert!(profile.rpg.is_none());
+
+        let fields = profile.fields();
+        assert_eq!(fields[0].1, "3W 1L 0D\nStreak: **2 wins**");
+        assert_eq!(fields[2].1, "No dinos");
+    }
+}
@@ -4,7 +4,7 @@ use crate::{Context, Result};
 
 use anyhow::bail;
 use chrono::Utc;
-use rand::seq::SliceRandom;
+use rand::{seq::SliceRandom, Rng};
 use serde::Deserialize;
 use tokio::sync::{OnceCell, RwLock};
 
@@ -34,7 +34,8 @@ pub async fn quote(
     ctx: Context<'_>,
     #[description = "Quote ID"] quote_id: Option<u64>,
 ) -> Result<()> {
-    let message = match generate_message(quote_id).await {
+    let mut rng = ctx.data().game_rng("quote");
+    let message = match generate_message(quote_id, &mut rng).await {
         Ok(message) => message,
         Err(e) => e.to_string(),
     };
@@ -44,7 +45,7 @@ pub async fn quote(
     Ok(())
 }
 
-async fn generate_message(quote_id: Option<u64>) -> Result<String> {
+async fn generate_message(quote_id: Option<u64>, rng: &mut impl Rng) -> Result<String> {
     if has_cache_expired() {
         update_quotes().await;
     }
@@ -59,21 +60,22 @@ async fn generate_message(quote_id: Option<u64>) -> Result<String> {
         bail!("Could not retrieve any quotes");
     }
 
-    let quote = choose_quote(&quotes, quote_id)?;
+    let quote = choose_quote(&quotes, quote_id, rng)?;
 
     Ok(format!("[{}] {}", quote.id, quote.body))
 }
 
-fn choose_quote(quotes: &[Quote], quote_id: Option<u64>) -> Result<&Quote> {
+fn choose_quote<'a>(
+    quotes: &'a [Quote],
+    quote_id: Option<u64>,
+    rng: &mut impl Rng,
+) -> Result<&'a Quote> {
     let quote = match quote_id {
         Some(id) => quotes
             .iter()
             .find(|q| q.id == id)
             .ok_or(anyhow::anyhow!("Unable to find quote #{id}"))?,
-        None => {
-            let mut rng = rand::thread_rng();
-            quotes.choose(&mut rng).expect("Quotes to not be empty")
-        }
+        None => quotes.choose(rng).expect("Quotes to not be empty"),
     };
 
     Ok(quote)
@@ -83,7 +85,7 @@ async fn update_quotes() {
     let response = match fetch_quotes().await {
         Ok(response) => response,
         Err(e) => {
-            eprintln!("Failed to fetch quotes: {e:?}");
+            tracing::error!("Failed to fetch quotes: {e:?}");
             return;
         }
     };
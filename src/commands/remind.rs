@@ -0,0 +1,491 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use poise::serenity_prelude::{
+    AutocompleteChoice, ChannelId, Context as SerenityContext, CreateAllowedMentions, CreateEmbed,
+    CreateMessage, Mention, UserId,
+};
+use poise::CreateReply;
+use sqlx::{Connection, SqliteConnection, SqliteExecutor, SqlitePool};
+
+use crate::{
+    common::{bail_reply, ephemeral_reply, escape_like, truncate},
+    metrics::timed_query,
+    scheduler::{Job, Scheduler},
+    Context, Result,
+};
+
+const MAX_PENDING_REMINDERS: i64 = 25;
+const MAX_MESSAGE_LENGTH: usize = 1000;
+const MAX_CHOICE_LENGTH: usize = 100;
+const MAX_DAYS_AHEAD: i64 = 365;
+const WHEN_EXAMPLES: &str = "`2h30m`, `18:00` or `2026-10-20 18:00` (UTC)";
+
+struct NewReminder {
+    user_id: UserId,
+    channel_id: ChannelId,
+    message: String,
+    remind_at: NaiveDateTime,
+    by_dm: bool,
+}
+
+struct PendingReminder {
+    id: i64,
+    channel_id: i64,
+    message: String,
+    remind_at: NaiveDateTime,
+    by_dm: bool,
+}
+
+#[poise::command(
+    slash_command,
+    prefix_command,
+    subcommands("me", "channel", "list", "cancel")
+)]
+pub async fn remind(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Get a DM when the time comes
+#[poise::command(slash_command, prefix_command)]
+async fn me(
+    ctx: Context<'_>,
+    #[description = "When, like 2h30m, 18:00 or 2026-10-20 18:00 (UTC)"] when: String,
+    #[description = "What to remind you of"]
+    #[rest]
+    what: String,
+) -> Result<()> {
+    set_reminder(ctx, &when, what, true).await
+}
+
+/// Get a mention in this channel when the time comes
+#[poise::command(slash_command, prefix_command)]
+async fn channel(
+    ctx: Context<'_>,
+    #[description = "When, like 2h30m, 18:00 or 2026-10-20 18:00 (UTC)"] when: String,
+    #[description = "What to remind you of"]
+    #[rest]
+    what: String,
+) -> Result<()> {
+    set_reminder(ctx, &when, what, false).await
+}
+
+/// List your pending reminders
+#[poise::command(slash_command, prefix_command)]
+async fn list(ctx: Context<'_>) -> Result<()> {
+    let reminders = timed_query(
+        "pending_reminders",
+        pending_reminders(
+            &ctx.data().database,
+            ctx.author().id,
+            "",
+            MAX_PENDING_REMINDERS,
+        ),
+    )
+    .await?;
+
+    if reminders.is_empty() {
+        return bail_reply(ctx, "You don't have any pending reminders.").await;
+    }
+
+    let lines = reminders
+        .iter()
+        .map(|r| {
+            let place = if r.by_dm {
+                "by DM".to_string()
+            } else {
+                format!(
+                    "in {}",
+                    Mention::Channel(ChannelId::new(r.channel_id as u64))
+                )
+            };
+            format!(
+                "`#{}` <t:{}:R> {place}: {}",
+                r.id,
+                r.remind_at.and_utc().timestamp(),
+                truncate(&r.message, MAX_CHOICE_LENGTH)
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let embed = CreateEmbed::default()
+        .title("Your reminders")
+        .colour(0x77618F)
+        .description(lines.join("\n"));
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Cancel one of your pending reminders
+#[poise::command(slash_command, prefix_command)]
+async fn cancel(
+    ctx: Context<'_>,
+    #[description = "The reminder to cancel"]
+    #[autocomplete = "autocomplete_pending_reminders"]
+    reminder: i64,
+) -> Result<()> {
+    let mut conn = ctx.data().database.acquire().await?;
+    let cancelled =
+        cancel_reminder(&mut conn, &ctx.data().scheduler, ctx.author().id, reminder).await?;
+
+    let msg = match cancelled {
+        true => format!("Reminder #{reminder} has been cancelled."),
+        false => format!("You don't have a pending reminder #{reminder}."),
+    };
+    bail_reply(ctx, msg).await
+}
+
+async fn set_reminder(ctx: Context<'_>, when: &str, what: String, by_dm: bool) -> Result<()> {
+    let remind_at = match parse_when(when, Utc::now().naive_utc()) {
+        Ok(remind_at) => remind_at,
+        Err(e) => return bail_reply(ctx, e).await,
+    };
+
+    let message = what.trim().to_string();
+    if message.is_empty() || message.chars().count() > MAX_MESSAGE_LENGTH {
+        let msg = format!("Reminders must be between 1 and {MAX_MESSAGE_LENGTH} characters.");
+        return bail_reply(ctx, msg).await;
+    }
+
+    let database = &ctx.data().database;
+    let author_id = ctx.author().id;
+    let pending = pending_reminders(database, author_id, "", MAX_PENDING_REMINDERS).await?;
+    if pending.len() as i64 >= MAX_PENDING_REMINDERS {
+        let msg = format!(
+            "You already have {MAX_PENDING_REMINDERS} pending reminders, cancel some first."
+        );
+        return bail_reply(ctx, msg).await;
+    }
+
+    let reminder = NewReminder {
+        user_id: author_id,
+        channel_id: ctx.channel_id(),
+        message,
+        remind_at,
+        by_dm,
+    };
+    let scheduler = &ctx.data().scheduler;
+    let mut conn = database.acquire().await?;
+    let id = timed_query(
+        "schedule_reminder",
+        schedule_reminder(&mut conn, scheduler, &reminder),
+    )
+    .await?;
+    scheduler.wake();
+
+    let timestamp = remind_at.and_utc().timestamp();
+    if by_dm {
+        let msg = format!("I'll DM you <t:{timestamp}:R>. (#{id})");
+        ctx.send(ephemeral_reply(msg)).await?;
+    } else {
+        let msg = format!("I'll remind you here <t:{timestamp}:R>. (#{id})");
+        ctx.say(msg).await?;
+    }
+
+    Ok(())
+}
+
+/// Understands durations such as `2h30m` or `1w 2d`, and UTC times such as `18:00` or
+/// `2026-10-20 18:00`. A time without a date is the next time the clock shows it.
+fn parse_when(input: &str, now: NaiveDateTime) -> std::result::Result<NaiveDateTime, String> {
+    let too_far =
+        || format!("That's too far away, reminders are at most {MAX_DAYS_AHEAD} days ahead.");
+    let when = match parse_duration(input) {
+        // The duration can be far larger than the calendar goes
+        Some(duration) => now.checked_add_signed(duration).ok_or_else(too_far)?,
+        None => parse_time(input.trim(), now).ok_or_else(|| {
+            format!("I don't understand `{input}`, try something like {WHEN_EXAMPLES}.")
+        })?,
+    };
+
+    if when <= now {
+        return Err("That's in the past, I can't do anything about it.".to_string());
+    }
+    if when > now + chrono::Duration::days(MAX_DAYS_AHEAD) {
+        return Err(too_far());
+    }
+
+    Ok(when)
+}
+
+fn parse_duration(input: &str) -> Option<chrono::Duration> {
+    let input = input
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>();
+    if input.is_empty() {
+        return None;
+    }
+
+    let mut seconds: i64 = 0;
+    let mut number = String::new();
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c.to_ascii_lowercase() {
+            'w' => 7 * 24 * 60 * 60,
+            'd' => 24 * 60 * 60,
+            'h' => 60 * 60,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        let value: i64 = number.parse().ok()?;
+        seconds = seconds.checked_add(value.checked_mul(unit)?)?;
+        number.clear();
+    }
+
+    // A number without a unit could mean anything
+    if !number.is_empty() {
+        return None;
+    }
+
+    chrono::Duration::try_seconds(seconds)
+}
+
+fn parse_time(input: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    for format in ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(input, format) {
+            return Some(time);
+        }
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0);
+    }
+
+    let time = NaiveTime::parse_from_str(input, "%H:%M").ok()?;
+    let today = now.date().and_time(time);
+    if today > now {
+        Some(today)
+    } else {
+        Some(today + chrono::Duration::days(1))
+    }
+}
+
+async fn schedule_reminder(
+    conn: &mut SqliteConnection,
+    scheduler: &Scheduler,
+    reminder: &NewReminder,
+) -> Result<i64> {
+    let user_id = reminder.user_id.get() as i64;
+    let channel_id = reminder.channel_id.get() as i64;
+
+    let mut transaction = conn.begin().await?;
+    let id = sqlx::query!(
+        "INSERT INTO Reminders (user_id, channel_id, message, remind_at, by_dm) VALUES (?, ?, ?, ?, ?)",
+        user_id,
+        channel_id,
+        reminder.message,
+        reminder.remind_at,
+        reminder.by_dm
+    )
+    .execute(&mut *transaction)
+    .await?
+    .last_insert_rowid();
+
+    let job = Job::Reminder { reminder_id: id };
+    let task_id = scheduler
+        .enqueue(&mut *transaction, &job, reminder.remind_at)
+        .await?;
+    sqlx::query!("UPDATE Reminders SET task_id = ? WHERE id = ?", task_id, id)
+        .execute(&mut *transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(id)
+}
+
+/// Removes a reminder and the task delivering it. Returns whether the user had that reminder.
+async fn cancel_reminder(
+    conn: &mut SqliteConnection,
+    scheduler: &Scheduler,
+    user_id: UserId,
+    id: i64,
+) -> Result<bool> {
+    let user_id = user_id.get() as i64;
+
+    let mut transaction = conn.begin().await?;
+    let task_id = sqlx::query_scalar!(
+        "DELETE FROM Reminders WHERE id = ? AND user_id = ? RETURNING task_id",
+        id,
+        user_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    let Some(task_id) = task_id else {
+        return Ok(false);
+    };
+    if let Some(task_id) = task_id {
+        scheduler.cancel(&mut *transaction, task_id).await?;
+    }
+    transaction.commit().await?;
+
+    Ok(true)
+}
+
+async fn pending_reminders(
+    database: &SqlitePool,
+    user_id: UserId,
+    partial: &str,
+    limit: i64,
+) -> Result<Vec<PendingReminder>> {
+    let user_id = user_id.get() as i64;
+    let partial = format!("%{}%", escape_like(partial));
+
+    let reminders = sqlx::query_as!(
+        PendingReminder,
+        r#"SELECT id, channel_id, message, remind_at as "remind_at: NaiveDateTime", by_dm as "by_dm: bool"
+        FROM Reminders WHERE user_id = ? AND message LIKE ? ESCAPE '\' ORDER BY remind_at LIMIT ?"#,
+        user_id,
+        partial,
+        limit
+    )
+    .fetch_all(database)
+    .await?;
+
+    Ok(reminders)
+}
+
+/// Sends a reminder once it's due, by DM or in the channel it was set in if DMs fail.
+pub async fn deliver_reminder(
+    ctx: &SerenityContext,
+    database: &SqlitePool,
+    reminder_id: i64,
+) -> Result<()> {
+    let reminder = sqlx::query!(
+        r#"SELECT user_id, channel_id, message, by_dm as "by_dm: bool" FROM Reminders WHERE id = ?"#,
+        reminder_id
+    )
+    .fetch_optional(database)
+    .await?;
+
+    // It was cancelled in the meantime
+    let Some(reminder) = reminder else {
+        return Ok(());
+    };
+
+    let user_id = UserId::new(reminder.user_id as u64);
+    let content = format!("⏰ {}", reminder.message);
+    let mut delivered = false;
+    if reminder.by_dm {
+        let message = CreateMessage::new().content(&content);
+        let sent = match user_id.create_dm_channel(ctx).await {
+            Ok(dm) => dm.send_message(ctx, message).await,
+            Err(e) => Err(e),
+        };
+        match sent {
+            Ok(_) => delivered = true,
+            Err(e) => tracing::warn!(
+                reminder = reminder_id,
+                "Could not DM a reminder, posting it in its channel instead: {e}"
+            ),
+        }
+    }
+
+    if !delivered {
+        let message = CreateMessage::new()
+            .content(format!("{} {content}", Mention::User(user_id)))
+            .allowed_mentions(CreateAllowedMentions::new().users([user_id]));
+        ChannelId::new(reminder.channel_id as u64)
+            .send_message(ctx, message)
+            .await?;
+    }
+
+    forget_reminder(database, reminder_id).await
+}
+
+/// Deletes a reminder that was delivered, or couldn't be and was given up.
+pub async fn forget_reminder(executor: impl SqliteExecutor<'_>, reminder_id: i64) -> Result<()> {
+    sqlx::query!("DELETE FROM Reminders WHERE id = ?", reminder_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+async fn autocomplete_pending_reminders<'a>(
+    ctx: Context<'a>,
+    partial: &'a str,
+) -> impl Iterator<Item = AutocompleteChoice> + 'a {
+    let suggestions = pending_reminders(&ctx.data().database, ctx.author().id, partial, 25)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Error while trying to suggest autocomplete for '{partial}': {e}");
+            vec![]
+        });
+
+    suggestions.into_iter().map(|r| {
+        let name = truncate(&format!("#{} {}", r.id, r.message), MAX_CHOICE_LENGTH);
+        AutocompleteChoice::new(name, r.id)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 5, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn durations_and_times_are_understood() {
+        let now = at(2, 18, 0);
+        assert_eq!(parse_when("2h30m", now), Ok(at(2, 20, 30)));
+        assert_eq!(parse_when("1d 2h", now), Ok(at(3, 20, 0)));
+        assert_eq!(parse_when("1w", now), Ok(at(9, 18, 0)));
+        assert_eq!(parse_when("19:15", now), Ok(at(2, 19, 15)));
+        assert_eq!(parse_when("08:00", now), Ok(at(3, 8, 0)));
+        assert_eq!(parse_when("2024-05-10 12:00", now), Ok(at(10, 12, 0)));
+        assert_eq!(parse_when("2024-05-10", now), Ok(at(10, 0, 0)));
+
+        assert!(parse_when("30", now).is_err());
+        assert!(parse_when("soon", now).is_err());
+        assert!(parse_when("0m", now).is_err());
+        assert!(parse_when("2024-05-01 12:00", now).is_err());
+        assert!(parse_when("400d", now).is_err());
+        assert!(parse_when("100000000000000s", now).is_err());
+    }
+
+    #[tokio::test]
+    async fn cancelled_reminders_are_unscheduled() {
+        let database = testing::database().await;
+        let scheduler = Scheduler::new(&database);
+        let mut conn = database.acquire().await.unwrap();
+
+        let reminder = NewReminder {
+            user_id: UserId::new(1),
+            channel_id: ChannelId::new(2),
+            message: "Feed the dinos".to_string(),
+            remind_at: Utc::now().naive_utc() + chrono::Duration::hours(1),
+            by_dm: true,
+        };
+        let id = schedule_reminder(&mut conn, &scheduler, &reminder)
+            .await
+            .unwrap();
+
+        let tasks = sqlx::query_scalar!(r#"SELECT COUNT(*) as "count: i64" FROM ScheduledTasks"#);
+        assert_eq!(tasks.fetch_one(&mut *conn).await.unwrap(), 1);
+
+        let someone_else = UserId::new(3);
+        let cancelled = cancel_reminder(&mut conn, &scheduler, someone_else, id).await;
+        assert!(!cancelled.unwrap());
+
+        let cancelled = cancel_reminder(&mut conn, &scheduler, reminder.user_id, id).await;
+        assert!(cancelled.unwrap());
+
+        let tasks = sqlx::query_scalar!(r#"SELECT COUNT(*) as "count: i64" FROM ScheduledTasks"#);
+        assert_eq!(tasks.fetch_one(&mut *conn).await.unwrap(), 0);
+    }
+}
@@ -12,7 +12,8 @@ pub async fn dice(
     ctx: Context<'_>,
     #[description = "The expression that will be parsed and rolled"] expression: String,
 ) -> Result<()> {
-    let rolled = match rpg_dice_roller::roll(&expression) {
+    let mut rng = ctx.data().game_rng("roll");
+    let rolled = match rpg_dice_roller::roll_with(&expression, &mut rng) {
         Ok(rolled) => rolled,
         Err(msg) => {
             let full_message = format!("```\nFailed to parse expression:\n{msg}\n```");
@@ -28,6 +29,7 @@ pub async fn dice(
         format!("`{expression}` = {}", rolled.value())
     };
 
+    rng.audit(rolled.value());
     ctx.say(message).await?;
 
     Ok(())
@@ -36,7 +38,9 @@ pub async fn dice(
 #[poise::command(slash_command, prefix_command)]
 pub async fn cursed(ctx: Context<'_>) -> Result<()> {
     let dice = Dice::new(999, DiceKind::Standard(444), &[]);
-    let rolled = dice.roll_all();
+    let mut rng = ctx.data().game_rng("roll");
+    let rolled = dice.roll_all_with(&mut rng);
+    rng.audit(rolled.value());
 
     ctx.say(format!("'999d444' = {}", rolled.value())).await?;
 
@@ -1,10 +1,8 @@
 use std::collections::HashMap;
 use std::fmt::Display;
 
-use chrono::NaiveDateTime;
 use poise::serenity_prelude::CreateEmbed;
-use rand::SeedableRng;
-use rand::{rngs::StdRng, seq::SliceRandom};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 use rand_seeder::Seeder;
 use serenity::all::User;
 
@@ -30,7 +28,6 @@ const DEFAULT_STATS: [(Stat, u8); 6] = [
 
 #[derive(Default)]
 pub struct CharacterPastStats {
-    pub last_loss: NaiveDateTime,
     pub elo_rank: i64,
 }
 
@@ -49,16 +46,26 @@ pub struct Character {
     move_choices: Vec<Stat>,
 }
 
+/// Characters of members with a nickname only depend on it, so they're the same in every fight.
+fn character_rng(nickname: Option<&str>, game_rng: &mut impl Rng) -> StdRng {
+    match nickname {
+        Some(s) => Seeder::from(&s).make_rng(),
+        None => StdRng::seed_from_u64(game_rng.gen()),
+    }
+}
+
 impl Character {
-    pub fn new(user: &User, nickname: Option<&str>, record: CharacterPastStats) -> Self {
+    pub fn new(
+        user: &User,
+        nickname: Option<&str>,
+        record: CharacterPastStats,
+        game_rng: &mut impl Rng,
+    ) -> Self {
         let user_id = user.id.get();
         let seed = nickname;
         let name = nickname.unwrap_or(&user.name);
 
-        let mut rng = match seed {
-            Some(s) => Seeder::from(&s).make_rng(),
-            None => StdRng::seed_from_u64(rand::random::<u64>()),
-        };
+        let mut rng = character_rng(seed, game_rng);
         let class = CLASSES
             .choose(&mut rng)
             .expect("Expected the class array to not be empty");
@@ -86,16 +93,21 @@ impl Character {
             }
         }
 
-        let stats = HashMap::from(DEFAULT_STATS);
-        let mut stats: HashMap<Stat, usize> = stats
-            .into_keys()
-            .map(|k| (k, pick_best_x_dice_rolls(6, 3, 3, seed)))
+        // Rolled in a fixed order, the keys of a HashMap come out differently every time
+        let mut stats: HashMap<Stat, usize> = DEFAULT_STATS
+            .into_iter()
+            .map(|(k, _)| {
+                let mut rng = character_rng(seed, game_rng);
+                (k, pick_best_x_dice_rolls(6, 3, 3, &mut rng))
+            })
             .collect();
         for stat in specie.stat_bonuses.iter() {
             *stats.get_mut(stat).expect("Expected to have all the stats") += 1;
         }
 
-        let max_hp = pick_best_x_dice_rolls(HIT_DICE_SIDES, HIT_DICE_POOL, HIT_DICE, seed) as isize;
+        let mut rng = character_rng(seed, game_rng);
+        let max_hp =
+            pick_best_x_dice_rolls(HIT_DICE_SIDES, HIT_DICE_POOL, HIT_DICE, &mut rng) as isize;
 
         Self {
             user_id,
@@ -111,16 +123,15 @@ impl Character {
         }
     }
 
-    pub fn random_move_stat(&self) -> Stat {
-        let mut rng = rand::thread_rng();
+    pub fn random_move_stat(&self, rng: &mut impl Rng) -> Stat {
         *self
             .move_choices
-            .choose(&mut rng)
+            .choose(rng)
             .expect("Expected to have at least 1 move choice")
     }
 
-    pub fn get_modifier(&self, stat: &Stat) -> usize {
-        self.stats[stat] / 2 - 5
+    pub fn get_modifier(&self, stat: &Stat) -> isize {
+        self.stats[stat] as isize / 2 - 5
     }
 
     pub fn to_embed(&self) -> CreateEmbed {
@@ -7,26 +7,38 @@ use sqlx::SqlitePool;
 
 use crate::common::ephemeral_text_message;
 use crate::common::response;
+use crate::metrics::{metrics, timed_query};
 use crate::Data;
 use crate::Result;
 
 pub async fn setup_rpg_summary(ctx: &serenity::Context, user_data: &Data) -> Result<()> {
     let mut collector = ComponentInteractionCollector::new(ctx)
-        .filter(|f| f.data.custom_id == "rpg-summary")
+        .filter(|f| {
+            let matches = f.data.custom_id == "rpg-summary";
+            if matches {
+                metrics().collector_received("rpg-summary");
+            }
+            matches
+        })
         .stream();
 
-    println!("Setup rpg summary collector");
+    tracing::info!("Setup rpg summary collector");
 
     while let Some(interaction) = collector.next().await {
         let mut cache = user_data.rpg_summary_cache.lock().await;
         let message_id = interaction.message.id;
 
-        let btn_response = match cache.get(&message_id.get()).cloned() {
+        let cached = cache.get(&message_id.get()).cloned();
+        metrics().cache_lookup("rpg_summary", cached.is_some());
+
+        let btn_response = match cached {
             Some(log) => log,
             None => {
-                let retrieved =
-                    retrieve_fight_record(&user_data.database, interaction.message.id.to_string())
-                        .await;
+                let retrieved = timed_query(
+                    "rpg_fight_record",
+                    retrieve_fight_record(&user_data.database, interaction.message.id.to_string()),
+                )
+                .await;
 
                 if let Some(log) = retrieved.ok().flatten() {
                     cache.put(message_id.get(), log.clone());
@@ -52,8 +64,13 @@ pub async fn setup_rpg_summary(ctx: &serenity::Context, user_data: &Data) -> Res
             .create_response(ctx, response(response_message))
             .await
         {
-            eprintln!("[RPG COLLECTOR ERROR] {e:?}")
+            tracing::error!(
+                user = interaction.user.id.get(),
+                message = interaction.message.id.get(),
+                "Could not send the rpg summary: {e:?}"
+            )
         }
+        metrics().collector_handled("rpg-summary");
     }
 
     Ok(())
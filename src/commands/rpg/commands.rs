@@ -7,15 +7,22 @@ use crate::common::{
     avatar_url, bail_reply, ephemeral_text_message, name, nickname, reply_with_buttons, response,
     text_message, update_response, Score,
 };
+use crate::cooldowns::Action;
+use crate::i18n;
+use crate::metrics::timed_query;
+use crate::rng::GameRng;
+use crate::shutdown::PendingChallenge;
+use crate::wallet::{self, Reason};
 use crate::Context;
 
-use anyhow::{bail, Context as DiscordContext, Result};
-use chrono::{NaiveDateTime, Utc};
+use anyhow::{Context as DiscordContext, Result};
+use chrono::NaiveDateTime;
 use poise::serenity_prelude::{ButtonStyle, CreateActionRow};
 use poise::serenity_prelude::{
     CreateButton, CreateEmbed, CreateEmbedAuthor, Mention, User, UserId,
 };
 use poise::{CreateReply, ReplyHandle};
+use rand::Rng;
 use serenity::all::{ComponentInteraction, ComponentInteractionCollector, MessageId};
 use sqlx::{Connection, SqliteConnection};
 use std::str::FromStr;
@@ -23,7 +30,6 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
 const DEAD_DUEL_COOLDOWN: Duration = Duration::from_secs(5 * 60);
-const LOSS_COOLDOWN: Duration = Duration::from_secs(30);
 
 static IN_PROGRESS: AtomicBool = AtomicBool::new(false);
 
@@ -49,13 +55,18 @@ async fn challenge(ctx: Context<'_>) -> Result<()> {
         return bail_reply(ctx, "Something went wrong when trying to join the fight.").await;
     };
 
-    if let Err(e) = assert_no_recent_loss(&challenger_stats) {
+    if let Err(e) = assert_no_recent_loss(ctx, challenger).await {
         return bail_reply(ctx, e.to_string()).await;
     };
 
+    let mut rng = ctx.data().game_rng("rpg");
     let challenger_nick = nickname(&ctx, challenger).await;
-    let challenger_character =
-        Character::new(challenger, challenger_nick.as_deref(), challenger_stats);
+    let challenger_character = Character::new(
+        challenger,
+        challenger_nick.as_deref(),
+        challenger_stats,
+        &mut rng,
+    );
 
     let reply_content = format!(
         "{} is throwing down the gauntlet in challenge...",
@@ -69,8 +80,8 @@ async fn challenge(ctx: Context<'_>) -> Result<()> {
         .await?;
 
     IN_PROGRESS.store(true, Ordering::Release);
-    if let Err(e) = run_duel(ctx, challenger_character, reply_handle).await {
-        eprintln!("Failed to run duel to completion: {e:?}");
+    if let Err(e) = run_duel(ctx, challenger_character, reply_handle, rng).await {
+        tracing::error!("Failed to run duel to completion: {e:?}");
     }
     IN_PROGRESS.store(false, Ordering::Release);
 
@@ -81,12 +92,15 @@ async fn run_duel(
     ctx: Context<'_>,
     challenger_character: Character,
     reply_handle: ReplyHandle<'_>,
+    mut rng: GameRng,
 ) -> Result<()> {
     let message = reply_handle.message().await?;
+    let pending = PendingChallenge::track(message.channel_id, message.id);
+    let opponent = find_opponent(ctx, message.id, challenger_character.user_id).await;
+    // The challenge is over, a shutdown must not overwrite the result with a cancellation
+    drop(pending);
 
-    let Some((interaction, accepter_stats)) =
-        find_opponent(ctx, message.id, challenger_character.user_id).await?
-    else {
+    let Some((interaction, accepter_stats)) = opponent? else {
         let content = format!(
             "No one was brave enough to do battle with **{}**",
             challenger_character.name
@@ -99,19 +113,54 @@ async fn run_duel(
 
     let accepter = &interaction.user;
     let accepter_nick = nickname(&ctx, accepter).await;
-    let accepter_character = Character::new(accepter, accepter_nick.as_deref(), accepter_stats);
+    let accepter_character =
+        Character::new(accepter, accepter_nick.as_deref(), accepter_stats, &mut rng);
 
-    let mut fight = RPGFight::new(challenger_character, accepter_character);
+    let locale = i18n::locale(ctx);
+    let mut fight = RPGFight::new(challenger_character, accepter_character, rng, locale);
     let fight_result = fight.fight();
+    let winner_and_loser = match fight_result {
+        FightOutcome::ChallengerWin => Some((fight.challenger.user_id, fight.accepter.user_id)),
+        FightOutcome::AccepterWin => Some((fight.accepter.user_id, fight.challenger.user_id)),
+        FightOutcome::Draw => None,
+    };
 
     let mut conn = ctx.data().database.acquire().await?;
     let mut transaction = conn.begin().await?;
 
-    let (challenger_elo, accepter_elo) =
-        update_character_stats(&mut transaction, &fight, fight_result).await?;
+    if let Some((winner_id, loser_id)) = winner_and_loser {
+        ctx.data()
+            .cooldowns
+            .start(
+                &mut *transaction,
+                ctx.guild_id(),
+                UserId::new(loser_id),
+                Action::RpgLoss,
+            )
+            .await?;
+
+        wallet::pay_out(
+            &mut transaction,
+            UserId::new(winner_id),
+            ctx.data().config.payouts.rpg_win,
+            Reason::RpgWin,
+            &message.id.to_string(),
+        )
+        .await?;
+    }
+
+    let (challenger_elo, accepter_elo) = timed_query(
+        "update_character_stats",
+        update_character_stats(&mut transaction, &fight, fight_result),
+    )
+    .await?;
 
     let fight_log = fight.to_string();
-    new_fight_record(&mut transaction, &message.id.to_string(), &fight_log).await?;
+    timed_query(
+        "new_fight_record",
+        new_fight_record(&mut transaction, &message.id.to_string(), &fight_log),
+    )
+    .await?;
 
     transaction.commit().await?;
     update_summary_cache(ctx, message.id.get(), &fight_log).await;
@@ -159,7 +208,7 @@ async fn find_opponent(
         }
 
         let accepter_stats = retrieve_user_stats(ctx, &interaction.user).await?;
-        if let Err(e) = assert_no_recent_loss(&accepter_stats) {
+        if let Err(e) = assert_no_recent_loss(ctx, &interaction.user).await {
             interaction
                 .create_response(ctx, response(ephemeral_text_message(e.to_string())))
                 .await?;
@@ -172,19 +221,16 @@ async fn find_opponent(
     Ok(None)
 }
 
-fn assert_no_recent_loss(stats: &CharacterPastStats) -> Result<()> {
-    let now = Utc::now().naive_utc();
-    let loss_cooldown_duration = chrono::Duration::from_std(LOSS_COOLDOWN)?;
-
-    if stats.last_loss + loss_cooldown_duration > now {
-        let time_until_duel = (stats.last_loss + loss_cooldown_duration)
-            .and_utc()
-            .timestamp();
-
-        bail!("You have recently lost a duel. Please try again <t:{time_until_duel}:R>.");
-    }
-
-    Ok(())
+async fn assert_no_recent_loss(ctx: Context<'_>, user: &User) -> Result<()> {
+    ctx.data()
+        .cooldowns
+        .ensure_ready(
+            ctx.guild_id(),
+            user.id,
+            Action::RpgLoss,
+            "You have recently lost a duel.",
+        )
+        .await
 }
 
 async fn retrieve_user_stats(ctx: Context<'_>, user: &User) -> Result<CharacterPastStats> {
@@ -230,7 +276,13 @@ async fn preview(
     }
 
     let silent = silent.unwrap_or(true);
-    let character = Character::new(ctx.author(), Some(&name), CharacterPastStats::default());
+    let mut rng = ctx.data().game_rng("rpg_character");
+    let character = Character::new(
+        ctx.author(),
+        Some(&name),
+        CharacterPastStats::default(),
+        &mut rng,
+    );
     ctx.send(
         CreateReply::default()
             .embed(character.to_embed())
@@ -252,7 +304,13 @@ async fn character(
     let user = user.as_ref().unwrap_or_else(|| ctx.author());
 
     let nick = nickname(&ctx, user).await;
-    let character = Character::new(user, nick.as_deref(), CharacterPastStats::default());
+    let mut rng = ctx.data().game_rng("rpg_character");
+    let character = Character::new(
+        user,
+        nick.as_deref(),
+        CharacterPastStats::default(),
+        &mut rng,
+    );
 
     ctx.send(
         CreateReply::default()
@@ -322,29 +380,31 @@ async fn stats(ctx: Context<'_>, user: Option<User>, silent: Option<bool>) -> Re
 #[poise::command(guild_only, slash_command, prefix_command)]
 async fn ladder(ctx: Context<'_>, silent: Option<bool>) -> Result<()> {
     let silent = silent.unwrap_or(true);
+    let mut rng = ctx.data().game_rng("rpg_ladder");
     let mut conn = ctx.data().database.acquire().await?;
     let ladder_state = get_ladder_state(&mut conn).await?;
 
-    let mut fields: Vec<(LadderPosition, String, bool)> = vec![];
+    let locale = i18n::locale(ctx);
+    let mut fields: Vec<(String, String, bool)> = vec![];
     if let Some(user) = ladder_state.top {
         let position = LadderPosition::Top;
-        let result = ladder_result(&user.user_id, user.elo_rank, position);
-        fields.push((position, result, false));
+        let (title, result) = ladder_result(&locale, &user, position, &mut rng);
+        fields.push((title, result, false));
     };
     if let Some(user) = ladder_state.tail {
         let position = LadderPosition::Tail;
-        let result = ladder_result(&user.user_id, user.elo_rank, position);
-        fields.push((position, result, false));
+        let (title, result) = ladder_result(&locale, &user, position, &mut rng);
+        fields.push((title, result, false));
     };
     if let Some(user) = ladder_state.wins {
         let position = LadderPosition::Wins;
-        let result = ladder_result(&user.user_id, user.elo_rank, position);
-        fields.push((position, result, false));
+        let (title, result) = ladder_result(&locale, &user, position, &mut rng);
+        fields.push((title, result, false));
     };
     if let Some(user) = ladder_state.losses {
         let position = LadderPosition::Losses;
-        let result = ladder_result(&user.user_id, user.elo_rank, position);
-        fields.push((position, result, false));
+        let (title, result) = ladder_result(&locale, &user, position, &mut rng);
+        fields.push((title, result, false));
     };
 
     if fields.is_empty() {
@@ -367,17 +427,17 @@ async fn get_character_stats(
 ) -> Result<CharacterPastStats> {
     let user_id = user_id.to_string();
 
-    let row = sqlx::query_as!(
+    let query = sqlx::query_as!(
         CharacterPastStats,
         r#"
         INSERT OR IGNORE INTO RPGCharacter (user_id) VALUES (?);
-        SELECT last_loss, elo_rank FROM RPGCharacter WHERE user_id = ?
+        SELECT elo_rank FROM RPGCharacter WHERE user_id = ?
         "#,
         user_id,
         user_id
     )
-    .fetch_one(conn)
-    .await?;
+    .fetch_one(conn);
+    let row = timed_query("get_character_stats", query).await?;
 
     Ok(row)
 }
@@ -398,13 +458,13 @@ async fn try_get_character_scoresheet(
     conn: &mut SqliteConnection,
     user_id: &str,
 ) -> Result<Option<CharacterScoresheet>> {
-    let row = sqlx::query_as!(
+    let query = sqlx::query_as!(
         CharacterScoresheet,
         r#"SELECT * FROM RPGCharacter WHERE user_id = ?"#,
         user_id
     )
-    .fetch_optional(conn)
-    .await?;
+    .fetch_optional(conn);
+    let row = timed_query("try_get_character_scoresheet", query).await?;
 
     Ok(row)
 }
@@ -445,7 +505,7 @@ async fn update_stats_draw(
 
     let challenger_id = challenger.user_id.to_string();
     let accepter_id = accepter.user_id.to_string();
-    sqlx::query!(
+    let query = sqlx::query!(
         r#"INSERT INTO RPGCharacter (user_id, wins, elo_rank, peak_elo, floor_elo)
         VALUES ($1, 1, $2, $2, $2), ($3, 1, $4, $4, $4)
         ON CONFLICT(user_id) DO UPDATE SET
@@ -458,14 +518,15 @@ async fn update_stats_draw(
         accepter_id,
         accepter_elo
     )
-    .execute(conn)
-    .await
-    .with_context(|| {
-        format!(
-            "Failed to update {} and/or {}'s draws",
-            challenger.name, accepter.name
-        )
-    })?;
+    .execute(conn);
+    timed_query("update_stats_draw", query)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to update {} and/or {}'s draws",
+                challenger.name, accepter.name
+            )
+        })?;
 
     Ok((challenger_elo, accepter_elo))
 }
@@ -480,7 +541,7 @@ async fn update_stats_win_loss(
 
     let victor_id = victor.user_id.to_string();
     let loser_id = loser.user_id.to_string();
-    sqlx::query!(
+    let query = sqlx::query!(
         r#"INSERT INTO RPGCharacter (user_id, wins, elo_rank, peak_elo, floor_elo)
         VALUES ($1, 1, $2, $2, $2)
         ON CONFLICT(user_id) DO UPDATE SET
@@ -499,14 +560,15 @@ async fn update_stats_win_loss(
         loser_id,
         loser_elo
     )
-    .execute(conn)
-    .await
-    .with_context(|| {
-        format!(
-            "Failed to update {} and/or {}'s wins/losses",
-            victor.name, loser.name
-        )
-    })?;
+    .execute(conn);
+    timed_query("update_stats_win_loss", query)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to update {} and/or {}'s wins/losses",
+                victor.name, loser.name
+            )
+        })?;
 
     Ok((victor_elo, loser_elo))
 }
@@ -531,30 +593,30 @@ struct LadderState {
 }
 
 async fn get_ladder_state(conn: &mut SqliteConnection) -> Result<LadderState> {
-    let top = sqlx::query_as!(
+    let query = sqlx::query_as!(
         CharacterScoresheet,
         "SELECT * FROM RPGCharacter WHERE elo_rank = (SELECT MAX(elo_rank) FROM RPGCharacter)"
     )
-    .fetch_optional(&mut *conn)
-    .await?;
-    let tail = sqlx::query_as!(
+    .fetch_optional(&mut *conn);
+    let top = timed_query("get_ladder_top", query).await?;
+    let query = sqlx::query_as!(
         CharacterScoresheet,
         "SELECT * FROM RPGCharacter WHERE elo_rank = (SELECT MIN(elo_rank) FROM RPGCharacter)"
     )
-    .fetch_optional(&mut *conn)
-    .await?;
-    let wins = sqlx::query_as!(
+    .fetch_optional(&mut *conn);
+    let tail = timed_query("get_ladder_tail", query).await?;
+    let query = sqlx::query_as!(
         CharacterScoresheet,
         "SELECT * FROM RPGCharacter WHERE wins = (SELECT MAX(wins) FROM RPGCharacter)"
     )
-    .fetch_optional(&mut *conn)
-    .await?;
-    let losses = sqlx::query_as!(
+    .fetch_optional(&mut *conn);
+    let wins = timed_query("get_ladder_wins", query).await?;
+    let query = sqlx::query_as!(
         CharacterScoresheet,
         "SELECT * FROM RPGCharacter WHERE wins = (SELECT MAX(wins) FROM RPGCharacter)"
     )
-    .fetch_optional(&mut *conn)
-    .await?;
+    .fetch_optional(&mut *conn);
+    let losses = timed_query("get_ladder_losses", query).await?;
 
     Ok(LadderState {
         top,
@@ -564,14 +626,21 @@ async fn get_ladder_state(conn: &mut SqliteConnection) -> Result<LadderState> {
     })
 }
 
-fn ladder_result(user_id: &str, score: i64, position: LadderPosition) -> String {
-    let mention = match UserId::from_str(user_id) {
+/// The title of the position and a line about who holds it.
+fn ladder_result(
+    locale: &str,
+    user: &CharacterScoresheet,
+    position: LadderPosition,
+    rng: &mut impl Rng,
+) -> (String, String) {
+    let mention = match UserId::from_str(&user.user_id) {
         Ok(id) => Mention::from(id).to_string(),
         Err(_) => "Some unknown user".to_string(),
     };
-    format!(
-        "{mention} {random_text} with {score} {suffix}",
-        random_text = position.random_text(),
-        suffix = position.suffix()
+    let args = [("user", mention.into()), ("score", user.elo_rank.into())];
+
+    (
+        i18n::text(locale, position.message(), &[]),
+        i18n::pick(locale, position.message(), &args, rng),
     )
 }
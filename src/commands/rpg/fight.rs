@@ -2,8 +2,9 @@ use super::character::Character;
 use super::data::{Stat, VictoryKind};
 
 use crate::common::pick_best_x_dice_rolls;
+use crate::i18n;
+use crate::rng::GameRng;
 
-use rand::seq::SliceRandom;
 use std::cmp;
 use std::fmt::Display;
 
@@ -21,15 +22,19 @@ pub struct RPGFight {
     pub accepter: Character,
     pub log: String,
     pub summary: String,
+    rng: GameRng,
+    locale: String,
 }
 
 impl RPGFight {
-    pub fn new(challenger: Character, accepter: Character) -> Self {
+    pub fn new(challenger: Character, accepter: Character, rng: GameRng, locale: String) -> Self {
         Self {
             challenger,
             accepter,
             log: String::new(),
             summary: String::new(),
+            rng,
+            locale,
         }
     }
 
@@ -37,11 +42,11 @@ impl RPGFight {
         let mut rounds = 0;
 
         while self.challenger.hp > 0 && self.accepter.hp > 0 && rounds < MAX_ROUNDS {
-            let challenger_initiative = pick_best_x_dice_rolls(20, 1, 1, None)
+            let challenger_initiative = pick_best_x_dice_rolls(20, 1, 1, &mut self.rng) as isize
                 + self.challenger.get_modifier(&Stat::DEX)
                 - self.challenger.get_modifier(&Stat::CHR);
 
-            let accepter_initiative = pick_best_x_dice_rolls(20, 1, 1, None)
+            let accepter_initiative = pick_best_x_dice_rolls(20, 1, 1, &mut self.rng) as isize
                 + self.accepter.get_modifier(&Stat::DEX)
                 - self.accepter.get_modifier(&Stat::CHR);
 
@@ -66,25 +71,27 @@ impl RPGFight {
         } else if self.challenger.hp == 0 {
             (FightOutcome::AccepterWin, &self.accepter, &self.challenger)
         } else {
-            self.summary = format!("After {MAX_ROUNDS} rounds they decide to call it a draw.");
+            let rounds = [("rounds", MAX_ROUNDS.into())];
+            self.summary = i18n::text(&self.locale, "rpg-draw", &rounds);
+            self.rng.audit(&self.summary);
             return FightOutcome::Draw;
         };
 
         self.log += "\n";
-        let result_texts = if victor.hp == victor.max_hp {
-            VictoryKind::Perfect.get_texts()
+        let victory = if victor.hp == victor.max_hp {
+            VictoryKind::Perfect
         } else if victor.hp < 5 {
-            VictoryKind::Close.get_texts()
+            VictoryKind::Close
         } else {
-            VictoryKind::Standard.get_texts()
+            VictoryKind::Standard
         };
 
-        let mut rng = rand::thread_rng();
-        self.summary = result_texts
-            .choose(&mut rng)
-            .expect("Expected to have at least one result text")
-            .replace("VICTOR", &format!("**{}**", victor.name))
-            .replace("LOSER", &format!("**{}**", loser.name));
+        let names = [
+            ("victor", format!("**{}**", victor.name).into()),
+            ("loser", format!("**{}**", loser.name).into()),
+        ];
+        self.summary = i18n::pick(&self.locale, victory.message(), &names, &mut self.rng);
+        self.rng.audit(&self.summary);
 
         result
     }
@@ -96,43 +103,56 @@ impl RPGFight {
             (&mut self.accepter, &mut self.challenger)
         };
 
-        let attack_stat = attacker.random_move_stat();
-        let defence_stat = defender.random_move_stat();
+        let rng = &mut self.rng;
+        let attack_stat = attacker.random_move_stat(rng);
+        let defence_stat = defender.random_move_stat(rng);
 
         let attack_reroll = attack_stat.has_advantage(&defence_stat) as usize;
         let defence_reroll = defence_stat.has_advantage(&attack_stat) as usize;
 
-        let attack_roll = pick_best_x_dice_rolls(20, 1 + attack_reroll, 1, None)
+        let attack_roll = pick_best_x_dice_rolls(20, 1 + attack_reroll, 1, rng) as isize
             + attacker.get_modifier(&attack_stat);
-        let defence_roll = pick_best_x_dice_rolls(20, 1 + defence_reroll, 1, None)
+        let defence_roll = pick_best_x_dice_rolls(20, 1 + defence_reroll, 1, rng) as isize
             + defender.get_modifier(&defence_stat);
 
-        let mut turn_log = String::new();
-
-        turn_log += &attack_stat.get_attack_text();
-
-        let damage = if attack_roll >= defence_roll {
-            turn_log += &format!(" {}", defence_stat.get_defence_failure_text());
+        // The texts are picked before the damage is rolled, as they always were, so the seeds
+        // from before the texts were translated still replay the same fights
+        let is_hit = attack_roll >= defence_roll;
+        let defence_message = match is_hit {
+            true => defence_stat.defence_failure_message(),
+            false => defence_stat.defence_success_message(),
+        };
+        let attack_text = i18n::choose(&self.locale, &attack_stat.attack_message(), rng);
+        let defence_text = i18n::choose(&self.locale, &defence_message, rng);
 
+        let damage = if is_hit {
             let damage_modifier = match attack_stat {
                 Stat::STR | Stat::DEX | Stat::CON => cmp::max(0, attacker.get_modifier(&Stat::STR)),
                 Stat::INT | Stat::CHR | Stat::WIS => cmp::max(0, attacker.get_modifier(&Stat::INT)),
             };
 
-            pick_best_x_dice_rolls(10, 1, 1, None) + damage_modifier
+            pick_best_x_dice_rolls(10, 1, 1, rng) as isize + damage_modifier
         } else {
-            turn_log += &format!(" {}", defence_stat.get_defence_success_text());
             0
         };
 
-        turn_log = turn_log
-            .replace("DEF", &format!("**{}**[{}]", defender.name, defender.hp))
-            .replace("ATK", &format!("**{}**[{}]", attacker.name, attacker.hp))
-            .replace("DMG", &damage.to_string());
+        let args = [
+            (
+                "attacker",
+                format!("**{}**[{}]", attacker.name, attacker.hp).into(),
+            ),
+            (
+                "defender",
+                format!("**{}**[{}]", defender.name, defender.hp).into(),
+            ),
+            ("damage", damage.into()),
+        ];
+        let attack_text = attack_text.format(&args);
+        let defence_text = defence_text.format(&args);
 
-        self.log += &format!("{}\n", turn_log);
+        self.log += &format!("{attack_text} {defence_text}\n");
 
-        defender.hp = cmp::max(0, defender.hp - damage as isize);
+        defender.hp = cmp::max(0, defender.hp - damage);
         defender.hp == 0
     }
 
@@ -162,3 +182,49 @@ impl Display for RPGFight {
         writeln!(f, "{}", self.summary)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::rpg::character::CharacterPastStats;
+    use serenity::all::{User, UserId};
+
+    fn fight(seed: u64) -> (String, String) {
+        let mut rng = GameRng::new("rpg", seed, false);
+        let mut challenger = User::default();
+        challenger.id = UserId::new(1);
+        challenger.name = "challenger".to_string();
+        let mut accepter = User::default();
+        accepter.id = UserId::new(2);
+        accepter.name = "accepter".to_string();
+
+        let challenger = Character::new(
+            &challenger,
+            Some("Sir Challenger"),
+            CharacterPastStats::default(),
+            &mut rng,
+        );
+        let accepter = Character::new(&accepter, None, CharacterPastStats::default(), &mut rng);
+
+        let mut fight = RPGFight::new(challenger, accepter, rng, i18n::FALLBACK.to_string());
+        fight.fight();
+
+        (fight.to_string(), fight.summary)
+    }
+
+    #[test]
+    fn fights_replay_from_their_seed() {
+        assert_eq!(fight(1234), fight(1234));
+        assert_ne!(fight(1234).0, fight(4321).0);
+    }
+
+    #[test]
+    fn fights_replay_seeds_from_before_the_translations() {
+        let (log, summary) = fight(1234);
+        let last_turn =
+            "**accepter**[11] flexes at **Sir Challenger**[1], and **Sir Challenger**[1] \
+            can't push through. 6 damage.";
+        assert!(log.contains(last_turn), "{log}");
+        assert_eq!(summary, "**Sir Challenger** taps out. **accepter** wins!");
+    }
+}
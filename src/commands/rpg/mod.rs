@@ -7,3 +7,5 @@ mod fight;
 
 pub use collector::setup_rpg_summary;
 pub use commands::*;
+#[cfg(feature = "profile")]
+pub use elo::find_ladder_rank;
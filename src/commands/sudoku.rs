@@ -23,10 +23,9 @@ pub async fn sudoku(
         return bail_reply(ctx, "Could not get your member details.").await;
     };
 
-    let random_timeout = {
-        let mut rng = rand::thread_rng();
-        rng.gen_range(420..=690)
-    };
+    let mut rng = ctx.data().game_rng("sudoku");
+    let random_timeout = rng.gen_range(420..=690);
+    rng.audit(format!("{random_timeout} seconds"));
 
     let now = Utc::now();
     let timeout_until = now + Duration::seconds(random_timeout);
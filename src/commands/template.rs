@@ -0,0 +1,185 @@
+//! The placeholders simple commands can use in their content, such as `{user}` or
+//! `{random:1-100}`. Anything between braces that isn't a placeholder is left as it is.
+
+use poise::serenity_prelude::{ChannelId, CreateAllowedMentions, Mention, UserId};
+use rand::Rng;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part<'a> {
+    Text(&'a str),
+    /// Whoever used the command.
+    User,
+    /// The user given to the command, or whoever used it.
+    Target,
+    Channel,
+    /// A number between both bounds, included.
+    Random(i64, i64),
+    /// How many times the command was used, this time included.
+    Count,
+    /// How many times whoever used the command used it, this time included.
+    UserCount,
+    /// The text given to the command.
+    Arg,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template<'a> {
+    parts: Vec<Part<'a>>,
+}
+
+/// What the placeholders are replaced with.
+#[derive(Debug, Clone)]
+pub struct Values {
+    pub user: UserId,
+    pub target: Option<UserId>,
+    pub channel: ChannelId,
+    pub count: i64,
+    pub user_count: i64,
+    pub arg: Option<String>,
+}
+
+impl Values {
+    /// Only `{user}` and `{target}` may ping, a mod can't make a command ping everyone or a role.
+    pub fn allowed_mentions(&self) -> CreateAllowedMentions {
+        CreateAllowedMentions::new().users(std::iter::once(self.user).chain(self.target))
+    }
+}
+
+impl<'a> Template<'a> {
+    pub fn parse(content: &'a str) -> Self {
+        let mut parts = Vec::new();
+        let mut rest = content;
+
+        while let Some(start) = rest.find('{') {
+            let Some(length) = rest[start..].find('}') else {
+                break;
+            };
+            let end = start + length;
+
+            if start > 0 {
+                parts.push(Part::Text(&rest[..start]));
+            }
+            let placeholder = placeholder(&rest[start + 1..end]);
+            parts.push(placeholder.unwrap_or(Part::Text(&rest[start..=end])));
+            rest = &rest[end + 1..];
+        }
+
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest));
+        }
+
+        Self { parts }
+    }
+
+    /// Whether the command needs a user option to fill in `{target}`.
+    pub fn uses_target(&self) -> bool {
+        self.parts.contains(&Part::Target)
+    }
+
+    /// Whether the command needs a text option to fill in `{arg}`.
+    pub fn uses_arg(&self) -> bool {
+        self.parts.contains(&Part::Arg)
+    }
+
+    pub fn render(&self, values: &Values, rng: &mut impl Rng) -> String {
+        let mut text = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(part) => text.push_str(part),
+                Part::User => text.push_str(&Mention::User(values.user).to_string()),
+                Part::Target => {
+                    let target = values.target.unwrap_or(values.user);
+                    text.push_str(&Mention::User(target).to_string())
+                }
+                Part::Channel => text.push_str(&Mention::Channel(values.channel).to_string()),
+                Part::Random(low, high) => text.push_str(&rng.gen_range(*low..=*high).to_string()),
+                Part::Count => text.push_str(&values.count.to_string()),
+                Part::UserCount => text.push_str(&values.user_count.to_string()),
+                Part::Arg => text.push_str(values.arg.as_deref().unwrap_or_default()),
+            }
+        }
+
+        text
+    }
+}
+
+fn placeholder(name: &str) -> Option<Part<'static>> {
+    let part = match name.trim() {
+        "user" => Part::User,
+        "target" => Part::Target,
+        "channel" => Part::Channel,
+        "count" => Part::Count,
+        "usercount" => Part::UserCount,
+        "arg" => Part::Arg,
+        other => {
+            let (low, high) = other.strip_prefix("random:")?.split_once('-')?;
+            let (low, high) = (low.trim().parse().ok()?, high.trim().parse().ok()?);
+            if low > high {
+                return None;
+            }
+            Part::Random(low, high)
+        }
+    };
+
+    Some(part)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::GameRng;
+
+    #[test]
+    fn placeholders_are_filled_in() {
+        let template = Template::parse(
+            "{user} hugs {target} in {channel} {count} times ({usercount} by them): {arg}",
+        );
+        assert!(template.uses_target() && template.uses_arg());
+
+        let mut values = Values {
+            user: UserId::new(1),
+            target: Some(UserId::new(2)),
+            channel: ChannelId::new(3),
+            count: 4,
+            user_count: 2,
+            arg: Some("aww".to_string()),
+        };
+        let mut rng = GameRng::new("dynamic_command", 1, false);
+        assert_eq!(
+            template.render(&values, &mut rng),
+            "<@1> hugs <@2> in <#3> 4 times (2 by them): aww"
+        );
+        assert_eq!(
+            values.allowed_mentions(),
+            CreateAllowedMentions::new().users([UserId::new(1), UserId::new(2)])
+        );
+
+        values.target = None;
+        values.arg = None;
+        assert_eq!(
+            template.render(&values, &mut rng),
+            "<@1> hugs <@1> in <#3> 4 times (2 by them): "
+        );
+    }
+
+    #[test]
+    fn everything_else_is_left_alone() {
+        let content = "{nope} {random:6-1} {random:1-6 {user";
+        let template = Template::parse(content);
+        assert!(!template.uses_target() && !template.uses_arg());
+
+        let values = Values {
+            user: UserId::new(1),
+            target: None,
+            channel: ChannelId::new(3),
+            count: 0,
+            user_count: 0,
+            arg: None,
+        };
+        let mut rng = GameRng::new("dynamic_command", 1, false);
+        assert_eq!(template.render(&values, &mut rng), content);
+
+        let roll = Template::parse("{random:1-6}").render(&values, &mut rng);
+        assert!((1..=6).contains(&roll.parse::<i64>().unwrap()));
+    }
+}
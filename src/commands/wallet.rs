@@ -0,0 +1,143 @@
+use poise::serenity_prelude::{CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, User};
+use poise::CreateReply;
+
+use crate::{
+    audit::{self, AuditAction, Event},
+    common::{avatar_url, bail_reply, colour, name},
+    metrics::timed_query,
+    wallet::{self, Entry, InsufficientFunds, Reason, CURRENCY},
+    Context, Result,
+};
+
+const HISTORY_LENGTH: i64 = 15;
+
+#[poise::command(
+    guild_only,
+    slash_command,
+    prefix_command,
+    subcommands("balance", "history", "give")
+)]
+pub async fn wallet(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// See how many Dino Bucks you or someone else have
+#[poise::command(guild_only, slash_command, prefix_command)]
+async fn balance(
+    ctx: Context<'_>,
+    #[description = "Whose wallet to look into"] user: Option<User>,
+) -> Result<()> {
+    let user = user.as_ref().unwrap_or_else(|| ctx.author());
+    let balance = timed_query(
+        "wallet_balance",
+        wallet::balance(&ctx.data().database, user.id),
+    )
+    .await?;
+
+    ctx.say(format!(
+        "{} has {balance} {CURRENCY}.",
+        name(&ctx, user).await
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// See where your last Dino Bucks came from and went to
+#[poise::command(guild_only, slash_command, prefix_command)]
+async fn history(ctx: Context<'_>) -> Result<()> {
+    let author = ctx.author();
+    let entries = timed_query(
+        "wallet_history",
+        wallet::history(&ctx.data().database, author.id, HISTORY_LENGTH),
+    )
+    .await?;
+
+    let Some(latest) = entries.first() else {
+        return bail_reply(ctx, format!("You have never had any {CURRENCY}.")).await;
+    };
+
+    let lines = entries.iter().map(describe).collect::<Vec<_>>();
+    let colour = colour(&ctx).await.unwrap_or_else(|| 0x77618F.into());
+    let embed = CreateEmbed::default()
+        .colour(colour)
+        .author(
+            CreateEmbedAuthor::new(format!("{}'s wallet", name(&ctx, author).await))
+                .icon_url(avatar_url(author)),
+        )
+        .description(lines.join("\n"))
+        .footer(CreateEmbedFooter::new(format!(
+            "Balance: {} {CURRENCY}",
+            latest.balance
+        )));
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Give some of your Dino Bucks to someone
+#[poise::command(guild_only, slash_command, prefix_command)]
+async fn give(
+    ctx: Context<'_>,
+    #[description = "Who gets the Dino Bucks"] user: User,
+    #[description = "How many Dino Bucks to give"]
+    #[min = 1]
+    amount: u32,
+) -> Result<()> {
+    let author = ctx.author();
+    if user.id == author.id {
+        return bail_reply(ctx, "You can't give money to yourself.").await;
+    }
+    if user.bot {
+        return bail_reply(ctx, "Bots have no use for money.").await;
+    }
+    if amount == 0 {
+        return bail_reply(ctx, "You have to give at least 1 Dino Buck.").await;
+    }
+
+    let mut transaction = ctx.data().database.begin().await?;
+    let reference_id = ctx.id().to_string();
+    let transfer = wallet::transfer(
+        &mut transaction,
+        author.id,
+        user.id,
+        amount.into(),
+        Reason::Gift,
+        Some(&reference_id),
+    );
+    if let Err(e) = timed_query("wallet_transfer", transfer).await {
+        return match e.downcast_ref::<InsufficientFunds>() {
+            Some(e) => bail_reply(ctx, e.to_string()).await,
+            None => Err(e),
+        };
+    }
+    let details = format!("{amount} {CURRENCY}");
+    let event = Event::from_ctx(ctx, AuditAction::MoneyGift, details).target(user.id);
+    audit::record(&mut *transaction, &event).await?;
+    transaction.commit().await?;
+
+    ctx.say(format!(
+        "{} gave {amount} {CURRENCY} to {}.",
+        name(&ctx, author).await,
+        name(&ctx, &user).await
+    ))
+    .await?;
+
+    Ok(())
+}
+
+fn describe(entry: &Entry) -> String {
+    let reason = match (entry.reason, entry.amount < 0) {
+        (Reason::Gift, true) => "Gave a gift".to_string(),
+        (Reason::Gift, false) => "Received a gift".to_string(),
+        (reason, _) => reason.to_string(),
+    };
+
+    format!(
+        "`{:+}` {reason} <t:{}:R>",
+        entry.amount,
+        entry.created_at.and_utc().timestamp()
+    )
+}
@@ -1,13 +1,17 @@
 use crate::Context;
 
+#[cfg(uses_names)]
+use poise::serenity_prelude::User;
+#[cfg(any(feature = "duel", feature = "wallet"))]
+use poise::serenity_prelude::{Colour, Member};
 use poise::serenity_prelude::{
-    Colour, CreateActionRow, CreateEmbed, CreateInteractionResponse,
-    CreateInteractionResponseMessage, Member, User,
+    ComponentInteractionCollector, CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter,
+    CreateInteractionResponse, CreateInteractionResponseMessage,
 };
 use poise::CreateReply;
-use rand::rngs::StdRng;
-use rand::{Rng, SeedableRng};
-use rand_seeder::Seeder;
+#[cfg(any(feature = "dino", feature = "rpg"))]
+use rand::Rng;
+#[cfg(any(feature = "duel", feature = "wallet"))]
 use std::borrow::Cow;
 
 pub fn response(message: CreateInteractionResponseMessage) -> CreateInteractionResponse {
@@ -25,9 +29,11 @@ pub fn ephemeral_text_message(text: impl Into<String>) -> CreateInteractionRespo
         .content(text)
         .ephemeral(true)
 }
+#[cfg(feature = "dino")]
 pub fn embed_message(embed: CreateEmbed) -> CreateInteractionResponseMessage {
     CreateInteractionResponseMessage::new().embed(embed)
 }
+#[cfg(feature = "rockpaperscissors")]
 pub fn message_with_buttons(
     text: impl Into<String>,
     rows: Vec<CreateActionRow>,
@@ -40,6 +46,7 @@ pub fn message_with_buttons(
 pub fn ephemeral_reply(content: impl Into<String>) -> CreateReply {
     CreateReply::default().content(content).ephemeral(true)
 }
+#[cfg(any(feature = "rockpaperscissors", feature = "rpg"))]
 pub fn reply_with_buttons(content: impl Into<String>, rows: Vec<CreateActionRow>) -> CreateReply {
     CreateReply::default().content(content).components(rows)
 }
@@ -49,48 +56,149 @@ pub async fn bail_reply(ctx: Context<'_>, content: impl Into<String>) -> anyhow:
     Ok(())
 }
 
+/// Shows the first page with buttons to flip through the others, only to whoever called the
+/// command. The buttons go away after `timeout` without a click.
+pub async fn paginate(
+    ctx: Context<'_>,
+    pages: Vec<CreateEmbed>,
+    timeout: std::time::Duration,
+) -> anyhow::Result<()> {
+    let Some(first) = pages.first() else {
+        return Ok(());
+    };
+
+    let ctx_id = ctx.id();
+    let prev_button_id = format!("{ctx_id}-prev");
+    let next_button_id = format!("{ctx_id}-next");
+    let buttons = |page: usize| {
+        let footer = format!("Page {}/{}", page + 1, pages.len());
+        let buttons = vec![
+            CreateButton::new(&prev_button_id).emoji('◀'),
+            CreateButton::new(&next_button_id).emoji('▶'),
+        ];
+        (footer, vec![CreateActionRow::Buttons(buttons)])
+    };
+
+    let (footer, rows) = buttons(0);
+    let mut reply = CreateReply::default()
+        .embed(first.clone().footer(CreateEmbedFooter::new(footer)))
+        .ephemeral(true);
+    if pages.len() > 1 {
+        reply = reply.components(rows);
+    }
+    let handle = ctx.send(reply).await?;
+    if pages.len() == 1 {
+        return Ok(());
+    }
+
+    let mut page = 0;
+    while let Some(press) = ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .custom_ids(vec![prev_button_id.clone(), next_button_id.clone()])
+        .timeout(timeout)
+        .await
+    {
+        page = if press.data.custom_id == next_button_id {
+            (page + 1) % pages.len()
+        } else {
+            page.checked_sub(1).unwrap_or(pages.len() - 1)
+        };
+
+        let (footer, rows) = buttons(page);
+        let message = CreateInteractionResponseMessage::new()
+            .embed(pages[page].clone().footer(CreateEmbedFooter::new(footer)))
+            .components(rows);
+        press.create_response(ctx, update_response(message)).await?;
+    }
+
+    let (footer, _) = buttons(page);
+    let last = CreateReply::default()
+        .embed(pages[page].clone().footer(CreateEmbedFooter::new(footer)))
+        .components(Vec::new());
+    handle.edit(ctx, last).await?;
+
+    Ok(())
+}
+
+/// Cuts `text` down to `max_length` characters, ending with "…" when something was cut.
+pub fn truncate(text: &str, max_length: usize) -> String {
+    if text.chars().count() <= max_length {
+        return text.to_string();
+    }
+
+    let kept = text
+        .chars()
+        .take(max_length.saturating_sub(1))
+        .collect::<String>();
+    format!("{kept}…")
+}
+
+/// Reads a color written like `#77618F`.
+pub fn to_color(hexcode: &str) -> Option<u32> {
+    let hexcode = hexcode.strip_prefix('#').unwrap_or(hexcode);
+
+    match u32::from_str_radix(hexcode, 16) {
+        Ok(code) if hexcode.len() == 6 && code > 0 => Some(code),
+        _ => None,
+    }
+}
+
+/// Makes `text` match itself in a `LIKE ... ESCAPE '\'` pattern, `%` and `_` included.
+pub fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(uses_names)]
 pub async fn nickname(ctx: &Context<'_>, person: &User) -> Option<String> {
     let guild_id = ctx.guild_id()?;
     person.nick_in(ctx, guild_id).await
 }
 
+#[cfg(uses_names)]
 pub async fn name(ctx: &Context<'_>, person: &User) -> String {
     nickname(ctx, person)
         .await
         .unwrap_or_else(|| person.name.clone())
 }
 
+#[cfg(any(feature = "duel", feature = "wallet"))]
 pub async fn member<'a>(ctx: &'a Context<'_>) -> Option<Cow<'a, Member>> {
     ctx.author_member().await
 }
 
+#[cfg(any(feature = "duel", feature = "wallet"))]
 pub async fn colour(ctx: &Context<'_>) -> Option<Colour> {
     member(ctx).await?.colour(ctx)
 }
 
+#[cfg(any(
+    feature = "dino",
+    feature = "duel",
+    feature = "rpg",
+    feature = "wallet"
+))]
 pub fn avatar_url(person: &User) -> String {
     person
         .avatar_url()
         .unwrap_or_else(|| person.default_avatar_url())
 }
 
+#[cfg(any(feature = "rockpaperscissors", feature = "rpg"))]
 pub enum Score {
     Win,
     Loss,
     Draw,
 }
 
+#[cfg(any(feature = "dino", feature = "rpg"))]
 pub fn pick_best_x_dice_rolls(
     die_sides: usize,
     total_rolls: usize,
     x: usize,
-    seed: Option<&str>,
+    rng: &mut impl Rng,
 ) -> usize {
-    let mut rng = match seed {
-        Some(s) => Seeder::from(&s).make_rng(),
-        None => StdRng::seed_from_u64(rand::random::<u64>()),
-    };
-
     let mut rolls = (0..total_rolls)
         .map(|_| rng.gen_range(1..=die_sides))
         .collect::<Vec<usize>>();
@@ -0,0 +1,318 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context as AnyhowContext};
+use serde::Deserialize;
+
+use crate::Result;
+
+const DEFAULT_CONFIG_PATH: &str = "twiggy.toml";
+
+/// Everything the bot needs to know before connecting to Discord.
+///
+/// Values are read from `twiggy.toml` (or the file in `TWIGGY_CONFIG`), then
+/// overridden by environment variables where one exists.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub token: Option<String>,
+    pub wolfram_app_id: Option<String>,
+    pub itad_client_id: Option<String>,
+    pub database: PathBuf,
+    pub prefix: String,
+    /// Only these commands get registered, every command is enabled when this is not set.
+    pub enabled_commands: Option<Vec<String>>,
+    pub assets: Assets,
+    pub cooldowns: Cooldowns,
+    pub logging: Logging,
+    pub metrics: Metrics,
+    pub rng: Rng,
+    pub payouts: Payouts,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Assets {
+    pub dino_fragments: PathBuf,
+    pub dino_output: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Logging {
+    /// A `tracing` filter such as `info` or `twiggy_bot=debug,serenity=warn`, `RUST_LOG` takes precedence.
+    pub level: String,
+    pub format: LogFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Metrics {
+    /// Metrics are served on `127.0.0.1:<port>` when set.
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rng {
+    /// Log the seed behind every game result.
+    pub audit: bool,
+    /// Seed every game with this instead of a random seed, to replay a result from the audit log.
+    pub seed: Option<u64>,
+}
+
+/// Dino Bucks paid out for game results, 0 turns a payout off.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Payouts {
+    pub duel_win: u32,
+    pub rpg_win: u32,
+    pub dino_hatch: u32,
+    pub best_mixu: u32,
+}
+
+/// Default cooldowns, in seconds.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Cooldowns {
+    pub ask: u64,
+    pub itad: u64,
+    pub color_change: u64,
+    pub duel_loss: u64,
+    pub rpg_loss: u64,
+    pub dino_slurp: u64,
+    pub dino_gift: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            token: None,
+            wolfram_app_id: None,
+            itad_client_id: None,
+            database: PathBuf::from("database.sqlite"),
+            prefix: String::from(">"),
+            enabled_commands: None,
+            assets: Assets::default(),
+            cooldowns: Cooldowns::default(),
+            logging: Logging::default(),
+            metrics: Metrics::default(),
+            rng: Rng::default(),
+            payouts: Payouts::default(),
+        }
+    }
+}
+
+impl Default for Assets {
+    fn default() -> Self {
+        Self {
+            dino_fragments: PathBuf::from("./assets/dino/fragments"),
+            dino_output: PathBuf::from("./assets/dino/complete"),
+        }
+    }
+}
+
+impl Default for Logging {
+    fn default() -> Self {
+        Self {
+            level: String::from("info,serenity=warn,sqlx=warn"),
+            format: LogFormat::Pretty,
+        }
+    }
+}
+
+impl Default for Payouts {
+    fn default() -> Self {
+        Self {
+            duel_win: 10,
+            rpg_win: 10,
+            dino_hatch: 5,
+            best_mixu: 50,
+        }
+    }
+}
+
+// Before the `Cooldowns` table these were hard-coded, the legacy-cooldowns migration still uses
+// the old values for the cooldowns it carries over
+impl Default for Cooldowns {
+    fn default() -> Self {
+        Self {
+            ask: 10,
+            itad: 30,
+            color_change: 60 * 60,
+            duel_loss: 60 * 60,
+            rpg_loss: 30,
+            dino_slurp: 60 * 60,
+            dino_gift: 60 * 60,
+        }
+    }
+}
+
+impl Cooldowns {
+    pub fn ask(&self) -> Duration {
+        Duration::from_secs(self.ask)
+    }
+
+    pub fn itad(&self) -> Duration {
+        Duration::from_secs(self.itad)
+    }
+
+    pub fn color_change(&self) -> Duration {
+        Duration::from_secs(self.color_change)
+    }
+
+    pub fn duel_loss(&self) -> Duration {
+        Duration::from_secs(self.duel_loss)
+    }
+
+    pub fn rpg_loss(&self) -> Duration {
+        Duration::from_secs(self.rpg_loss)
+    }
+
+    pub fn dino_slurp(&self) -> Duration {
+        Duration::from_secs(self.dino_slurp)
+    }
+
+    pub fn dino_gift(&self) -> Duration {
+        Duration::from_secs(self.dino_gift)
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Self> {
+        let (path, explicit) = match std::env::var("TWIGGY_CONFIG") {
+            Ok(path) => (PathBuf::from(path), true),
+            Err(_) => (PathBuf::from(DEFAULT_CONFIG_PATH), false),
+        };
+
+        let mut config = match std::fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content)
+                .with_context(|| format!("Invalid config file {}", path.display()))?,
+            // The config file is optional unless it was asked for explicitly
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !explicit => Self::default(),
+            Err(e) => return Err(e).with_context(|| format!("Could not read {}", path.display())),
+        };
+
+        config.apply_env_overrides()?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn parse(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    fn apply_env_overrides(&mut self) -> Result<()> {
+        let env = |key: &str| std::env::var(key).ok().filter(|v| !v.is_empty());
+
+        if let Some(token) = env("DISCORD_TOKEN") {
+            self.token = Some(token);
+        }
+        if let Some(app_id) = env("WOLFRAM_APP_ID") {
+            self.wolfram_app_id = Some(app_id);
+        }
+        if let Some(client_id) = env("ITAD_CLIENT_ID") {
+            self.itad_client_id = Some(client_id);
+        }
+        if let Some(database) = env("TWIGGY_DATABASE") {
+            self.database = PathBuf::from(database);
+        }
+        if let Some(prefix) = env("TWIGGY_PREFIX") {
+            self.prefix = prefix;
+        }
+        if let Some(format) = env("TWIGGY_LOG_FORMAT") {
+            match format.as_str() {
+                "json" => self.logging.format = LogFormat::Json,
+                "pretty" => self.logging.format = LogFormat::Pretty,
+                _ => bail!("TWIGGY_LOG_FORMAT must be `pretty` or `json`, got {format:?}"),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        ensure!(
+            !self.prefix.is_empty() && !self.prefix.contains(char::is_whitespace),
+            "The prefix must be a single word, got {:?}",
+            self.prefix
+        );
+
+        if let Some(parent) = self.database.parent() {
+            ensure!(
+                parent == Path::new("") || parent.is_dir(),
+                "The directory for the database {} does not exist",
+                self.database.display()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The token is only needed to connect, so it is checked here instead of in `validate`.
+    pub fn token(&self) -> Result<&str> {
+        match &self.token {
+            Some(token) if !token.trim().is_empty() => Ok(token),
+            _ => bail!("No Discord token was provided, set `token` in the config or DISCORD_TOKEN"),
+        }
+    }
+
+    pub fn is_command_enabled(&self, name: &str) -> bool {
+        self.enabled_commands
+            .as_ref()
+            .map_or(true, |enabled| enabled.iter().any(|c| c == name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_config_uses_defaults() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config.prefix, ">");
+        assert_eq!(config.database, PathBuf::from("database.sqlite"));
+        assert_eq!(config.cooldowns.itad(), Duration::from_secs(30));
+        assert!(config.is_command_enabled("duel"));
+    }
+
+    #[test]
+    fn partial_sections_keep_other_defaults() {
+        let config = Config::parse(
+            r#"
+            enabled_commands = ["duel", "rpg"]
+
+            [cooldowns]
+            ask = 5
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.cooldowns.ask(), Duration::from_secs(5));
+        assert_eq!(config.cooldowns.rpg_loss(), Duration::from_secs(30));
+        assert!(config.is_command_enabled("rpg"));
+        assert!(!config.is_command_enabled("sudoku"));
+    }
+
+    #[test]
+    fn logging_format_is_parsed() {
+        let config = Config::parse("[logging]\nformat = \"json\"").unwrap();
+        assert_eq!(config.logging.format, LogFormat::Json);
+        assert_eq!(config.logging.level, "info,serenity=warn,sqlx=warn");
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let error = Config::parse("[cooldowns]\nduel = 5").unwrap_err();
+        assert!(error.to_string().contains("unknown field `duel`"));
+    }
+}
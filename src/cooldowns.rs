@@ -0,0 +1,499 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::time::Duration;
+
+use anyhow::bail;
+#[cfg(any(uses_cooldowns, test))]
+use chrono::NaiveDateTime;
+use chrono::Utc;
+use poise::serenity_prelude::{GuildId, UserId};
+use poise::ChoiceParameter;
+#[cfg(any(uses_cooldowns, test))]
+use sqlx::SqliteExecutor;
+use sqlx::SqlitePool;
+use tokio::sync::RwLock;
+
+use crate::audit::{self, Event};
+#[cfg(any(uses_cooldowns, test))]
+use crate::metrics::timed_query;
+use crate::{config, Result};
+
+/// Everything that can be put on cooldown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, poise::ChoiceParameter)]
+pub enum Action {
+    #[name = "ask"]
+    Ask,
+    #[name = "itad"]
+    Itad,
+    #[name = "color_change"]
+    ColorChange,
+    #[name = "duel_loss"]
+    DuelLoss,
+    #[name = "rpg_loss"]
+    RpgLoss,
+    #[name = "dino_hatch"]
+    DinoHatch,
+    #[name = "dino_slurp"]
+    DinoSlurp,
+    #[name = "dino_gift"]
+    DinoGift,
+}
+
+impl Action {
+    /// Shared cooldowns apply to everyone in every server at once, they protect the quota of an
+    /// external API.
+    pub fn is_shared(self) -> bool {
+        matches!(self, Action::Ask | Action::Itad)
+    }
+
+    /// Hatching resets every day at midnight UTC instead of after a duration.
+    fn resets_daily(self) -> bool {
+        matches!(self, Action::DinoHatch)
+    }
+
+    fn default_duration(self, defaults: &config::Cooldowns) -> Duration {
+        match self {
+            Action::Ask => defaults.ask(),
+            Action::Itad => defaults.itad(),
+            Action::ColorChange => defaults.color_change(),
+            Action::DuelLoss => defaults.duel_loss(),
+            Action::RpgLoss => defaults.rpg_loss(),
+            Action::DinoHatch => Duration::from_secs(24 * 60 * 60),
+            Action::DinoSlurp => defaults.dino_slurp(),
+            Action::DinoGift => defaults.dino_gift(),
+        }
+    }
+
+    #[cfg(any(uses_cooldowns, test))]
+    fn expires_at(self, now: NaiveDateTime, duration: Duration) -> NaiveDateTime {
+        if self.resets_daily() {
+            return now.date().and_hms_opt(0, 0, 0).unwrap() + chrono::Duration::days(1);
+        }
+
+        now + chrono::Duration::from_std(duration).unwrap_or_default()
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The common "try again" message, `reason` should be a full sentence.
+#[cfg(uses_cooldowns)]
+pub fn try_again(reason: impl Display, expires_at: NaiveDateTime) -> String {
+    format!(
+        "{reason} Try again <t:{}:R>.",
+        expires_at.and_utc().timestamp()
+    )
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match (seconds / 3600, seconds % 3600 / 60, seconds % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, 0) => format!("{m}m"),
+        (0, m, s) => format!("{m}m{s}s"),
+        (h, 0, 0) => format!("{h}h"),
+        (h, m, _) => format!("{h}h{m}m"),
+    }
+}
+
+fn guild_key(guild_id: Option<GuildId>) -> i64 {
+    guild_id.map_or(0, |id| id.get() as i64)
+}
+
+/// The guild and user a cooldown is stored under, shared ones are under 0 for both.
+fn cooldown_key(action: Action, guild_id: Option<GuildId>, user_id: UserId) -> (i64, i64) {
+    if action.is_shared() {
+        (0, 0)
+    } else {
+        (guild_key(guild_id), user_id.get() as i64)
+    }
+}
+
+struct DurationRow {
+    guild_id: i64,
+    action: String,
+    seconds: i64,
+}
+
+/// Cooldowns for every command, keyed by guild, user and action.
+///
+/// Durations default to the `[cooldowns]` section of the config and can be overridden per guild.
+/// Cooldowns used in DMs and the shared ones are stored under guild 0.
+pub struct Cooldowns {
+    database: SqlitePool,
+    defaults: HashMap<Action, Duration>,
+    durations: RwLock<HashMap<i64, HashMap<Action, Duration>>>,
+}
+
+impl Cooldowns {
+    pub async fn new(database: &SqlitePool, defaults: &config::Cooldowns) -> Result<Self> {
+        let rows = sqlx::query_as!(
+            DurationRow,
+            "SELECT guild_id, action, seconds FROM CooldownDurations"
+        )
+        .fetch_all(database)
+        .await?;
+
+        let mut durations: HashMap<i64, HashMap<Action, Duration>> = HashMap::new();
+        for row in rows {
+            let Some(action) = Action::from_name(&row.action) else {
+                tracing::warn!(
+                    guild = row.guild_id,
+                    "Ignoring unknown cooldown '{}'",
+                    row.action
+                );
+                continue;
+            };
+
+            durations
+                .entry(row.guild_id)
+                .or_default()
+                .insert(action, Duration::from_secs(row.seconds.max(0) as u64));
+        }
+
+        let defaults = Action::list()
+            .into_iter()
+            .filter_map(|choice| Action::from_name(&choice.name))
+            .map(|action| (action, action.default_duration(defaults)))
+            .collect();
+
+        Ok(Self {
+            database: database.clone(),
+            defaults,
+            durations: RwLock::new(durations),
+        })
+    }
+
+    /// How long the action lasts in the guild, shared cooldowns always last the default.
+    pub async fn duration(&self, guild_id: Option<GuildId>, action: Action) -> Duration {
+        if action.is_shared() {
+            return self.defaults.get(&action).copied().unwrap_or_default();
+        }
+
+        let durations = self.durations.read().await;
+        durations
+            .get(&guild_key(guild_id))
+            .and_then(|d| d.get(&action))
+            .or_else(|| self.defaults.get(&action))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Overrides the duration for a guild, `None` goes back to the default.
+    pub async fn set_duration(
+        &self,
+        guild_id: GuildId,
+        action: Action,
+        duration: Option<Duration>,
+        event: Option<&Event>,
+    ) -> Result<()> {
+        if action.resets_daily() {
+            bail!("The {action} cooldown resets every day at midnight UTC and can't be changed.");
+        }
+        if action.is_shared() {
+            bail!("The {action} cooldown is shared by every server and can't be changed.");
+        }
+
+        let guild_id = guild_key(Some(guild_id));
+        let name = action.name();
+
+        let mut durations = self.durations.write().await;
+        let mut transaction = self.database.begin().await?;
+        match duration {
+            Some(duration) => {
+                let Ok(seconds) = i64::try_from(duration.as_secs()) else {
+                    bail!("That is too long for a cooldown.");
+                };
+                sqlx::query!(
+                    r#"INSERT INTO CooldownDurations (guild_id, action, seconds) VALUES (?, ?, ?)
+                    ON CONFLICT(guild_id, action) DO UPDATE SET seconds = excluded.seconds"#,
+                    guild_id,
+                    name,
+                    seconds
+                )
+                .execute(&mut *transaction)
+                .await?;
+            }
+            None => {
+                sqlx::query!(
+                    "DELETE FROM CooldownDurations WHERE guild_id = ? AND action = ?",
+                    guild_id,
+                    name
+                )
+                .execute(&mut *transaction)
+                .await?;
+            }
+        }
+        if let Some(event) = event {
+            audit::record(&mut *transaction, event).await?;
+        }
+        transaction.commit().await?;
+
+        match duration {
+            Some(duration) => {
+                durations
+                    .entry(guild_id)
+                    .or_default()
+                    .insert(action, duration);
+            }
+            None => {
+                if let Some(guild_durations) = durations.get_mut(&guild_id) {
+                    guild_durations.remove(&action);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// When the cooldown ends, `None` if the action can be used right now.
+    #[cfg(any(uses_cooldowns, test))]
+    pub async fn expiry(
+        &self,
+        guild_id: Option<GuildId>,
+        user_id: UserId,
+        action: Action,
+    ) -> Result<Option<NaiveDateTime>> {
+        let (guild_id, user_id) = cooldown_key(action, guild_id, user_id);
+        let name = action.name();
+
+        let query = sqlx::query_scalar!(
+            r#"SELECT expires_at as "expires_at: NaiveDateTime" FROM Cooldowns
+            WHERE guild_id = ? AND user_id = ? AND action = ?"#,
+            guild_id,
+            user_id,
+            name
+        )
+        .fetch_optional(&self.database);
+        let expires_at = timed_query("cooldown_expiry", query).await?;
+
+        Ok(expires_at.filter(|expires_at| *expires_at > Utc::now().naive_utc()))
+    }
+
+    /// Fails with a "try again" message if the action is on cooldown.
+    #[cfg(uses_cooldowns)]
+    pub async fn ensure_ready(
+        &self,
+        guild_id: Option<GuildId>,
+        user_id: UserId,
+        action: Action,
+        reason: impl Display,
+    ) -> Result<()> {
+        if let Some(expires_at) = self.expiry(guild_id, user_id, action).await? {
+            bail!(try_again(reason, expires_at));
+        }
+
+        Ok(())
+    }
+
+    /// Puts the action on cooldown, pass a transaction to start it only if everything else succeeds.
+    #[cfg(any(uses_cooldowns, test))]
+    pub async fn start(
+        &self,
+        executor: impl SqliteExecutor<'_>,
+        guild_id: Option<GuildId>,
+        user_id: UserId,
+        action: Action,
+    ) -> Result<NaiveDateTime> {
+        let duration = self.duration(guild_id, action).await;
+        let expires_at = action.expires_at(Utc::now().naive_utc(), duration);
+
+        let (guild_id, user_id) = cooldown_key(action, guild_id, user_id);
+        let name = action.name();
+
+        let query = sqlx::query!(
+            r#"INSERT INTO Cooldowns (guild_id, user_id, action, expires_at) VALUES (?, ?, ?, ?)
+            ON CONFLICT(guild_id, user_id, action) DO UPDATE SET expires_at = excluded.expires_at"#,
+            guild_id,
+            user_id,
+            name,
+            expires_at
+        )
+        .execute(executor);
+        timed_query("cooldown_start", query).await?;
+
+        Ok(expires_at)
+    }
+
+    /// Removes a user's cooldowns in the guild, or only the one for `action`. Shared cooldowns
+    /// can't be reset. Returns how many were removed.
+    pub async fn reset(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        action: Option<Action>,
+        event: Option<&Event>,
+    ) -> Result<u64> {
+        if let Some(action) = action.filter(|action| action.is_shared()) {
+            bail!("The {action} cooldown is shared by every server and can't be reset.");
+        }
+
+        let mut transaction = self.database.begin().await?;
+        let result = match action {
+            Some(action) => {
+                let (guild_id, user_id) = cooldown_key(action, Some(guild_id), user_id);
+                let name = action.name();
+                sqlx::query!(
+                    "DELETE FROM Cooldowns WHERE guild_id = ? AND user_id = ? AND action = ?",
+                    guild_id,
+                    user_id,
+                    name
+                )
+                .execute(&mut *transaction)
+                .await?
+            }
+            None => {
+                let guild_id = guild_key(Some(guild_id));
+                let user_id = user_id.get() as i64;
+                sqlx::query!(
+                    "DELETE FROM Cooldowns WHERE guild_id = ? AND user_id = ?",
+                    guild_id,
+                    user_id
+                )
+                .execute(&mut *transaction)
+                .await?
+            }
+        };
+        // Nothing changed when they weren't on cooldown
+        if result.rows_affected() > 0 {
+            if let Some(event) = event {
+                audit::record(&mut *transaction, event).await?;
+            }
+            transaction.commit().await?;
+        }
+
+        Ok(result.rows_affected())
+    }
+
+    /// Deletes every cooldown that is over. Returns how many were removed.
+    pub async fn prune(&self) -> Result<u64> {
+        let now = Utc::now().naive_utc();
+        let result = sqlx::query!("DELETE FROM Cooldowns WHERE expires_at <= ?", now)
+            .execute(&self.database)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditAction;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn hatching_resets_at_midnight() {
+        let now = NaiveDate::from_ymd_opt(2024, 5, 2)
+            .unwrap()
+            .and_hms_opt(18, 30, 0)
+            .unwrap();
+        let expected = NaiveDate::from_ymd_opt(2024, 5, 3)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+
+        assert_eq!(
+            Action::DinoHatch.expires_at(now, Duration::from_secs(60)),
+            expected
+        );
+        assert_eq!(
+            Action::DinoSlurp.expires_at(now, Duration::from_secs(60)),
+            now + chrono::Duration::minutes(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn shared_cooldowns_apply_in_every_guild() {
+        let database = crate::testing::database().await;
+        let cooldowns = crate::testing::cooldowns(&database).await;
+        let (first, second) = (Some(GuildId::new(1)), Some(GuildId::new(2)));
+        let (user, other) = (UserId::new(3), UserId::new(4));
+
+        cooldowns
+            .start(&database, first, user, Action::Ask)
+            .await
+            .unwrap();
+        cooldowns
+            .start(&database, first, user, Action::DuelLoss)
+            .await
+            .unwrap();
+
+        let expiry = |guild_id, user_id, action| cooldowns.expiry(guild_id, user_id, action);
+        assert!(expiry(second, other, Action::Ask).await.unwrap().is_some());
+        assert!(expiry(None, other, Action::Ask).await.unwrap().is_some());
+        assert!(expiry(first, user, Action::DuelLoss)
+            .await
+            .unwrap()
+            .is_some());
+        assert!(expiry(second, user, Action::DuelLoss)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(expiry(first, other, Action::DuelLoss)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn guild_overrides_stay_in_their_guild() {
+        let database = crate::testing::database().await;
+        let cooldowns = crate::testing::cooldowns(&database).await;
+        let (first, second) = (GuildId::new(1), GuildId::new(2));
+        let user = UserId::new(3);
+        let defaults = config::Cooldowns::default();
+
+        let hour = Duration::from_secs(60 * 60);
+        let event = Event::new(Some(first), user, AuditAction::CooldownChange, "duel_loss");
+        cooldowns
+            .set_duration(first, Action::DuelLoss, Some(hour), Some(&event))
+            .await
+            .unwrap();
+        assert!(cooldowns
+            .set_duration(first, Action::Ask, Some(hour), Some(&event))
+            .await
+            .is_err());
+        assert!(cooldowns
+            .reset(first, user, Some(Action::Ask), None)
+            .await
+            .is_err());
+
+        let audited = sqlx::query_scalar!(r#"SELECT COUNT(*) as "count: i64" FROM AuditLog"#);
+        assert_eq!(audited.fetch_one(&database).await.unwrap(), 1);
+
+        assert_eq!(
+            cooldowns.duration(Some(first), Action::DuelLoss).await,
+            hour
+        );
+        assert_eq!(
+            cooldowns.duration(Some(second), Action::DuelLoss).await,
+            defaults.duel_loss()
+        );
+        assert_eq!(
+            cooldowns.duration(Some(first), Action::Ask).await,
+            defaults.ask()
+        );
+
+        let before = Utc::now().naive_utc();
+        let expires_at = cooldowns
+            .start(&database, Some(second), user, Action::DuelLoss)
+            .await
+            .unwrap();
+        let default_loss = chrono::Duration::from_std(defaults.duel_loss()).unwrap();
+        assert!(expires_at >= before + default_loss);
+        assert!(expires_at < before + default_loss + chrono::Duration::seconds(5));
+    }
+
+    #[test]
+    fn durations_are_formatted_compactly() {
+        assert_eq!(format_duration(Duration::from_secs(30)), "30s");
+        assert_eq!(format_duration(Duration::from_secs(90)), "1m30s");
+        assert_eq!(format_duration(Duration::from_secs(3600)), "1h");
+        assert_eq!(format_duration(Duration::from_secs(5400)), "1h30m");
+    }
+}
@@ -0,0 +1,248 @@
+//! What the bot says, looked up by message ID in the Fluent bundles under `locales/`.
+//!
+//! Messages missing from a locale come from English, so a translation can be partial. Lists
+//! the bot picks from at random, like the eightball replies, are the attributes of a message.
+
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Context as AnyhowContext};
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentError, FluentResource, FluentValue};
+#[cfg(any(feature = "eightball", feature = "rpg", test))]
+use rand::{seq::SliceRandom, Rng};
+use unic_langid::LanguageIdentifier;
+
+use crate::{Context, Result};
+
+/// Used when nothing else matches, it has every message.
+pub const FALLBACK: &str = "en-US";
+
+const BUILTIN: &[(&str, &[&str])] = &[(
+    FALLBACK,
+    &[
+        include_str!("../locales/en-US/dino.ftl"),
+        include_str!("../locales/en-US/duel.ftl"),
+        include_str!("../locales/en-US/eightball.ftl"),
+        include_str!("../locales/en-US/rpg.ftl"),
+    ],
+)];
+
+type Bundle = FluentBundle<FluentResource>;
+
+pub struct Locales {
+    bundles: Vec<(LanguageIdentifier, Bundle)>,
+}
+
+impl Locales {
+    /// Builds a bundle per locale out of its Fluent sources, [`FALLBACK`] has to be one of them.
+    pub fn new(sources: &[(&str, &[&str])]) -> Result<Self> {
+        let mut bundles = Vec::with_capacity(sources.len());
+        for (locale, resources) in sources {
+            let id: LanguageIdentifier = locale
+                .parse()
+                .with_context(|| format!("{locale} is not a locale"))?;
+            let mut bundle = Bundle::new_concurrent(vec![id.clone()]);
+            // Discord shows the isolation marks around arguments as boxes
+            bundle.set_use_isolating(false);
+
+            for source in resources.iter() {
+                let resource = FluentResource::try_new(source.to_string())
+                    .map_err(|(_, errors)| anyhow!("Invalid {locale} messages: {errors:?}"))?;
+                bundle
+                    .add_resource(resource)
+                    .map_err(|errors| anyhow!("Duplicate {locale} messages: {errors:?}"))?;
+            }
+            bundles.push((id, bundle));
+        }
+
+        anyhow::ensure!(
+            bundles.iter().any(|(id, _)| *id == FALLBACK),
+            "The {FALLBACK} messages are missing"
+        );
+
+        Ok(Self { bundles })
+    }
+
+    /// The bundles to look in, best match first: the locale itself, then the same language,
+    /// then English.
+    fn candidates(&self, locale: &str) -> Vec<&Bundle> {
+        let wanted = locale.parse::<LanguageIdentifier>().ok();
+        let exact = self
+            .bundles
+            .iter()
+            .filter(|(id, _)| wanted.as_ref() == Some(id));
+        let language = self
+            .bundles
+            .iter()
+            .filter(|(id, _)| wanted.as_ref().is_some_and(|w| w.language == id.language));
+        let fallback = self.bundles.iter().filter(|(id, _)| *id == FALLBACK);
+
+        exact
+            .chain(language)
+            .chain(fallback)
+            .map(|(_, bundle)| bundle)
+            .collect()
+    }
+
+    /// The message `id`, with `args` filled in.
+    #[cfg(any(feature = "dino", feature = "duel", feature = "rpg", test))]
+    pub fn text(&self, locale: &str, id: &str, args: &[(&str, FluentValue)]) -> String {
+        let args = to_fluent_args(args);
+        for bundle in self.candidates(locale) {
+            let Some(pattern) = bundle.get_message(id).and_then(|m| m.value()) else {
+                continue;
+            };
+            let mut errors = Vec::new();
+            let text = bundle.format_pattern(pattern, Some(&args), &mut errors);
+            log_errors(id, &errors);
+
+            return text.into_owned();
+        }
+
+        tracing::error!("There is no message {id}");
+        id.to_string()
+    }
+
+    /// One of the attributes of the message `id` at random, with `args` filled in.
+    #[cfg(any(feature = "eightball", feature = "rpg", test))]
+    pub fn pick(
+        &self,
+        locale: &str,
+        id: &str,
+        args: &[(&str, FluentValue)],
+        rng: &mut impl Rng,
+    ) -> String {
+        self.choose(locale, id, rng).format(args)
+    }
+
+    /// One of the attributes of the message `id` at random, to fill in once the arguments are
+    /// known. Games pick their texts before rolling what goes in them, so seeds keep replaying.
+    #[cfg(any(feature = "eightball", feature = "rpg", test))]
+    pub fn choose(&self, locale: &str, id: &str, rng: &mut impl Rng) -> Choice<'_> {
+        for bundle in self.candidates(locale) {
+            let Some(message) = bundle.get_message(id) else {
+                continue;
+            };
+            let choices = message.attributes().collect::<Vec<_>>();
+            if let Some(choice) = choices.choose(rng) {
+                return Choice {
+                    id: id.to_string(),
+                    picked: Some((bundle, choice.id())),
+                };
+            }
+        }
+
+        Choice {
+            id: id.to_string(),
+            picked: None,
+        }
+    }
+}
+
+/// A text [`Locales::choose`] picked.
+#[cfg(any(feature = "eightball", feature = "rpg", test))]
+pub struct Choice<'a> {
+    id: String,
+    /// The bundle the message was found in and the name of the attribute.
+    picked: Option<(&'a Bundle, &'a str)>,
+}
+
+#[cfg(any(feature = "eightball", feature = "rpg", test))]
+impl Choice<'_> {
+    /// The text with `args` filled in.
+    pub fn format(&self, args: &[(&str, FluentValue)]) -> String {
+        let attribute = self.picked.and_then(|(bundle, name)| {
+            let attribute = bundle.get_message(&self.id)?.get_attribute(name)?;
+            Some((bundle, attribute))
+        });
+        let Some((bundle, attribute)) = attribute else {
+            tracing::error!("There are no choices for the message {}", self.id);
+            return self.id.clone();
+        };
+        let args = to_fluent_args(args);
+        let mut errors = Vec::new();
+        let text = bundle.format_pattern(attribute.value(), Some(&args), &mut errors);
+        log_errors(&self.id, &errors);
+
+        text.into_owned()
+    }
+}
+
+fn to_fluent_args<'a>(args: &'a [(&str, FluentValue)]) -> FluentArgs<'a> {
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        fluent_args.set(*name, value.clone());
+    }
+
+    fluent_args
+}
+
+fn log_errors(id: &str, errors: &[FluentError]) {
+    if !errors.is_empty() {
+        tracing::warn!("Could not fully format the message {id}: {errors:?}");
+    }
+}
+
+fn locales() -> &'static Locales {
+    static LOCALES: OnceLock<Locales> = OnceLock::new();
+    LOCALES
+        .get_or_init(|| Locales::new(BUILTIN).expect("Expected the built-in messages to be valid"))
+}
+
+/// The message `id` in `locale`, see [`Locales::text`].
+#[cfg(any(feature = "dino", feature = "duel", feature = "rpg", test))]
+pub fn text(locale: &str, id: &str, args: &[(&str, FluentValue)]) -> String {
+    locales().text(locale, id, args)
+}
+
+/// A random choice of the message `id` in `locale`, see [`Locales::pick`].
+#[cfg(any(feature = "eightball", feature = "rpg"))]
+pub fn pick(locale: &str, id: &str, args: &[(&str, FluentValue)], rng: &mut impl Rng) -> String {
+    locales().pick(locale, id, args, rng)
+}
+
+/// A random choice of the message `id` in `locale` to fill in later, see [`Locales::choose`].
+#[cfg(feature = "rpg")]
+pub fn choose(locale: &str, id: &str, rng: &mut impl Rng) -> Choice<'static> {
+    locales().choose(locale, id, rng)
+}
+
+/// The language of whoever used the command, or else the server's.
+pub fn locale(ctx: Context<'_>) -> String {
+    if let Some(locale) = ctx.locale() {
+        return locale.to_string();
+    }
+
+    ctx.guild()
+        .map(|guild| guild.preferred_locale.clone())
+        .unwrap_or_else(|| FALLBACK.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::GameRng;
+
+    #[test]
+    fn builtin_messages_are_valid() {
+        let text = text(FALLBACK, "duel-won", &[("winner", "Rex".into())]);
+        assert_eq!(text, "Rex has won!");
+    }
+
+    #[test]
+    fn missing_translations_fall_back_to_english() {
+        let french = "duel-won = { $winner } a gagné !\neightball-reply =\n    .v1 = Oui.\n";
+        let english = "duel-won = { $winner } has won!\nduel-draw = It's a draw!\n";
+        let locales = Locales::new(&[(FALLBACK, &[english]), ("fr", &[french])]).unwrap();
+        let winner = [("winner", FluentValue::from("Rex"))];
+
+        assert_eq!(locales.text("fr", "duel-won", &winner), "Rex a gagné !");
+        assert_eq!(locales.text("fr-CA", "duel-won", &winner), "Rex a gagné !");
+        assert_eq!(locales.text("de", "duel-won", &winner), "Rex has won!");
+        assert_eq!(locales.text("fr", "duel-draw", &[]), "It's a draw!");
+        assert_eq!(locales.text("fr", "nothing", &[]), "nothing");
+
+        let mut rng = GameRng::new("eightball", 1, false);
+        assert_eq!(locales.pick("fr", "eightball-reply", &[], &mut rng), "Oui.");
+    }
+}
@@ -0,0 +1,240 @@
+mod audit;
+mod command_rules;
+mod commands;
+mod common;
+pub mod config;
+mod cooldowns;
+#[cfg(any(
+    feature = "dino",
+    feature = "duel",
+    feature = "eightball",
+    feature = "rpg"
+))]
+mod i18n;
+mod logging;
+pub mod maintenance;
+#[cfg(any(feature = "duel", feature = "poll"))]
+mod messaging;
+mod metrics;
+pub mod migrations;
+mod mod_log;
+mod rng;
+mod scheduler;
+mod settings;
+mod shutdown;
+#[cfg(test)]
+mod testing;
+#[cfg(any(
+    feature = "dino",
+    feature = "duel",
+    feature = "mixu",
+    feature = "rpg",
+    feature = "wallet"
+))]
+mod wallet;
+
+#[cfg(feature = "rpg")]
+use std::num::NonZeroUsize;
+
+use anyhow::{Context as AnyhowContext, Result};
+#[cfg(feature = "rpg")]
+use lru::LruCache;
+use poise::serenity_prelude::{self as serenity, FullEvent};
+use tokio::sync::RwLock;
+
+pub struct Data {
+    config: config::Config,
+    database: sqlx::SqlitePool,
+    #[cfg(feature = "rpg")]
+    rpg_summary_cache: tokio::sync::Mutex<LruCache<u64, String>>,
+    simple_commands: RwLock<commands::SimpleCommands>,
+    /// Held while a change to the simple commands is registered with Discord and saved, so the
+    /// commands being run don't wait on Discord.
+    command_changes: tokio::sync::Mutex<()>,
+    guild_settings: RwLock<settings::GuildSettings>,
+    cooldowns: cooldowns::Cooldowns,
+    command_rules: command_rules::CommandRules,
+    rng: Box<dyn rng::RngProvider>,
+    scheduler: scheduler::Scheduler,
+}
+pub type Context<'a> = poise::Context<'a, Data, anyhow::Error>;
+pub type Error = anyhow::Error;
+
+/// Opens the database from the config.
+pub async fn connect(config: &config::Config, create_if_missing: bool) -> Result<sqlx::SqlitePool> {
+    sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(
+            sqlx::sqlite::SqliteConnectOptions::new()
+                .filename(&config.database)
+                .create_if_missing(create_if_missing),
+        )
+        .await
+        .with_context(|| format!("Could not open the database {}", config.database.display()))
+}
+
+/// Starts the bot and runs it until it's told to shut down.
+pub async fn run() -> Result<()> {
+    let check_migrations = std::env::args().any(|arg| arg == "--check-migrations");
+    let config = config::Config::load()?;
+    logging::init(&config.logging)?;
+    let intents =
+        serenity::GatewayIntents::non_privileged() | serenity::GatewayIntents::MESSAGE_CONTENT;
+
+    let database = connect(&config, !check_migrations).await?;
+
+    if check_migrations {
+        return migrations::check_migrations(&database).await;
+    }
+    migrations::run_migrations(&database).await?;
+    let token = config.token()?.to_string();
+
+    if let Some(port) = config.metrics.port {
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(port).await {
+                tracing::error!("The metrics endpoint stopped: {e:#}");
+            }
+        });
+    }
+
+    let guild_settings = settings::fetch_guild_settings(&database)
+        .await
+        .context("Could not load the guild settings")?;
+    let cooldowns = cooldowns::Cooldowns::new(&database, &config.cooldowns)
+        .await
+        .context("Could not load the cooldowns")?;
+    let command_rules = command_rules::CommandRules::new(&database)
+        .await
+        .context("Could not load the command rules")?;
+    let rng = rng::provider(&config.rng);
+    let scheduler = scheduler::Scheduler::new(&database);
+    scheduler
+        .ensure_recurring(&scheduler::Job::PruneCooldowns, &"@daily".parse()?)
+        .await
+        .context("Could not schedule the cooldown cleanup")?;
+    scheduler
+        .ensure_recurring(&scheduler::Job::PruneCommandUsage, &"@daily".parse()?)
+        .await
+        .context("Could not schedule the command usage cleanup")?;
+
+    // Initialize default commands
+    let commands = commands::initialize_commands(
+        #[cfg(feature = "mixu")]
+        &database,
+        &config,
+    )
+    .await?;
+    commands::set_system_commands(&commands);
+
+    let options = poise::FrameworkOptions {
+        commands,
+        prefix_options: poise::PrefixFrameworkOptions {
+            dynamic_prefix: Some(|ctx| Box::pin(settings::dynamic_prefix(ctx))),
+            mention_as_prefix: false,
+            ..Default::default()
+        },
+        event_handler: |ctx, event, framework, user_data| {
+            Box::pin(event_event_handler(ctx, event, framework, user_data))
+        },
+        on_error: |err| Box::pin(on_error(err)),
+        command_check: Some(|ctx| Box::pin(command_check(ctx))),
+        pre_command: |ctx| Box::pin(pre_command(ctx)),
+        post_command: |ctx| Box::pin(post_command(ctx)),
+        ..Default::default()
+    };
+
+    let user_data = Data {
+        config,
+        database: database.clone(),
+        #[cfg(feature = "rpg")]
+        rpg_summary_cache: tokio::sync::Mutex::new(LruCache::new(NonZeroUsize::new(10).unwrap())),
+        simple_commands: RwLock::default(),
+        command_changes: tokio::sync::Mutex::default(),
+        guild_settings: RwLock::new(guild_settings),
+        cooldowns,
+        command_rules,
+        rng,
+        scheduler,
+    };
+    let framework = poise::Framework::builder()
+        .options(options)
+        .setup(|ctx, _ready, framework| {
+            Box::pin(async move {
+                // poise::builtins::register_globally(ctx, Vec::new()).await?;
+                poise::builtins::register_globally(ctx, &framework.options().commands).await?;
+                Ok(user_data)
+            })
+        })
+        .build();
+
+    let mut client = serenity::ClientBuilder::new(token, intents)
+        .framework(logging::TracedFramework(framework))
+        .await
+        .context("Could not create the Discord client")?;
+    let shutdown = shutdown::shutdown_on_signal(client.http.clone(), client.shard_manager.clone());
+
+    // The client doesn't always return once the shards are shut down, e.g. when none connected
+    tokio::select! {
+        result = client.start() => result.context("The Discord client stopped")?,
+        _ = shutdown => {}
+    }
+
+    // Waits for the connections in use, so running transactions get to finish
+    database.close().await;
+    tracing::info!("Shut down cleanly");
+
+    Ok(())
+}
+
+async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
+    match error {
+        poise::FrameworkError::Command { error, ctx, .. } => {
+            metrics::command_error(ctx).await;
+            mod_log::report_command_error(ctx, &error).await;
+        }
+        _ => {
+            if let Err(e) = poise::builtins::on_error(error).await {
+                tracing::error!("Error while trying to handle poise error: {e}")
+            }
+        }
+    }
+}
+
+async fn command_check(ctx: Context<'_>) -> Result<bool> {
+    Ok(shutdown::reject_during_shutdown(ctx).await? && command_rules::check(ctx).await?)
+}
+
+async fn pre_command(ctx: Context<'_>) {
+    logging::pre_command(ctx).await;
+    metrics::pre_command(ctx);
+}
+
+async fn post_command(ctx: Context<'_>) {
+    logging::post_command(ctx).await;
+    metrics::post_command(ctx).await;
+}
+
+async fn event_event_handler<'a>(
+    ctx: &'a serenity::Context,
+    event: &'a serenity::FullEvent,
+    _framework: poise::FrameworkContext<'a, Data, Error>,
+    user_data: &Data,
+) -> Result<(), Error> {
+    match event {
+        FullEvent::Ready { data_about_bot } => {
+            tracing::info!("{} is connected!", data_about_bot.user.name);
+            commands::register_dynamic_commands_for_every_guild(ctx, user_data).await;
+            let collectors = async {
+                #[cfg(uses_collectors)]
+                commands::setup_collectors(ctx, user_data).await;
+            };
+            tokio::join!(collectors, user_data.scheduler.run_worker(ctx, user_data));
+        }
+        FullEvent::InteractionCreate { interaction } => {
+            commands::try_intercepting_command_call(ctx, user_data, interaction).await?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
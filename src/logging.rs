@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Context as AnyhowContext;
+use poise::serenity_prelude::{self as serenity, FullEvent, Interaction};
+use tracing::{field, Instrument, Span};
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+use crate::config::{LogFormat, Logging};
+use crate::{Context, Data, Error, Result};
+
+pub fn init(config: &Logging) -> Result<()> {
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) => EnvFilter::try_new(&config.level)
+            .with_context(|| format!("Invalid log level {:?}", config.level))?,
+    };
+
+    let registry = tracing_subscriber::registry().with(filter);
+    match config.format {
+        LogFormat::Pretty => registry.with(fmt::layer()).try_init()?,
+        LogFormat::Json => registry
+            .with(fmt::layer().json().flatten_event(true))
+            .try_init()?,
+    }
+
+    Ok(())
+}
+
+/// Wraps the poise framework so every message and interaction is handled inside its own span.
+///
+/// Everything logged while handling the event, including command errors, carries the guild,
+/// channel, user and command name of the span.
+pub struct TracedFramework(pub poise::Framework<Data, Error>);
+
+#[serenity::async_trait]
+impl serenity::Framework for TracedFramework {
+    async fn init(&mut self, client: &serenity::Client) {
+        serenity::Framework::init(&mut self.0, client).await
+    }
+
+    async fn dispatch(&self, ctx: serenity::Context, event: FullEvent) {
+        match event_span(&event) {
+            Some(span) => {
+                serenity::Framework::dispatch(&self.0, ctx, event)
+                    .instrument(span)
+                    .await
+            }
+            None => serenity::Framework::dispatch(&self.0, ctx, event).await,
+        }
+    }
+}
+
+fn event_span(event: &FullEvent) -> Option<Span> {
+    let (span, guild_id) = match event {
+        FullEvent::Message { new_message } if !new_message.author.bot => {
+            let span = tracing::info_span!(
+                "message",
+                id = new_message.id.get(),
+                guild = field::Empty,
+                channel = new_message.channel_id.get(),
+                user = new_message.author.id.get(),
+                command = field::Empty,
+            );
+            (span, new_message.guild_id)
+        }
+        FullEvent::InteractionCreate {
+            interaction: Interaction::Command(command),
+        } => {
+            let span = tracing::info_span!(
+                "interaction",
+                id = command.id.get(),
+                guild = field::Empty,
+                channel = command.channel_id.get(),
+                user = command.user.id.get(),
+                command = command.data.name,
+            );
+            (span, command.guild_id)
+        }
+        FullEvent::InteractionCreate {
+            interaction: Interaction::Component(component),
+        } => {
+            let span = tracing::info_span!(
+                "component",
+                id = component.id.get(),
+                guild = field::Empty,
+                channel = component.channel_id.get(),
+                user = component.user.id.get(),
+                custom_id = component.data.custom_id,
+            );
+            (span, component.guild_id)
+        }
+        _ => return None,
+    };
+
+    if let Some(guild_id) = guild_id {
+        span.record("guild", guild_id.get());
+    }
+
+    Some(span)
+}
+
+/// When the command started running, stored as the invocation data in `pre_command`.
+struct CommandStart(Instant);
+
+pub async fn pre_command(ctx: Context<'_>) {
+    Span::current().record("command", ctx.command().qualified_name.as_str());
+    ctx.set_invocation_data(CommandStart(Instant::now())).await;
+    tracing::debug!("Command started");
+}
+
+pub async fn post_command(ctx: Context<'_>) {
+    let latency = command_latency(ctx).await.unwrap_or_default();
+    tracing::info!(latency_ms = latency.as_millis() as u64, "Command finished");
+}
+
+/// How long the command has been running, `None` when it failed before `pre_command`.
+pub async fn command_latency(ctx: Context<'_>) -> Option<Duration> {
+    ctx.invocation_data::<CommandStart>()
+        .await
+        .map(|start| start.0.elapsed())
+}
@@ -0,0 +1,288 @@
+//! Database upkeep for `twiggy-admin`, meant to run while the bot is stopped.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context as AnyhowContext};
+use poise::serenity_prelude::{GuildId, UserId};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection, SqliteExecutor, SqlitePool};
+
+use crate::commands::{self, CommandInfo, ExportedCommand, ImportPlan};
+#[cfg(feature = "dino")]
+use crate::config::Assets;
+use crate::Result;
+
+/// Writes a consistent copy of the database to `destination`, which must not exist yet.
+pub async fn backup(database: &SqlitePool, destination: &Path) -> Result<()> {
+    ensure!(
+        !destination.exists(),
+        "{} already exists, pick another file",
+        destination.display()
+    );
+
+    let destination = destination.to_string_lossy();
+    sqlx::query("VACUUM INTO ?")
+        .bind(destination.as_ref())
+        .execute(database)
+        .await?;
+
+    Ok(())
+}
+
+/// Replaces the database with `backup` once it passes an integrity check. The current one is
+/// moved next to it with a `.before-restore` extension, which is returned.
+pub async fn restore(backup: &Path, database: &Path) -> Result<Option<PathBuf>> {
+    let mut conn = SqliteConnectOptions::new()
+        .filename(backup)
+        .read_only(true)
+        .connect()
+        .await
+        .with_context(|| format!("Could not open the backup {}", backup.display()))?;
+    let problems = sqlite_integrity(&mut conn).await?;
+    conn.close().await?;
+    ensure!(
+        problems.is_empty(),
+        "The backup is damaged: {}",
+        problems.join(", ")
+    );
+
+    let previous = match database.exists() {
+        true => {
+            let previous = database.with_extension("before-restore");
+            std::fs::rename(database, &previous)
+                .with_context(|| format!("Could not move {} out of the way", database.display()))?;
+            Some(previous)
+        }
+        false => None,
+    };
+
+    // Leftovers of the old database would be applied on top of the backup
+    for suffix in ["-wal", "-shm"] {
+        let mut leftover = database.as_os_str().to_owned();
+        leftover.push(suffix);
+        if let Err(e) = std::fs::remove_file(&leftover) {
+            ensure!(
+                e.kind() == std::io::ErrorKind::NotFound,
+                "Could not remove {}: {e}",
+                Path::new(&leftover).display()
+            );
+        }
+    }
+
+    std::fs::copy(backup, database)
+        .with_context(|| format!("Could not copy the backup to {}", database.display()))?;
+
+    Ok(previous)
+}
+
+/// Puts someone's RPG rating back to where everyone starts. Returns whether they had one.
+pub async fn reset_elo(executor: impl SqliteExecutor<'_>, user_id: UserId) -> Result<bool> {
+    let user_id = user_id.to_string();
+    let result = sqlx::query!(
+        r#"UPDATE RPGCharacter SET elo_rank = 1000, peak_elo = 1000, floor_elo = 1000
+        WHERE user_id = ?"#,
+        user_id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Removes someone's cooldowns, in every server unless `guild_id` is given. Returns how many.
+pub async fn reset_cooldowns(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+    guild_id: Option<GuildId>,
+) -> Result<u64> {
+    let user_id = user_id.get() as i64;
+    let guild_id = guild_id.map(|id| id.get() as i64);
+    let result = sqlx::query!(
+        "DELETE FROM Cooldowns WHERE user_id = ? AND (? IS NULL OR guild_id = ?)",
+        user_id,
+        guild_id,
+        guild_id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Draws the dino images missing from the output directory. Returns the names of the dinos.
+#[cfg(feature = "dino")]
+pub async fn regenerate_dino_images(database: &SqlitePool, assets: &Assets) -> Result<Vec<String>> {
+    commands::setup_dinos(assets)?;
+    commands::regenerate_missing_dino_images(database).await
+}
+
+/// A guild's simple commands as pretty printed JSON.
+pub async fn export_commands(database: &SqlitePool, guild_id: GuildId) -> Result<String> {
+    let commands = commands::export_commands(database, guild_id).await?;
+
+    Ok(serde_json::to_string_pretty(&commands)?)
+}
+
+/// Adds the commands of an export to a guild, replacing the ones with the same name and
+/// leaving out the ones `/commands import` would. Returns what happened.
+pub async fn import_commands(
+    database: &SqlitePool,
+    guild_id: GuildId,
+    json: &str,
+) -> Result<String> {
+    let commands: Vec<ExportedCommand> =
+        serde_json::from_str(json).context("The file is not a command export")?;
+
+    let mut conn = database.acquire().await?;
+    let existing = commands::export_commands(&mut *conn, guild_id)
+        .await?
+        .into_iter()
+        .map(|command| {
+            let info = CommandInfo {
+                kind: command.kind,
+                content: command.content,
+            };
+            (command.name, info)
+        })
+        .collect();
+    // Whatever ends up enabled, the bot's own commands keep their names
+    let default_commands = commands::KNOWN_COMMANDS
+        .iter()
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+
+    let plan = ImportPlan::new(commands, &default_commands, Some(&existing));
+    commands::import_commands(&mut conn, guild_id, &plan.commands, None).await?;
+
+    Ok(plan.summary())
+}
+
+async fn sqlite_integrity(conn: &mut sqlx::SqliteConnection) -> Result<Vec<String>> {
+    let results: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(&mut *conn)
+        .await?;
+
+    Ok(results.into_iter().filter(|r| r != "ok").collect())
+}
+
+/// Looks for rows that point at something that doesn't exist. Returns a line per problem.
+pub async fn check_integrity(database: &SqlitePool) -> Result<Vec<String>> {
+    let mut conn = database.acquire().await?;
+    let mut problems = sqlite_integrity(&mut conn).await?;
+
+    let orphans = [
+        (
+            "Dino.owner_id has no DinoUser",
+            "SELECT 'Dino ' || id || ' (' || name || ') belongs to ' || owner_id FROM Dino
+            WHERE owner_id NOT IN (SELECT id FROM DinoUser)",
+        ),
+        (
+            "DinoTransactions point at a missing dino",
+            "SELECT 'DinoTransactions ' || id || ' is about dino ' || dino_id FROM DinoTransactions
+            WHERE dino_id NOT IN (SELECT id FROM Dino)",
+        ),
+        (
+            "DuelStats have no User",
+            "SELECT 'DuelStats of ' || user_id FROM DuelStats WHERE user_id NOT IN (SELECT id FROM User)",
+        ),
+        (
+            "Reminders have no scheduled task",
+            "SELECT 'Reminder ' || id || ' of ' || user_id FROM Reminders
+            WHERE task_id IS NULL OR task_id NOT IN (SELECT id FROM ScheduledTasks)",
+        ),
+        (
+            "Wallets don't match their ledger",
+            "SELECT 'Wallet of ' || w.user_id || ' has ' || w.balance || ' instead of ' || l.balance
+            FROM Wallet w JOIN Ledger l ON l.id = (SELECT MAX(id) FROM Ledger WHERE user_id = w.user_id)
+            WHERE w.balance != l.balance",
+        ),
+    ];
+
+    for (check, query) in orphans {
+        let rows: Vec<String> = sqlx::query_scalar(query)
+            .fetch_all(&mut *conn)
+            .await
+            .with_context(|| format!("Could not check that {check}"))?;
+        problems.extend(rows.into_iter().map(|row| format!("{check}: {row}")));
+    }
+
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[tokio::test]
+    async fn orphaned_rows_are_found() {
+        let database = testing::database().await;
+        assert!(check_integrity(&database).await.unwrap().is_empty());
+
+        sqlx::query(
+            r#"PRAGMA foreign_keys = OFF;
+            INSERT INTO Dino (id, owner_id, name, filename, created_at, body, mouth, eyes)
+                VALUES (1, '42', 'Rex', 'rex.png', 0, 'b', 'm', 'e');
+            INSERT INTO Wallet (user_id, balance) VALUES (1, 10);
+            INSERT INTO Ledger (user_id, amount, balance, reason) VALUES (1, 5, 5, 'gift');
+            PRAGMA foreign_keys = ON;"#,
+        )
+        .execute(&database)
+        .await
+        .unwrap();
+
+        let problems = check_integrity(&database).await.unwrap();
+        assert_eq!(
+            problems,
+            [
+                "Dino.owner_id has no DinoUser: Dino 1 (Rex) belongs to 42",
+                "Wallets don't match their ledger: Wallet of 1 has 10 instead of 5",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn backups_can_be_restored() {
+        let directory = std::env::temp_dir().join(format!("twiggy-backup-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let (original_path, backup_path, database_path) = (
+            directory.join("original.sqlite"),
+            directory.join("backup.sqlite"),
+            directory.join("db.sqlite"),
+        );
+        std::fs::write(&database_path, "not a database").unwrap();
+
+        // An in-memory database would back up to memory too
+        let options = SqliteConnectOptions::new()
+            .filename(&original_path)
+            .create_if_missing(true);
+        let original = SqlitePool::connect_with(options).await.unwrap();
+        sqlx::query("CREATE TABLE RPGCharacter AS SELECT '1' as user_id, 1500 as elo_rank, 1500 as peak_elo, 1500 as floor_elo")
+            .execute(&original)
+            .await
+            .unwrap();
+        backup(&original, &backup_path).await.unwrap();
+        assert!(backup(&original, &backup_path).await.is_err());
+        original.close().await;
+
+        let previous = restore(&backup_path, &database_path).await.unwrap();
+        assert_eq!(
+            previous,
+            Some(database_path.with_extension("before-restore"))
+        );
+
+        let restored =
+            SqlitePool::connect_with(SqliteConnectOptions::new().filename(&database_path))
+                .await
+                .unwrap();
+        assert!(reset_elo(&restored, UserId::new(1)).await.unwrap());
+        let elo: i64 = sqlx::query_scalar("SELECT elo_rank FROM RPGCharacter WHERE user_id = '1'")
+            .fetch_one(&restored)
+            .await
+            .unwrap();
+        assert_eq!(elo, 1000);
+
+        restored.close().await;
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+#[cfg(feature = "duel")]
+use std::time::Duration;
+
+#[cfg(feature = "duel")]
+use chrono::{DateTime, Utc};
+#[cfg(feature = "poll")]
+use poise::serenity_prelude::ReactionType;
+use poise::serenity_prelude::{
+    ButtonStyle, CreateActionRow, CreateButton, CreateEmbed, CreateInteractionResponseMessage,
+    EditMessage, MessageId,
+};
+#[cfg(feature = "duel")]
+use poise::serenity_prelude::{
+    ComponentInteraction, ComponentInteractionCollector, EditMember, InteractionId, UserId,
+};
+use poise::{CreateReply, ReplyHandle};
+use tokio::sync::Mutex;
+
+#[cfg(feature = "duel")]
+use crate::common::{name, response, update_response};
+use crate::{Context, Result};
+
+/// A message as the game logic sees it, without the serenity builders so it can be inspected.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reply {
+    pub content: String,
+    pub ephemeral: bool,
+    pub buttons: Vec<Button>,
+    pub embed: Option<Embed>,
+}
+
+impl Reply {
+    pub fn new(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn ephemeral(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            ephemeral: true,
+            ..Default::default()
+        }
+    }
+
+    /// A message made of an embed only.
+    pub fn embed(title: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            embed: Some(Embed {
+                title: title.into(),
+                description: description.into(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    pub fn button(mut self, button: Button) -> Self {
+        self.buttons.push(button);
+        self
+    }
+
+    fn components(&self) -> Vec<CreateActionRow> {
+        if self.buttons.is_empty() {
+            return Vec::new();
+        }
+
+        let buttons = self.buttons.iter().map(Button::create).collect();
+        vec![CreateActionRow::Buttons(buttons)]
+    }
+
+    fn embeds(&self) -> Vec<CreateEmbed> {
+        self.embed.iter().map(Embed::create).collect()
+    }
+}
+
+impl From<Reply> for CreateReply {
+    fn from(reply: Reply) -> Self {
+        let embeds = reply.embeds();
+        let create = CreateReply::default()
+            .components(reply.components())
+            .content(reply.content)
+            .ephemeral(reply.ephemeral);
+
+        CreateReply { embeds, ..create }
+    }
+}
+
+impl From<Reply> for CreateInteractionResponseMessage {
+    fn from(reply: Reply) -> Self {
+        CreateInteractionResponseMessage::new()
+            .components(reply.components())
+            .embeds(reply.embeds())
+            .content(reply.content)
+            .ephemeral(reply.ephemeral)
+    }
+}
+
+impl From<Reply> for EditMessage {
+    fn from(reply: Reply) -> Self {
+        EditMessage::new()
+            .components(reply.components())
+            .embeds(reply.embeds())
+            .content(reply.content)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Embed {
+    pub title: String,
+    pub description: String,
+}
+
+impl Embed {
+    fn create(&self) -> CreateEmbed {
+        CreateEmbed::new()
+            .title(&self.title)
+            .description(&self.description)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Button {
+    pub custom_id: String,
+    pub label: String,
+    pub emoji: char,
+    pub style: ButtonStyle,
+}
+
+impl Button {
+    fn create(&self) -> CreateButton {
+        CreateButton::new(&self.custom_id)
+            .emoji(self.emoji)
+            .label(&self.label)
+            .style(self.style)
+    }
+}
+
+/// Someone pressed a button.
+#[cfg(feature = "duel")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Click {
+    pub id: InteractionId,
+    pub user_id: UserId,
+    /// Server nickname of whoever pressed the button, or their username.
+    pub user_name: String,
+}
+
+/// Everything duels and polls say to Discord, so they can be run against a fake in the tests.
+pub trait Messenger {
+    /// Sends a message, the first one answers the command itself.
+    async fn send(&self, reply: Reply) -> Result<MessageId>;
+
+    /// Replaces a message sent with [`Messenger::send`], or one the bot sent earlier in the
+    /// command's channel.
+    async fn edit(&self, message_id: MessageId, reply: Reply) -> Result<()>;
+
+    /// Reacts to a message in the command's channel.
+    #[cfg(feature = "poll")]
+    async fn react(&self, message_id: MessageId, emoji: &str) -> Result<()>;
+
+    /// Takes every `emoji` reaction off a message in the command's channel.
+    #[cfg(feature = "poll")]
+    async fn remove_reactions(&self, message_id: MessageId, emoji: &str) -> Result<()>;
+
+    /// The emojis the bot reacted with on a message in the command's channel, with how many
+    /// reacted with each, the bot included.
+    #[cfg(feature = "poll")]
+    async fn own_reactions(&self, message_id: MessageId) -> Result<Vec<(String, u64)>>;
+
+    /// Waits for the next press of the `custom_id` button, `None` once `timeout` passes without one.
+    #[cfg(feature = "duel")]
+    async fn next_click(
+        &self,
+        message_id: MessageId,
+        custom_id: &str,
+        timeout: Duration,
+    ) -> Option<Click>;
+
+    /// Answers a button press, ephemeral replies are only shown to whoever pressed the button,
+    /// others replace the message it was on.
+    #[cfg(feature = "duel")]
+    async fn answer(&self, click: &Click, reply: Reply) -> Result<()>;
+
+    /// Prevents a member from talking until `until`.
+    #[cfg(feature = "duel")]
+    async fn timeout_member(&self, user_id: UserId, until: DateTime<Utc>) -> Result<()>;
+}
+
+/// Talks to Discord on behalf of a command.
+pub struct DiscordMessenger<'a> {
+    ctx: Context<'a>,
+    replies: Mutex<HashMap<MessageId, ReplyHandle<'a>>>,
+    #[cfg(feature = "duel")]
+    clicks: Mutex<HashMap<InteractionId, ComponentInteraction>>,
+}
+
+impl<'a> DiscordMessenger<'a> {
+    pub fn new(ctx: Context<'a>) -> Self {
+        Self {
+            ctx,
+            replies: Mutex::default(),
+            #[cfg(feature = "duel")]
+            clicks: Mutex::default(),
+        }
+    }
+}
+
+impl Messenger for DiscordMessenger<'_> {
+    async fn send(&self, reply: Reply) -> Result<MessageId> {
+        let handle = self.ctx.send(reply.into()).await?;
+        let message_id = handle.message().await?.id;
+        self.replies.lock().await.insert(message_id, handle);
+
+        Ok(message_id)
+    }
+
+    async fn edit(&self, message_id: MessageId, reply: Reply) -> Result<()> {
+        let replies = self.replies.lock().await;
+        match replies.get(&message_id) {
+            Some(handle) => handle.edit(self.ctx, reply.into()).await?,
+            None => {
+                self.ctx
+                    .channel_id()
+                    .edit_message(self.ctx, message_id, reply.into())
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "poll")]
+    async fn react(&self, message_id: MessageId, emoji: &str) -> Result<()> {
+        let reaction = ReactionType::Unicode(emoji.to_string());
+        self.ctx
+            .channel_id()
+            .create_reaction(self.ctx, message_id, reaction)
+            .await?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "poll")]
+    async fn remove_reactions(&self, message_id: MessageId, emoji: &str) -> Result<()> {
+        let reaction = ReactionType::Unicode(emoji.to_string());
+        self.ctx
+            .channel_id()
+            .delete_reaction_emoji(self.ctx, message_id, reaction)
+            .await?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "poll")]
+    async fn own_reactions(&self, message_id: MessageId) -> Result<Vec<(String, u64)>> {
+        let message = self.ctx.channel_id().message(self.ctx, message_id).await?;
+        let reactions = message
+            .reactions
+            .iter()
+            .filter(|r| r.me)
+            .map(|r| (r.reaction_type.to_string(), r.count))
+            .collect();
+
+        Ok(reactions)
+    }
+
+    #[cfg(feature = "duel")]
+    async fn next_click(
+        &self,
+        message_id: MessageId,
+        custom_id: &str,
+        timeout: Duration,
+    ) -> Option<Click> {
+        let custom_id = custom_id.to_string();
+        let interaction = ComponentInteractionCollector::new(self.ctx)
+            .message_id(message_id)
+            .filter(move |i| i.data.custom_id == custom_id)
+            .timeout(timeout)
+            .await?;
+
+        let click = Click {
+            id: interaction.id,
+            user_id: interaction.user.id,
+            user_name: name(&self.ctx, &interaction.user).await,
+        };
+        self.clicks.lock().await.insert(interaction.id, interaction);
+
+        Some(click)
+    }
+
+    #[cfg(feature = "duel")]
+    async fn answer(&self, click: &Click, reply: Reply) -> Result<()> {
+        let Some(interaction) = self.clicks.lock().await.remove(&click.id) else {
+            anyhow::bail!("Button press {} was already answered", click.id);
+        };
+
+        let resp = if reply.ephemeral {
+            response(reply.into())
+        } else {
+            update_response(reply.into())
+        };
+        interaction.create_response(self.ctx, resp).await?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "duel")]
+    async fn timeout_member(&self, user_id: UserId, until: DateTime<Utc>) -> Result<()> {
+        let Some(guild_id) = self.ctx.guild_id() else {
+            return Ok(());
+        };
+
+        let edit = EditMember::new().disable_communication_until(until.to_rfc3339());
+        guild_id.edit_member(self.ctx, user_id, edit).await?;
+
+        Ok(())
+    }
+}
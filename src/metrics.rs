@@ -0,0 +1,220 @@
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use anyhow::Context as AnyhowContext;
+use prometheus::{
+    Encoder, Gauge, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::{Context, Result};
+
+pub struct Metrics {
+    registry: Registry,
+    commands: IntCounterVec,
+    command_errors: IntCounterVec,
+    command_duration: HistogramVec,
+    query_duration: HistogramVec,
+    task_duration: HistogramVec,
+    #[cfg(uses_collectors)]
+    collector_queue: IntGaugeVec,
+    #[cfg(feature = "rpg")]
+    cache_lookups: IntCounterVec,
+    gateway_latency: Gauge,
+}
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+pub fn metrics() -> &'static Metrics {
+    METRICS.get_or_init(|| Metrics::new().expect("Expected the metrics to be valid"))
+}
+
+impl Metrics {
+    fn new() -> Result<Self> {
+        let registry = Registry::new_custom(Some("twiggy".to_string()), None)?;
+
+        let commands = IntCounterVec::new(
+            Opts::new("commands_total", "Command invocations"),
+            &["command"],
+        )?;
+        let command_errors = IntCounterVec::new(
+            Opts::new("command_errors_total", "Commands that returned an error"),
+            &["command"],
+        )?;
+        let command_duration = HistogramVec::new(
+            HistogramOpts::new("command_duration_seconds", "Time spent running a command"),
+            &["command"],
+        )?;
+        let query_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "db_query_duration_seconds",
+                "Time spent on database queries",
+            )
+            .buckets(vec![0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5, 1.0]),
+            &["query"],
+        )?;
+        let task_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "task_duration_seconds",
+                "Time spent on slow work that isn't a single query, such as drawing a dino",
+            ),
+            &["task"],
+        )?;
+        let collector_queue = IntGaugeVec::new(
+            Opts::new(
+                "collector_queue_size",
+                "Interactions received by a collector that haven't been handled yet",
+            ),
+            &["collector"],
+        )?;
+        let cache_lookups = IntCounterVec::new(
+            Opts::new("cache_lookups_total", "Cache lookups by result"),
+            &["cache", "result"],
+        )?;
+        let gateway_latency = Gauge::new(
+            "gateway_latency_seconds",
+            "Heartbeat latency of the shard that ran the last command",
+        )?;
+
+        registry.register(Box::new(commands.clone()))?;
+        registry.register(Box::new(command_errors.clone()))?;
+        registry.register(Box::new(command_duration.clone()))?;
+        registry.register(Box::new(query_duration.clone()))?;
+        registry.register(Box::new(task_duration.clone()))?;
+        registry.register(Box::new(collector_queue.clone()))?;
+        registry.register(Box::new(cache_lookups.clone()))?;
+        registry.register(Box::new(gateway_latency.clone()))?;
+
+        Ok(Self {
+            registry,
+            commands,
+            command_errors,
+            command_duration,
+            query_duration,
+            task_duration,
+            #[cfg(uses_collectors)]
+            collector_queue,
+            #[cfg(feature = "rpg")]
+            cache_lookups,
+            gateway_latency,
+        })
+    }
+
+    pub fn render(&self) -> Result<String> {
+        let mut buffer = vec![];
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+
+    #[cfg(uses_collectors)]
+    pub fn collector_received(&self, collector: &str) {
+        self.collector_queue.with_label_values(&[collector]).inc();
+    }
+
+    #[cfg(uses_collectors)]
+    pub fn collector_handled(&self, collector: &str) {
+        self.collector_queue.with_label_values(&[collector]).dec();
+    }
+
+    #[cfg(feature = "rpg")]
+    pub fn cache_lookup(&self, cache: &str, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+        self.cache_lookups.with_label_values(&[cache, result]).inc();
+    }
+
+    fn command_finished(&self, command: &str, duration: Option<Duration>) {
+        if let Some(duration) = duration {
+            self.command_duration
+                .with_label_values(&[command])
+                .observe(duration.as_secs_f64());
+        }
+    }
+}
+
+/// Runs a database query and records how long it took under `query`.
+pub async fn timed_query<T>(query: &str, future: impl Future<Output = T>) -> T {
+    let start = Instant::now();
+    let result = future.await;
+    metrics()
+        .query_duration
+        .with_label_values(&[query])
+        .observe(start.elapsed().as_secs_f64());
+
+    result
+}
+
+/// Runs some work other than a database query and records how long it took under `task`.
+pub async fn timed_task<T>(task: &str, future: impl Future<Output = T>) -> T {
+    let start = Instant::now();
+    let result = future.await;
+    metrics()
+        .task_duration
+        .with_label_values(&[task])
+        .observe(start.elapsed().as_secs_f64());
+
+    result
+}
+
+pub fn pre_command(ctx: Context<'_>) {
+    metrics()
+        .commands
+        .with_label_values(&[&ctx.command().qualified_name])
+        .inc();
+}
+
+pub async fn post_command(ctx: Context<'_>) {
+    let metrics = metrics();
+    let latency = crate::logging::command_latency(ctx).await;
+    metrics.command_finished(&ctx.command().qualified_name, latency);
+
+    let ping = ctx.ping().await;
+    if !ping.is_zero() {
+        metrics.gateway_latency.set(ping.as_secs_f64());
+    }
+}
+
+pub async fn command_error(ctx: Context<'_>) {
+    let metrics = metrics();
+    let command = &ctx.command().qualified_name;
+    metrics.command_errors.with_label_values(&[command]).inc();
+
+    let latency = crate::logging::command_latency(ctx).await;
+    metrics.command_finished(command, latency);
+}
+
+/// Serves the metrics on `127.0.0.1:<port>`, every request gets the metrics whatever its path.
+pub async fn serve(port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .await
+        .with_context(|| format!("Could not listen on 127.0.0.1:{port}"))?;
+
+    tracing::info!("Serving metrics on http://127.0.0.1:{port}/metrics");
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            if let Err(e) = respond(stream).await {
+                tracing::debug!("Could not answer a metrics request: {e}");
+            }
+        });
+    }
+}
+
+async fn respond(mut stream: TcpStream) -> Result<()> {
+    // The request itself doesn't matter, but it has to be read before answering
+    let mut request = [0; 1024];
+    let _ = stream.read(&mut request).await?;
+
+    let body = metrics().render()?;
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use std::collections::HashSet;
+
+use anyhow::Context as AnyhowContext;
+use sqlx::migrate::{Migrate, Migration, Migrator};
+use sqlx::SqlitePool;
+
+use crate::Result;
+
+/// Every file in `migrations/`, embedded at compile time.
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Migrations that are embedded in the binary but not applied to the database yet.
+pub async fn pending_migrations(db: &SqlitePool) -> Result<Vec<&'static Migration>> {
+    let mut conn = db.acquire().await?;
+    // Checking must not write to the database, so the table isn't created here
+    let has_table: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if !has_table {
+        return Ok(MIGRATOR.iter().collect());
+    }
+
+    let applied: HashSet<i64> = conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect();
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|migration| !applied.contains(&migration.version))
+        .collect())
+}
+
+/// Applies every pending migration and logs which ones were run.
+pub async fn run_migrations(db: &SqlitePool) -> Result<()> {
+    let pending = pending_migrations(db).await?;
+
+    MIGRATOR
+        .run(db)
+        .await
+        .context("Could not apply the database migrations")?;
+
+    if pending.is_empty() {
+        tracing::info!("Database schema is up to date");
+    }
+    for migration in pending {
+        tracing::info!(
+            version = migration.version,
+            "Applied migration {}",
+            migration.description
+        );
+    }
+
+    Ok(())
+}
+
+/// Lists pending migrations, fails if there are any.
+pub async fn check_migrations(db: &SqlitePool) -> Result<()> {
+    let pending = pending_migrations(db).await?;
+
+    if pending.is_empty() {
+        tracing::info!("Database schema is up to date");
+        return Ok(());
+    }
+
+    for migration in &pending {
+        tracing::warn!(
+            version = migration.version,
+            "Pending migration {}",
+            migration.description
+        );
+    }
+    anyhow::bail!("{} migration(s) have not been applied", pending.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[tokio::test]
+    async fn checking_leaves_a_new_database_alone() {
+        let db = testing::empty_database().await;
+
+        let pending = pending_migrations(&db).await.unwrap();
+        assert_eq!(pending.len(), MIGRATOR.iter().count());
+
+        let tables: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(tables, 0);
+    }
+}
@@ -0,0 +1,94 @@
+use poise::serenity_prelude::{CreateEmbed, CreateEmbedFooter, CreateMessage, Mention, Timestamp};
+use rand::Rng;
+
+use crate::{
+    common::{ephemeral_reply, truncate},
+    logging,
+    settings::Setting,
+    Context, Error,
+};
+
+/// Embed fields are limited to 1024 characters.
+const MAX_FIELD_LENGTH: usize = 1000;
+
+/// Logs a failed command, reports it to the mod-log channel if the server has one and tells
+/// the user something went wrong. The same error ID is shown everywhere so they can be matched.
+pub async fn report_command_error(ctx: Context<'_>, error: &Error) {
+    let error_id = new_error_id();
+    let latency = logging::command_latency(ctx).await.unwrap_or_default();
+    tracing::error!(
+        error_id,
+        latency_ms = latency.as_millis() as u64,
+        error = format!("{error:#}"),
+        "Command failed"
+    );
+
+    if let Err(e) = post_to_mod_log(ctx, error, &error_id).await {
+        tracing::warn!(
+            error_id,
+            "Could not post the error to the mod-log channel: {e:#}"
+        );
+    }
+
+    let msg = format!(
+        "Something went wrong while running this command. If you report it, please mention the error ID `{error_id}`."
+    );
+    if let Err(e) = ctx.send(ephemeral_reply(msg)).await {
+        tracing::warn!(error_id, "Could not tell the user about the error: {e:#}");
+    }
+}
+
+async fn post_to_mod_log(ctx: Context<'_>, error: &Error, error_id: &str) -> crate::Result<()> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let Some(channel_id) = ctx
+        .data()
+        .guild_channel(guild_id, Setting::ModLogChannel)
+        .await
+    else {
+        return Ok(());
+    };
+
+    let chain = error
+        .chain()
+        .map(|cause| cause.to_string())
+        .collect::<Vec<_>>()
+        .join("\n↳ ");
+
+    let embed = CreateEmbed::default()
+        .title("Command error")
+        .colour(0xD9534F)
+        .field(
+            "Command",
+            format!("`{}`", ctx.command().qualified_name),
+            true,
+        )
+        .field("User", Mention::from(ctx.author().id).to_string(), true)
+        .field("Channel", Mention::from(ctx.channel_id()).to_string(), true)
+        .field(
+            "Invocation",
+            format!(
+                "```{}```",
+                truncate(&ctx.invocation_string(), MAX_FIELD_LENGTH)
+            ),
+            false,
+        )
+        .field(
+            "Error",
+            format!("```{}```", truncate(&chain, MAX_FIELD_LENGTH)),
+            false,
+        )
+        .footer(CreateEmbedFooter::new(format!("Error ID: {error_id}")))
+        .timestamp(Timestamp::now());
+
+    channel_id
+        .send_message(ctx, CreateMessage::new().embed(embed))
+        .await?;
+
+    Ok(())
+}
+
+fn new_error_id() -> String {
+    format!("{:08x}", rand::thread_rng().gen::<u32>())
+}
@@ -0,0 +1,112 @@
+use std::fmt::Display;
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+use crate::{config, Data};
+
+/// Picks the seed of every game, so it can be swapped for a fixed one in tests and replays.
+pub trait RngProvider: Send + Sync {
+    fn next_seed(&self) -> u64;
+}
+
+/// A new random seed for every game.
+pub struct RandomSeeds;
+
+impl RngProvider for RandomSeeds {
+    fn next_seed(&self) -> u64 {
+        rand::random()
+    }
+}
+
+/// The same seed for every game, replaying it gives the same result.
+pub struct FixedSeed(pub u64);
+
+impl RngProvider for FixedSeed {
+    fn next_seed(&self) -> u64 {
+        self.0
+    }
+}
+
+pub fn provider(config: &config::Rng) -> Box<dyn RngProvider> {
+    match config.seed {
+        Some(seed) => {
+            tracing::warn!(seed, "Every game uses the same seed");
+            Box::new(FixedSeed(seed))
+        }
+        None => Box::new(RandomSeeds),
+    }
+}
+
+/// The randomness behind a single game, everything it rolls comes from its seed.
+pub struct GameRng {
+    game: &'static str,
+    seed: u64,
+    audit: bool,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(game: &'static str, seed: u64, audit: bool) -> Self {
+        Self {
+            game,
+            seed,
+            audit,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Logs the seed behind `result` when the audit mode is on.
+    pub fn audit(&self, result: impl Display) {
+        if self.audit {
+            tracing::info!(
+                game = self.game,
+                seed = self.seed,
+                result = %result,
+                "Game result"
+            );
+        }
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+impl Data {
+    /// A freshly seeded RNG for one run of `game`.
+    pub fn game_rng(&self, game: &'static str) -> GameRng {
+        GameRng::new(game, self.rng.next_seed(), self.config.rng.audit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn same_seed_same_rolls() {
+        let seed = FixedSeed(42).next_seed();
+        let mut first = GameRng::new("test", seed, false);
+        let mut second = GameRng::new("test", seed, false);
+
+        let first_rolls: Vec<u8> = (0..16).map(|_| first.gen_range(1..=20)).collect();
+        let second_rolls: Vec<u8> = (0..16).map(|_| second.gen_range(1..=20)).collect();
+        assert_eq!(first_rolls, second_rolls);
+    }
+}
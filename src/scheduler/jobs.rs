@@ -0,0 +1,63 @@
+use poise::serenity_prelude::Context as SerenityContext;
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteExecutor;
+
+use crate::{Data, Result};
+
+/// Everything that can be scheduled, stored as JSON so jobs can carry their own arguments.
+///
+/// Only the cleanups and reminders run through here so far, a feature that has to do something
+/// later gets a variant of its own.
+///
+/// Renaming a variant or one of its fields breaks the tasks already in the database.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Job {
+    /// Deletes the cooldowns that are over.
+    PruneCooldowns,
+    /// Folds the simple command uses too old for `/commands stats`.
+    PruneCommandUsage,
+    /// Delivers a `/remind`, unless it was cancelled since.
+    Reminder { reminder_id: i64 },
+}
+
+impl Job {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Job::PruneCooldowns => "prune_cooldowns",
+            Job::PruneCommandUsage => "prune_command_usage",
+            Job::Reminder { .. } => "reminder",
+        }
+    }
+
+    pub async fn run(&self, ctx: &SerenityContext, data: &Data) -> Result<()> {
+        match self {
+            Job::PruneCooldowns => {
+                let pruned = data.cooldowns.prune().await?;
+                tracing::debug!(pruned, "Pruned expired cooldowns");
+            }
+            Job::PruneCommandUsage => {
+                let mut conn = data.database.acquire().await?;
+                let pruned = crate::commands::prune_old_usage(&mut conn).await?;
+                tracing::debug!(pruned, "Pruned old simple command uses");
+            }
+            Job::Reminder { reminder_id } => {
+                crate::commands::deliver_reminder(ctx, &data.database, *reminder_id).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Cleans up after a job that kept failing and won't be run again.
+    pub async fn give_up(&self, executor: impl SqliteExecutor<'_>) -> Result<()> {
+        match self {
+            Job::PruneCooldowns | Job::PruneCommandUsage => {}
+            Job::Reminder { reminder_id } => {
+                crate::commands::forget_reminder(executor, *reminder_id).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,414 @@
+//! Work that has to happen later, persisted so it survives a restart.
+
+mod jobs;
+mod schedule;
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use poise::serenity_prelude::Context as SerenityContext;
+use sqlx::{SqliteExecutor, SqlitePool};
+use tokio::sync::Notify;
+
+use crate::{metrics::timed_task, shutdown, Data, Result};
+
+pub use jobs::Job;
+pub use schedule::Schedule;
+
+/// Longest the worker sleeps, tasks enqueued inside a transaction are only noticed then.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// Tasks run at most this many times in a row before they're given up.
+const MAX_ATTEMPTS: i64 = 5;
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+const BATCH_SIZE: i64 = 20;
+
+static WORKER_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// How long to wait before the next attempt, doubling after every failure.
+fn backoff(attempts: i64) -> chrono::Duration {
+    let factor = 2_i32.pow(attempts.clamp(1, 10) as u32 - 1);
+    chrono::Duration::from_std(RETRY_DELAY * factor as u32).unwrap_or_default()
+}
+
+struct TaskRow {
+    id: i64,
+    kind: String,
+    payload: String,
+    recurrence: Option<String>,
+    attempts: i64,
+}
+
+/// Stores the tasks and runs them once they are due.
+pub struct Scheduler {
+    database: SqlitePool,
+    wake: Notify,
+}
+
+impl Scheduler {
+    pub fn new(database: &SqlitePool) -> Self {
+        Self {
+            database: database.clone(),
+            wake: Notify::new(),
+        }
+    }
+
+    /// Runs `job` once at `run_at` and returns the task ID.
+    ///
+    /// Pass a transaction to only schedule it if everything else succeeds.
+    pub async fn enqueue(
+        &self,
+        executor: impl SqliteExecutor<'_>,
+        job: &Job,
+        run_at: NaiveDateTime,
+    ) -> Result<i64> {
+        let id = insert(executor, job, run_at, None).await?;
+        self.wake.notify_one();
+
+        Ok(id)
+    }
+
+    /// Makes the worker look for due tasks, call it after committing a transaction that
+    /// enqueued some.
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+
+    /// Runs `job` every time `schedule` fires, unless a task of the same kind already does.
+    pub async fn ensure_recurring(&self, job: &Job, schedule: &Schedule) -> Result<()> {
+        let kind = job.kind();
+        let existing = sqlx::query_scalar!(
+            "SELECT id FROM ScheduledTasks WHERE kind = ? AND recurrence IS NOT NULL",
+            kind
+        )
+        .fetch_optional(&self.database)
+        .await?;
+        if existing.is_some() {
+            return Ok(());
+        }
+
+        let Some(run_at) = schedule.next_after(Utc::now().naive_utc()) else {
+            anyhow::bail!("The schedule {schedule} never fires");
+        };
+        insert(&self.database, job, run_at, Some(schedule)).await?;
+        self.wake.notify_one();
+
+        Ok(())
+    }
+
+    /// Removes a task before it runs. Returns whether there was one.
+    pub async fn cancel(&self, executor: impl SqliteExecutor<'_>, id: i64) -> Result<bool> {
+        let result = sqlx::query!("DELETE FROM ScheduledTasks WHERE id = ?", id)
+            .execute(executor)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Runs due tasks until the bot shuts down, only the first call does anything.
+    pub async fn run_worker(&self, ctx: &SerenityContext, data: &Data) {
+        if WORKER_STARTED.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        tracing::info!("Scheduled task worker started");
+        while !shutdown::is_shutting_down() {
+            let now = Utc::now().naive_utc();
+            let run = |job: Job| async move { job.run(ctx, data).await };
+            if let Err(e) = timed_task("run_scheduled_tasks", self.run_due(now, run)).await {
+                tracing::error!("Could not run the scheduled tasks: {e:#}");
+            }
+
+            let sleep = match self.next_run_at().await {
+                Ok(Some(run_at)) => (run_at - Utc::now().naive_utc())
+                    .to_std()
+                    .unwrap_or_default()
+                    .min(POLL_INTERVAL),
+                Ok(None) => POLL_INTERVAL,
+                Err(e) => {
+                    tracing::error!("Could not find the next scheduled task: {e:#}");
+                    POLL_INTERVAL
+                }
+            };
+
+            tokio::select! {
+                _ = tokio::time::sleep(sleep) => {}
+                _ = self.wake.notified() => {}
+            }
+        }
+    }
+
+    async fn next_run_at(&self) -> Result<Option<NaiveDateTime>> {
+        let run_at = sqlx::query_scalar!(
+            r#"SELECT run_at as "run_at: NaiveDateTime" FROM ScheduledTasks ORDER BY run_at LIMIT 1"#
+        )
+        .fetch_optional(&self.database)
+        .await?;
+
+        Ok(run_at)
+    }
+
+    /// Runs every task due at `now`, then reschedules, retries or removes it. Returns how many ran.
+    async fn run_due<F, Fut>(&self, now: NaiveDateTime, run: F) -> Result<usize>
+    where
+        F: Fn(Job) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let tasks = sqlx::query_as!(
+            TaskRow,
+            r#"SELECT id, kind, payload, recurrence, attempts FROM ScheduledTasks
+            WHERE run_at <= ? ORDER BY run_at LIMIT ?"#,
+            now,
+            BATCH_SIZE
+        )
+        .fetch_all(&self.database)
+        .await?;
+
+        let count = tasks.len();
+        for task in tasks {
+            let parsed = serde_json::from_str::<Job>(&task.payload)
+                .map_err(anyhow::Error::from)
+                .and_then(|job| {
+                    let schedule = task.recurrence.as_deref().map(str::parse).transpose()?;
+                    Ok((job, schedule))
+                });
+            let (job, schedule): (Job, Option<Schedule>) = match parsed {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    tracing::error!(
+                        task = task.id,
+                        kind = task.kind,
+                        "Dropping an invalid task: {e:#}"
+                    );
+                    self.cancel(&self.database, task.id).await?;
+                    continue;
+                }
+            };
+
+            let next_occurrence = schedule.and_then(|s| s.next_after(now));
+            let error = match run(job.clone()).await {
+                Ok(()) => {
+                    match next_occurrence {
+                        Some(run_at) => self.reschedule(task.id, run_at, 0, None).await?,
+                        None => {
+                            self.cancel(&self.database, task.id).await?;
+                        }
+                    }
+                    continue;
+                }
+                Err(e) => format!("{e:#}"),
+            };
+
+            let attempts = task.attempts + 1;
+            if attempts < MAX_ATTEMPTS {
+                tracing::warn!(
+                    task = task.id,
+                    kind = task.kind,
+                    attempts,
+                    "Scheduled task failed, retrying: {error}"
+                );
+                let run_at = now + backoff(attempts);
+                self.reschedule(task.id, run_at, attempts, Some(&error))
+                    .await?;
+            } else if let Some(run_at) = next_occurrence {
+                tracing::error!(
+                    task = task.id,
+                    kind = task.kind,
+                    "Scheduled task keeps failing, skipping to its next run: {error}"
+                );
+                self.reschedule(task.id, run_at, 0, Some(&error)).await?;
+            } else {
+                tracing::error!(
+                    task = task.id,
+                    kind = task.kind,
+                    "Scheduled task keeps failing, giving up: {error}"
+                );
+                let mut transaction = self.database.begin().await?;
+                job.give_up(&mut *transaction).await?;
+                self.cancel(&mut *transaction, task.id).await?;
+                transaction.commit().await?;
+            }
+        }
+
+        Ok(count)
+    }
+
+    async fn reschedule(
+        &self,
+        id: i64,
+        run_at: NaiveDateTime,
+        attempts: i64,
+        error: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query!(
+            "UPDATE ScheduledTasks SET run_at = ?, attempts = ?, last_error = ? WHERE id = ?",
+            run_at,
+            attempts,
+            error,
+            id
+        )
+        .execute(&self.database)
+        .await?;
+
+        Ok(())
+    }
+}
+
+async fn insert(
+    executor: impl SqliteExecutor<'_>,
+    job: &Job,
+    run_at: NaiveDateTime,
+    recurrence: Option<&Schedule>,
+) -> Result<i64> {
+    let kind = job.kind();
+    let payload = serde_json::to_string(job)?;
+    let recurrence = recurrence.map(ToString::to_string);
+
+    let result = sqlx::query!(
+        "INSERT INTO ScheduledTasks (kind, payload, run_at, recurrence) VALUES (?, ?, ?, ?)",
+        kind,
+        payload,
+        run_at,
+        recurrence
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.last_insert_rowid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    struct Task {
+        run_at: NaiveDateTime,
+        attempts: i64,
+        last_error: Option<String>,
+    }
+
+    async fn task(database: &SqlitePool, id: i64) -> Option<Task> {
+        sqlx::query_as!(
+            Task,
+            r#"SELECT run_at as "run_at: NaiveDateTime", attempts, last_error
+            FROM ScheduledTasks WHERE id = ?"#,
+            id
+        )
+        .fetch_optional(database)
+        .await
+        .unwrap()
+    }
+
+    fn now() -> NaiveDateTime {
+        Utc::now().naive_utc()
+    }
+
+    #[tokio::test]
+    async fn tasks_run_once_when_due() {
+        let database = testing::database().await;
+        let scheduler = Scheduler::new(&database);
+        let later = now() + chrono::Duration::hours(1);
+        let id = scheduler
+            .enqueue(&database, &Job::PruneCooldowns, later)
+            .await
+            .unwrap();
+
+        let ran = scheduler.run_due(now(), |_| async { Ok(()) }).await;
+        assert_eq!(ran.unwrap(), 0);
+
+        let ran = scheduler.run_due(later, |_| async { Ok(()) }).await;
+        assert_eq!(ran.unwrap(), 1);
+        assert!(task(&database, id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn failed_tasks_are_retried_with_backoff() {
+        let database = testing::database().await;
+        let scheduler = Scheduler::new(&database);
+        let start = now();
+        let id = scheduler
+            .enqueue(&database, &Job::PruneCooldowns, start)
+            .await
+            .unwrap();
+
+        let fail = |_| async { Err(anyhow::anyhow!("Discord is down")) };
+        scheduler.run_due(start, fail).await.unwrap();
+        let retry = task(&database, id).await.unwrap();
+        assert_eq!(retry.attempts, 1);
+        assert_eq!(retry.run_at, start + chrono::Duration::seconds(30));
+        assert_eq!(retry.last_error.as_deref(), Some("Discord is down"));
+
+        scheduler.run_due(retry.run_at, fail).await.unwrap();
+        let retry = task(&database, id).await.unwrap();
+        assert_eq!(retry.attempts, 2);
+        assert_eq!(retry.run_at, start + chrono::Duration::seconds(90));
+
+        for _ in 2..MAX_ATTEMPTS {
+            let run_at = task(&database, id).await.unwrap().run_at;
+            scheduler.run_due(run_at, fail).await.unwrap();
+        }
+        assert!(task(&database, id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn reminders_that_keep_failing_are_deleted() {
+        let database = testing::database().await;
+        let scheduler = Scheduler::new(&database);
+        let start = now();
+        let reminder_id = sqlx::query_scalar!(
+            r#"INSERT INTO Reminders (user_id, channel_id, message, remind_at, by_dm)
+            VALUES (1, 2, 'Feed the dinos', ?, FALSE) RETURNING id as "id!""#,
+            start
+        )
+        .fetch_one(&database)
+        .await
+        .unwrap();
+        let id = scheduler
+            .enqueue(&database, &Job::Reminder { reminder_id }, start)
+            .await
+            .unwrap();
+
+        let fail = |_| async { Err(anyhow::anyhow!("Discord is down")) };
+        for _ in 0..MAX_ATTEMPTS {
+            let run_at = task(&database, id).await.unwrap().run_at;
+            scheduler.run_due(run_at, fail).await.unwrap();
+        }
+
+        assert!(task(&database, id).await.is_none());
+        let reminder = sqlx::query!("SELECT id FROM Reminders WHERE id = ?", reminder_id)
+            .fetch_optional(&database)
+            .await
+            .unwrap();
+        assert!(reminder.is_none(), "Expected the reminder to be deleted");
+    }
+
+    #[tokio::test]
+    async fn recurring_tasks_move_to_their_next_run() {
+        let database = testing::database().await;
+        let scheduler = Scheduler::new(&database);
+        let schedule: Schedule = "@hourly".parse().unwrap();
+        scheduler
+            .ensure_recurring(&Job::PruneCooldowns, &schedule)
+            .await
+            .unwrap();
+        // Already scheduled, so it isn't added twice
+        scheduler
+            .ensure_recurring(&Job::PruneCooldowns, &schedule)
+            .await
+            .unwrap();
+
+        let first_run = scheduler.next_run_at().await.unwrap().unwrap();
+        let ran = scheduler.run_due(first_run, |_| async { Ok(()) }).await;
+        assert_eq!(ran.unwrap(), 1);
+
+        let second_run = scheduler.next_run_at().await.unwrap().unwrap();
+        assert_eq!(second_run, first_run + chrono::Duration::hours(1));
+        let count = sqlx::query_scalar!(r#"SELECT COUNT(*) as "count: i64" FROM ScheduledTasks"#)
+            .fetch_one(&database)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,222 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{bail, ensure, Context as AnyhowContext};
+use chrono::{Datelike, NaiveDateTime, Timelike};
+
+use crate::Error;
+
+/// How far ahead to look for the next occurrence, long enough to reach a 29th of February.
+const SEARCH_DAYS: usize = 8 * 366;
+
+/// A cron-like schedule, in UTC.
+///
+/// The five fields are `minute hour day-of-month month day-of-week`, each one either `*`, a
+/// number, a range `1-5`, a step `*/15` or `10-30/5`, or a list of those separated by commas.
+/// Sunday is 0 or 7. Like cron, a day matches if either the day of the month or the day of the
+/// week does when both are restricted. `@hourly`, `@daily` and `@weekly` are shortcuts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Schedule {
+    /// The first time the schedule fires strictly after `after`.
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + chrono::Duration::minutes(1);
+
+        let mut date = start.date();
+        for _ in 0..SEARCH_DAYS {
+            if self.matches_day(date) {
+                let (first_hour, first_minute) = match date == start.date() {
+                    true => (start.hour(), start.minute()),
+                    false => (0, 0),
+                };
+
+                for hour in (first_hour..24).filter(|h| is_set(self.hours, *h)) {
+                    let from = if hour == first_hour { first_minute } else { 0 };
+                    if let Some(minute) = (from..60).find(|m| is_set(self.minutes, *m)) {
+                        return date.and_hms_opt(hour, minute, 0);
+                    }
+                }
+            }
+
+            date = date.succ_opt()?;
+        }
+
+        None
+    }
+
+    fn matches_day(&self, date: chrono::NaiveDate) -> bool {
+        if !is_set(self.months, date.month()) {
+            return false;
+        }
+
+        let day = is_set(self.days, date.day());
+        let weekday = is_set(self.weekdays, date.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+}
+
+fn is_set(field: u64, value: u32) -> bool {
+    field & (1 << value) != 0
+}
+
+/// Parses one field into a bit set of the values it matches.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, Error> {
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, Some(step.parse::<u32>()?)),
+            None => (part, None),
+        };
+
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (start.parse()?, end.parse()?),
+            // `5/10` runs from 5 to the end, like cron
+            None if step.is_some() => (range.parse()?, max),
+            None => {
+                let value = range.parse()?;
+                (value, value)
+            }
+        };
+
+        ensure!(
+            min <= start && start <= end && end <= max,
+            "{part} is outside of {min}-{max}"
+        );
+        let step = step.unwrap_or(1);
+        ensure!(step > 0, "{part} has a step of 0");
+
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+impl FromStr for Schedule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expanded = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            other => other,
+        };
+
+        let fields = expanded.split_whitespace().collect::<Vec<_>>();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            bail!(
+                "Expected 5 fields in the schedule {s:?}, got {}",
+                fields.len()
+            );
+        };
+
+        let context = |name: &str| format!("Invalid {name} in the schedule {s:?}");
+        let mut weekdays_bits = parse_field(weekdays, 0, 7).with_context(|| context("weekday"))?;
+        // 7 is another Sunday
+        if is_set(weekdays_bits, 7) {
+            weekdays_bits |= 1;
+        }
+
+        Ok(Self {
+            source: s.trim().to_string(),
+            minutes: parse_field(minutes, 0, 59).with_context(|| context("minute"))?,
+            hours: parse_field(hours, 0, 23).with_context(|| context("hour"))?,
+            days: parse_field(days, 1, 31).with_context(|| context("day"))?,
+            months: parse_field(months, 1, 12).with_context(|| context("month"))?,
+            weekdays: weekdays_bits,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+}
+
+impl Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // 2024-05-02 is a Thursday
+        NaiveDate::from_ymd_opt(2024, 5, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn next(schedule: &str, after: NaiveDateTime) -> NaiveDateTime {
+        schedule
+            .parse::<Schedule>()
+            .unwrap()
+            .next_after(after)
+            .unwrap()
+    }
+
+    #[test]
+    fn schedules_fire_on_the_next_match() {
+        assert_eq!(next("30 2 * * *", at(2, 18, 0)), at(3, 2, 30));
+        assert_eq!(next("*/15 * * * *", at(2, 10, 7)), at(2, 10, 15));
+        assert_eq!(next("*/15 * * * *", at(2, 10, 15)), at(2, 10, 30));
+        assert_eq!(next("0 9 * * 1", at(2, 12, 0)), at(6, 9, 0));
+        assert_eq!(next("0 9 * * 7", at(2, 12, 0)), at(5, 9, 0));
+        assert_eq!(next("0 0 1,15 * *", at(2, 12, 0)), at(15, 0, 0));
+        // Either the day of the month or of the week, like cron
+        assert_eq!(next("0 0 15 * 6", at(2, 12, 0)), at(4, 0, 0));
+        assert_eq!(next("@daily", at(2, 12, 0)), at(3, 0, 0));
+        assert_eq!(next("@hourly", at(2, 12, 0)), at(2, 13, 0));
+    }
+
+    #[test]
+    fn rare_dates_are_found() {
+        let leap_day = NaiveDate::from_ymd_opt(2028, 2, 29)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        assert_eq!(next("0 0 29 2 *", at(2, 12, 0)), leap_day);
+        assert!("0 0 31 2 *"
+            .parse::<Schedule>()
+            .unwrap()
+            .next_after(at(2, 12, 0))
+            .is_none());
+    }
+
+    #[test]
+    fn invalid_schedules_are_rejected() {
+        for schedule in [
+            "",
+            "* * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "*/0 * * * *",
+            "a * * * *",
+        ] {
+            assert!(
+                schedule.parse::<Schedule>().is_err(),
+                "Expected {schedule:?} to be rejected"
+            );
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use anyhow::bail;
+use poise::serenity_prelude::{ChannelId, GuildId, Mention, RoleId};
+use poise::ChoiceParameter;
+use sqlx::SqlitePool;
+
+use crate::audit::{self, Event};
+use crate::{Data, Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, poise::ChoiceParameter)]
+pub enum Setting {
+    #[name = "prefix"]
+    Prefix,
+    #[name = "sub_role"]
+    SubRole,
+    #[name = "embed_role"]
+    EmbedRole,
+    #[name = "color_anchor_role"]
+    ColorAnchorRole,
+    #[name = "mod_log_channel"]
+    ModLogChannel,
+}
+
+enum SettingKind {
+    Role,
+    Channel,
+    Text,
+}
+
+impl Setting {
+    fn kind(self) -> SettingKind {
+        match self {
+            Setting::Prefix => SettingKind::Text,
+            Setting::SubRole | Setting::EmbedRole | Setting::ColorAnchorRole => SettingKind::Role,
+            Setting::ModLogChannel => SettingKind::Channel,
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Setting::Prefix => "Prefix used for text commands",
+            Setting::SubRole => {
+                "Role allowed to change colors, gets a better chance to hatch dinos"
+            }
+            Setting::EmbedRole => "Role toggled by /embedpls",
+            Setting::ColorAnchorRole => {
+                "Color roles are created above this role, defaults to the sub role"
+            }
+            Setting::ModLogChannel => "Channel where command errors are reported",
+        }
+    }
+
+    /// Validate and normalize what a user typed into what gets stored.
+    fn parse_value(self, value: &str) -> Result<String> {
+        let value = value.trim();
+
+        match self.kind() {
+            SettingKind::Text if value.is_empty() || value.contains(char::is_whitespace) => {
+                bail!("The {} must be a single word.", self.name())
+            }
+            SettingKind::Text => Ok(value.to_string()),
+            SettingKind::Role => match parse_id(value, "<@&") {
+                Some(id) => Ok(id.to_string()),
+                None => bail!("Please provide a role mention or a role ID."),
+            },
+            SettingKind::Channel => match parse_id(value, "<#") {
+                Some(id) => Ok(id.to_string()),
+                None => bail!("Please provide a channel mention or a channel ID."),
+            },
+        }
+    }
+
+    fn display_value(self, value: &str) -> String {
+        match (self.kind(), value.parse::<u64>()) {
+            (SettingKind::Role, Ok(id)) if id != 0 => Mention::from(RoleId::new(id)).to_string(),
+            (SettingKind::Channel, Ok(id)) if id != 0 => {
+                Mention::from(ChannelId::new(id)).to_string()
+            }
+            _ => format!("`{value}`"),
+        }
+    }
+}
+
+impl Display for Setting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+fn parse_id(value: &str, mention_prefix: &str) -> Option<u64> {
+    let id = value
+        .strip_prefix(mention_prefix)
+        .and_then(|v| v.strip_suffix('>'))
+        .unwrap_or(value);
+
+    id.parse::<u64>().ok().filter(|id| *id != 0)
+}
+
+pub type GuildSettings = HashMap<i64, HashMap<Setting, String>>;
+
+struct SettingRow {
+    guild_id: i64,
+    key: String,
+    value: String,
+}
+
+pub async fn fetch_guild_settings(db: &SqlitePool) -> Result<GuildSettings> {
+    let rows = sqlx::query_as!(SettingRow, "SELECT guild_id, key, value FROM GuildSettings")
+        .fetch_all(db)
+        .await?;
+
+    let mut settings: GuildSettings = HashMap::new();
+    for row in rows {
+        let Some(setting) = Setting::from_name(&row.key) else {
+            tracing::warn!(
+                guild = row.guild_id,
+                "Ignoring unknown guild setting '{}'",
+                row.key
+            );
+            continue;
+        };
+
+        settings
+            .entry(row.guild_id)
+            .or_default()
+            .insert(setting, row.value);
+    }
+
+    Ok(settings)
+}
+
+impl Data {
+    pub async fn guild_setting(&self, guild_id: GuildId, setting: Setting) -> Option<String> {
+        self.guild_settings
+            .read()
+            .await
+            .get(&(guild_id.get() as i64))?
+            .get(&setting)
+            .cloned()
+    }
+
+    pub async fn guild_role(&self, guild_id: GuildId, setting: Setting) -> Option<RoleId> {
+        self.guild_setting(guild_id, setting)
+            .await?
+            .parse::<u64>()
+            .ok()
+            .map(RoleId::new)
+    }
+
+    pub async fn guild_channel(&self, guild_id: GuildId, setting: Setting) -> Option<ChannelId> {
+        self.guild_setting(guild_id, setting)
+            .await?
+            .parse::<u64>()
+            .ok()
+            .map(ChannelId::new)
+    }
+
+    /// Validates the value, saves it and returns how it should be displayed.
+    pub async fn set_guild_setting(
+        &self,
+        guild_id: GuildId,
+        setting: Setting,
+        value: &str,
+        event: Option<&Event>,
+    ) -> Result<String> {
+        let value = setting.parse_value(value)?;
+        let guild_id = guild_id.get() as i64;
+        let key = setting.name();
+
+        let mut settings = self.guild_settings.write().await;
+        let mut transaction = self.database.begin().await?;
+        sqlx::query!(
+            r#"INSERT INTO GuildSettings (guild_id, key, value) VALUES (?, ?, ?)
+            ON CONFLICT(guild_id, key) DO UPDATE SET value = excluded.value"#,
+            guild_id,
+            key,
+            value
+        )
+        .execute(&mut *transaction)
+        .await?;
+        if let Some(event) = event {
+            audit::record(&mut *transaction, event).await?;
+        }
+        transaction.commit().await?;
+
+        let display = setting.display_value(&value);
+        settings.entry(guild_id).or_default().insert(setting, value);
+
+        Ok(display)
+    }
+
+    pub async fn unset_guild_setting(
+        &self,
+        guild_id: GuildId,
+        setting: Setting,
+        event: Option<&Event>,
+    ) -> Result<()> {
+        let guild_id = guild_id.get() as i64;
+        let key = setting.name();
+
+        let mut settings = self.guild_settings.write().await;
+        let mut transaction = self.database.begin().await?;
+        let result = sqlx::query!(
+            "DELETE FROM GuildSettings WHERE guild_id = ? AND key = ?",
+            guild_id,
+            key
+        )
+        .execute(&mut *transaction)
+        .await?;
+        // Nothing changed when it wasn't set
+        if result.rows_affected() == 0 {
+            return Ok(());
+        }
+        if let Some(event) = event {
+            audit::record(&mut *transaction, event).await?;
+        }
+        transaction.commit().await?;
+
+        if let Some(guild_settings) = settings.get_mut(&guild_id) {
+            guild_settings.remove(&setting);
+        }
+
+        Ok(())
+    }
+}
+
+/// Lists every setting with its current value, or `not set`.
+pub async fn describe_guild_settings(data: &Data, guild_id: GuildId) -> Vec<(String, String)> {
+    let settings = data.guild_settings.read().await;
+    let guild_settings = settings.get(&(guild_id.get() as i64));
+
+    Setting::list()
+        .into_iter()
+        .filter_map(|choice| Setting::from_name(&choice.name))
+        .map(|setting| {
+            let value = guild_settings
+                .and_then(|s| s.get(&setting))
+                .map(|v| setting.display_value(v))
+                .unwrap_or_else(|| "not set".to_string());
+
+            (
+                setting.to_string(),
+                format!("{value}\n*{}*", setting.description()),
+            )
+        })
+        .collect()
+}
+
+pub fn describe_setting(setting: Setting, value: Option<&str>) -> String {
+    match value {
+        Some(value) => format!("**{setting}** is set to {}", setting.display_value(value)),
+        None => format!("**{setting}** is not set."),
+    }
+}
+
+pub async fn dynamic_prefix(ctx: poise::PartialContext<'_, Data, Error>) -> Result<Option<String>> {
+    let prefix = match ctx.guild_id {
+        Some(guild_id) => ctx.data.guild_setting(guild_id, Setting::Prefix).await,
+        None => None,
+    };
+
+    Ok(Some(
+        prefix.unwrap_or_else(|| ctx.data.config.prefix.clone()),
+    ))
+}
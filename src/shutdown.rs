@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use poise::serenity_prelude::{ChannelId, EditMessage, Http, MessageId, ShardManager};
+
+use crate::{common::ephemeral_reply, Context, Result};
+
+const CANCELLED_CHALLENGE_MESSAGE: &str = "Bot restarting, challenge cancelled.";
+pub const RESTARTING_MESSAGE: &str = "The bot is restarting, try again in a minute.";
+
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+static PENDING_CHALLENGES: OnceLock<Mutex<HashMap<MessageId, ChannelId>>> = OnceLock::new();
+
+fn pending_challenges() -> &'static Mutex<HashMap<MessageId, ChannelId>> {
+    PENDING_CHALLENGES.get_or_init(Mutex::default)
+}
+
+pub fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::Acquire)
+}
+
+/// A challenge message still waiting for an opponent, it gets cancelled if the bot shuts down
+/// before the guard is dropped.
+#[cfg(any(feature = "duel", feature = "rpg"))]
+pub struct PendingChallenge(MessageId);
+
+#[cfg(any(feature = "duel", feature = "rpg"))]
+impl PendingChallenge {
+    pub fn track(channel_id: ChannelId, message_id: MessageId) -> Self {
+        pending_challenges()
+            .lock()
+            .unwrap()
+            .insert(message_id, channel_id);
+
+        Self(message_id)
+    }
+}
+
+#[cfg(any(feature = "duel", feature = "rpg"))]
+impl Drop for PendingChallenge {
+    fn drop(&mut self) {
+        pending_challenges().lock().unwrap().remove(&self.0);
+    }
+}
+
+/// Refuses every command once the bot started shutting down.
+pub async fn reject_during_shutdown(ctx: Context<'_>) -> Result<bool> {
+    if !is_shutting_down() {
+        return Ok(true);
+    }
+
+    ctx.send(ephemeral_reply(RESTARTING_MESSAGE)).await?;
+
+    Ok(false)
+}
+
+/// Waits for SIGINT or SIGTERM, then cancels the pending challenges and stops the shards.
+pub async fn shutdown_on_signal(http: Arc<Http>, shard_manager: Arc<ShardManager>) {
+    wait_for_signal().await;
+
+    tracing::info!("Shutting down");
+    SHUTTING_DOWN.store(true, Ordering::Release);
+
+    let pending: Vec<_> = pending_challenges()
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(message_id, channel_id)| (*message_id, *channel_id))
+        .collect();
+
+    for (message_id, channel_id) in pending {
+        let edit = EditMessage::new()
+            .content(CANCELLED_CHALLENGE_MESSAGE)
+            .components(Vec::new());
+
+        if let Err(e) = channel_id.edit_message(&http, message_id, edit).await {
+            tracing::warn!(
+                message = message_id.get(),
+                "Could not cancel a pending challenge: {e}"
+            );
+        }
+    }
+
+    shard_manager.shutdown_all().await;
+}
+
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = sigterm.recv() => {}
+                }
+                return;
+            }
+            Err(e) => tracing::warn!("Could not listen for SIGTERM: {e}"),
+        }
+    }
+
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        tracing::error!("Could not listen for SIGINT, graceful shutdown is disabled: {e}");
+        std::future::pending::<()>().await;
+    }
+}
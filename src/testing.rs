@@ -0,0 +1,182 @@
+//! Fixtures for running the game logic without a Discord connection.
+
+#[cfg(feature = "duel")]
+use std::collections::VecDeque;
+use std::str::FromStr;
+#[cfg(any(feature = "duel", feature = "poll"))]
+use std::sync::Mutex;
+#[cfg(feature = "duel")]
+use std::time::Duration;
+
+#[cfg(feature = "duel")]
+use chrono::{DateTime, Utc};
+#[cfg(any(feature = "duel", feature = "poll"))]
+use poise::serenity_prelude::MessageId;
+#[cfg(feature = "duel")]
+use poise::serenity_prelude::{InteractionId, UserId};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+
+#[cfg(feature = "duel")]
+use crate::messaging::Click;
+#[cfg(any(feature = "duel", feature = "poll"))]
+use crate::messaging::{Messenger, Reply};
+#[cfg(any(feature = "duel", feature = "poll"))]
+use crate::Result;
+use crate::{config, cooldowns::Cooldowns, migrations};
+
+/// An empty in-memory database with every migration applied.
+pub async fn database() -> SqlitePool {
+    let database = empty_database().await;
+    migrations::run_migrations(&database)
+        .await
+        .expect("Expected the migrations to apply to an empty database");
+
+    database
+}
+
+/// An in-memory database without any table.
+pub async fn empty_database() -> SqlitePool {
+    // Every connection to `:memory:` gets its own database, so there must only be one
+    SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(SqliteConnectOptions::from_str("sqlite::memory:").unwrap())
+        .await
+        .expect("Expected an in-memory database to open")
+}
+
+/// Cooldowns with the default durations.
+pub async fn cooldowns(database: &SqlitePool) -> Cooldowns {
+    Cooldowns::new(database, &config::Cooldowns::default())
+        .await
+        .expect("Expected the cooldowns to load")
+}
+
+/// Something the code under test did through the [`FakeMessenger`].
+#[cfg(any(feature = "duel", feature = "poll"))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Sent(MessageId, Reply),
+    Edited(MessageId, Reply),
+    #[cfg(feature = "duel")]
+    Answered(UserId, Reply),
+    #[cfg(feature = "duel")]
+    TimedOut(UserId),
+    #[cfg(feature = "poll")]
+    Reacted(MessageId, String),
+    #[cfg(feature = "poll")]
+    Unreacted(MessageId, String),
+}
+
+/// Records every message and plays back button presses queued with [`FakeMessenger::click`].
+#[cfg(any(feature = "duel", feature = "poll"))]
+#[derive(Default)]
+pub struct FakeMessenger {
+    events: Mutex<Vec<Event>>,
+    #[cfg(feature = "duel")]
+    clicks: Mutex<VecDeque<Click>>,
+    /// The bot's reactions, with how many reacted with the same emoji.
+    #[cfg(feature = "poll")]
+    reactions: Mutex<Vec<(MessageId, String, u64)>>,
+    next_id: Mutex<u64>,
+}
+
+#[cfg(any(feature = "duel", feature = "poll"))]
+impl FakeMessenger {
+    /// Queues a button press, they are handed out in order whatever the message or button.
+    #[cfg(feature = "duel")]
+    pub fn click(&self, user_id: u64, user_name: &str) {
+        let id = InteractionId::new(self.next_id());
+        self.clicks.lock().unwrap().push_back(Click {
+            id,
+            user_id: UserId::new(user_id),
+            user_name: user_name.to_string(),
+        });
+    }
+
+    /// Someone else reacts with an emoji the bot reacted with.
+    #[cfg(feature = "poll")]
+    pub fn vote(&self, message_id: MessageId, emoji: &str) {
+        let mut reactions = self.reactions.lock().unwrap();
+        let (_, _, count) = reactions
+            .iter_mut()
+            .find(|(id, e, _)| *id == message_id && e == emoji)
+            .expect("Expected the bot to have reacted with the emoji");
+        *count += 1;
+    }
+
+    pub fn events(&self) -> Vec<Event> {
+        self.events.lock().unwrap().clone()
+    }
+
+    fn next_id(&self) -> u64 {
+        let mut next_id = self.next_id.lock().unwrap();
+        *next_id += 1;
+        *next_id
+    }
+
+    fn record(&self, event: Event) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+#[cfg(any(feature = "duel", feature = "poll"))]
+impl Messenger for FakeMessenger {
+    async fn send(&self, reply: Reply) -> Result<MessageId> {
+        let message_id = MessageId::new(self.next_id());
+        self.record(Event::Sent(message_id, reply));
+        Ok(message_id)
+    }
+
+    async fn edit(&self, message_id: MessageId, reply: Reply) -> Result<()> {
+        self.record(Event::Edited(message_id, reply));
+        Ok(())
+    }
+
+    #[cfg(feature = "poll")]
+    async fn react(&self, message_id: MessageId, emoji: &str) -> Result<()> {
+        let reaction = (message_id, emoji.to_string(), 1);
+        self.reactions.lock().unwrap().push(reaction);
+        self.record(Event::Reacted(message_id, emoji.to_string()));
+        Ok(())
+    }
+
+    #[cfg(feature = "poll")]
+    async fn remove_reactions(&self, message_id: MessageId, emoji: &str) -> Result<()> {
+        let mut reactions = self.reactions.lock().unwrap();
+        reactions.retain(|(id, e, _)| *id != message_id || e != emoji);
+        drop(reactions);
+        self.record(Event::Unreacted(message_id, emoji.to_string()));
+        Ok(())
+    }
+
+    #[cfg(feature = "poll")]
+    async fn own_reactions(&self, message_id: MessageId) -> Result<Vec<(String, u64)>> {
+        let reactions = self.reactions.lock().unwrap();
+        let own = reactions
+            .iter()
+            .filter(|(id, _, _)| *id == message_id)
+            .map(|(_, emoji, count)| (emoji.clone(), *count))
+            .collect();
+        Ok(own)
+    }
+
+    #[cfg(feature = "duel")]
+    async fn next_click(&self, _: MessageId, _: &str, _: Duration) -> Option<Click> {
+        self.clicks.lock().unwrap().pop_front()
+    }
+
+    #[cfg(feature = "duel")]
+    async fn answer(&self, click: &Click, reply: Reply) -> Result<()> {
+        self.record(Event::Answered(click.user_id, reply));
+        Ok(())
+    }
+
+    #[cfg(feature = "duel")]
+    async fn timeout_member(&self, user_id: UserId, _: DateTime<Utc>) -> Result<()> {
+        self.record(Event::TimedOut(user_id));
+        Ok(())
+    }
+}
@@ -0,0 +1,349 @@
+use std::fmt::Display;
+
+use anyhow::ensure;
+#[cfg(any(feature = "wallet", test))]
+use chrono::NaiveDateTime;
+use poise::serenity_prelude::UserId;
+#[cfg(any(feature = "profile", feature = "wallet", test))]
+use sqlx::SqliteExecutor;
+use sqlx::{Connection, SqliteConnection};
+
+use crate::{metrics::timed_query, Result};
+
+pub const CURRENCY: &str = "Dino Bucks";
+
+/// Why money came in or went out of a wallet.
+///
+/// Only the games in the build pay out, but `/wallet history` can read back any of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    #[cfg(any(feature = "duel", feature = "wallet", test))]
+    DuelWin,
+    #[cfg(any(feature = "rpg", feature = "wallet", test))]
+    RpgWin,
+    #[cfg(any(feature = "dino", feature = "wallet", test))]
+    DinoHatch,
+    #[cfg(any(feature = "mixu", feature = "wallet", test))]
+    BestMixu,
+    #[cfg(any(feature = "wallet", test))]
+    Gift,
+}
+
+impl Reason {
+    fn name(self) -> &'static str {
+        match self {
+            #[cfg(any(feature = "duel", feature = "wallet", test))]
+            Reason::DuelWin => "duel_win",
+            #[cfg(any(feature = "rpg", feature = "wallet", test))]
+            Reason::RpgWin => "rpg_win",
+            #[cfg(any(feature = "dino", feature = "wallet", test))]
+            Reason::DinoHatch => "dino_hatch",
+            #[cfg(any(feature = "mixu", feature = "wallet", test))]
+            Reason::BestMixu => "best_mixu",
+            #[cfg(any(feature = "wallet", test))]
+            Reason::Gift => "gift",
+        }
+    }
+
+    #[cfg(any(feature = "wallet", test))]
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "duel_win" => Some(Reason::DuelWin),
+            "rpg_win" => Some(Reason::RpgWin),
+            "dino_hatch" => Some(Reason::DinoHatch),
+            "best_mixu" => Some(Reason::BestMixu),
+            "gift" => Some(Reason::Gift),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            #[cfg(any(feature = "duel", feature = "wallet", test))]
+            Reason::DuelWin => "Won a duel",
+            #[cfg(any(feature = "rpg", feature = "wallet", test))]
+            Reason::RpgWin => "Won an RPG fight",
+            #[cfg(any(feature = "dino", feature = "wallet", test))]
+            Reason::DinoHatch => "Hatched a dino",
+            #[cfg(any(feature = "mixu", feature = "wallet", test))]
+            Reason::BestMixu => "Rolled the best mixu",
+            #[cfg(any(feature = "wallet", test))]
+            Reason::Gift => "Gift",
+        };
+        write!(f, "{text}")
+    }
+}
+
+/// A debit that would leave the wallet below zero.
+#[derive(Debug)]
+pub struct InsufficientFunds {
+    pub balance: i64,
+}
+
+impl Display for InsufficientFunds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "You only have {} {CURRENCY}.", self.balance)
+    }
+}
+
+impl std::error::Error for InsufficientFunds {}
+
+/// A line of the ledger.
+#[cfg(any(feature = "wallet", test))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Negative for debits.
+    pub amount: i64,
+    /// The balance right after this entry.
+    pub balance: i64,
+    pub reason: Reason,
+    pub reference_id: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[cfg(any(feature = "wallet", test))]
+struct EntryRow {
+    amount: i64,
+    balance: i64,
+    reason: String,
+    reference_id: Option<String>,
+    created_at: NaiveDateTime,
+}
+
+#[cfg(any(feature = "profile", feature = "wallet", test))]
+pub async fn balance(executor: impl SqliteExecutor<'_>, user_id: UserId) -> Result<i64> {
+    let user_id = user_id.get() as i64;
+    let balance = sqlx::query_scalar!("SELECT balance FROM Wallet WHERE user_id = ?", user_id)
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(balance.unwrap_or_default())
+}
+
+/// The latest `limit` entries of a user's ledger, newest first.
+#[cfg(any(feature = "wallet", test))]
+pub async fn history(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+    limit: i64,
+) -> Result<Vec<Entry>> {
+    let user_id = user_id.get() as i64;
+    let rows = sqlx::query_as!(
+        EntryRow,
+        r#"SELECT amount, balance, reason, reference_id, created_at as "created_at: NaiveDateTime"
+        FROM Ledger WHERE user_id = ? ORDER BY id DESC LIMIT ?"#,
+        user_id,
+        limit
+    )
+    .fetch_all(executor)
+    .await?;
+
+    let entries = rows
+        .into_iter()
+        .filter_map(|row| {
+            let Some(reason) = Reason::from_name(&row.reason) else {
+                tracing::warn!(
+                    user = user_id,
+                    "Ignoring unknown ledger reason '{}'",
+                    row.reason
+                );
+                return None;
+            };
+
+            Some(Entry {
+                amount: row.amount,
+                balance: row.balance,
+                reason,
+                reference_id: row.reference_id,
+                created_at: row.created_at,
+            })
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+/// Adds `amount` to a wallet and returns the new balance.
+///
+/// Pass a transaction to only keep the money if everything else succeeds.
+pub async fn credit(
+    conn: &mut SqliteConnection,
+    user_id: UserId,
+    amount: i64,
+    reason: Reason,
+    reference_id: Option<&str>,
+) -> Result<i64> {
+    ensure!(
+        amount > 0,
+        "Can only credit a positive amount, not {amount}"
+    );
+
+    let mut transaction = conn.begin().await?;
+    let record = record(&mut transaction, user_id, amount, reason, reference_id);
+    let balance = timed_query("wallet_credit", record).await?;
+    transaction.commit().await?;
+
+    Ok(balance)
+}
+
+/// Takes `amount` out of a wallet and returns the new balance, fails with [`InsufficientFunds`]
+/// instead of going below zero.
+#[cfg(any(feature = "wallet", test))]
+pub async fn debit(
+    conn: &mut SqliteConnection,
+    user_id: UserId,
+    amount: i64,
+    reason: Reason,
+    reference_id: Option<&str>,
+) -> Result<i64> {
+    ensure!(amount > 0, "Can only debit a positive amount, not {amount}");
+
+    let mut transaction = conn.begin().await?;
+    let record = record(&mut transaction, user_id, -amount, reason, reference_id);
+    let balance = timed_query("wallet_debit", record).await?;
+    transaction.commit().await?;
+
+    Ok(balance)
+}
+
+/// Moves `amount` from one wallet to another, either both sides happen or neither does.
+#[cfg(any(feature = "wallet", test))]
+pub async fn transfer(
+    conn: &mut SqliteConnection,
+    from: UserId,
+    to: UserId,
+    amount: i64,
+    reason: Reason,
+    reference_id: Option<&str>,
+) -> Result<()> {
+    ensure!(from != to, "Can't transfer money to the same wallet");
+
+    let mut transaction = conn.begin().await?;
+    debit(&mut transaction, from, amount, reason, reference_id).await?;
+    credit(&mut transaction, to, amount, reason, reference_id).await?;
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+/// Pays a game reward, nothing happens when the payout is set to 0 in the config.
+#[cfg(any(feature = "dino", feature = "duel", feature = "mixu", feature = "rpg"))]
+pub async fn pay_out(
+    conn: &mut SqliteConnection,
+    user_id: UserId,
+    amount: u32,
+    reason: Reason,
+    reference_id: &str,
+) -> Result<()> {
+    if amount > 0 {
+        credit(conn, user_id, amount.into(), reason, Some(reference_id)).await?;
+    }
+
+    Ok(())
+}
+
+async fn record(
+    conn: &mut SqliteConnection,
+    user_id: UserId,
+    amount: i64,
+    reason: Reason,
+    reference_id: Option<&str>,
+) -> Result<i64> {
+    let user_id = user_id.get() as i64;
+    sqlx::query!(
+        "INSERT INTO Wallet (user_id) VALUES (?) ON CONFLICT(user_id) DO NOTHING",
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let balance = sqlx::query_scalar!(
+        r#"UPDATE Wallet SET balance = balance + ? WHERE user_id = ? AND balance + ? >= 0
+        RETURNING balance as "balance!""#,
+        amount,
+        user_id,
+        amount
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(balance) = balance else {
+        let balance = sqlx::query_scalar!("SELECT balance FROM Wallet WHERE user_id = ?", user_id)
+            .fetch_one(&mut *conn)
+            .await?;
+        return Err(InsufficientFunds { balance }.into());
+    };
+
+    let reason = reason.name();
+    sqlx::query!(
+        "INSERT INTO Ledger (user_id, amount, balance, reason, reference_id) VALUES (?, ?, ?, ?, ?)",
+        user_id,
+        amount,
+        balance,
+        reason,
+        reference_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(balance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const ALICE: UserId = UserId::new(1);
+    const BOB: UserId = UserId::new(2);
+
+    #[tokio::test]
+    async fn transfers_move_money_between_wallets() {
+        let database = testing::database().await;
+        let mut conn = database.acquire().await.unwrap();
+
+        credit(&mut conn, ALICE, 50, Reason::DuelWin, Some("10"))
+            .await
+            .unwrap();
+        transfer(&mut conn, ALICE, BOB, 20, Reason::Gift, None)
+            .await
+            .unwrap();
+
+        assert_eq!(balance(&mut *conn, ALICE).await.unwrap(), 30);
+        assert_eq!(balance(&mut *conn, BOB).await.unwrap(), 20);
+
+        let entries = history(&mut *conn, ALICE, 10).await.unwrap();
+        let entries = entries
+            .iter()
+            .map(|e| (e.amount, e.balance, e.reason))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [(-20, 30, Reason::Gift), (50, 50, Reason::DuelWin)]
+        );
+    }
+
+    #[tokio::test]
+    async fn wallets_never_go_below_zero() {
+        let database = testing::database().await;
+        let mut conn = database.acquire().await.unwrap();
+
+        credit(&mut conn, ALICE, 10, Reason::DinoHatch, None)
+            .await
+            .unwrap();
+        let error = transfer(&mut conn, ALICE, BOB, 11, Reason::Gift, None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<InsufficientFunds>().map(|e| e.balance),
+            Some(10)
+        );
+
+        assert_eq!(balance(&mut *conn, ALICE).await.unwrap(), 10);
+        assert_eq!(balance(&mut *conn, BOB).await.unwrap(), 0);
+        assert_eq!(history(&mut *conn, ALICE, 10).await.unwrap().len(), 1);
+        assert!(history(&mut *conn, BOB, 10).await.unwrap().is_empty());
+    }
+}